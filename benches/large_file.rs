@@ -0,0 +1,56 @@
+//! Compares the buffered and the memory-mapped paths for large working tree files.
+//!
+//! Run with `cargo bench --bench large_file`.
+
+use sensleak::detect_utils::load_config_file;
+use sensleak::service::detect_service::detect_large_file;
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+const FILE_SIZE: usize = 256 * 1024 * 1024;
+const ROUNDS: u32 = 5;
+
+fn main() {
+    let scan = load_config_file("gitleaks.toml").expect("run from the crate root");
+
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    let mut written = 0;
+    let mut line_number = 0;
+    while written < FILE_SIZE {
+        line_number += 1;
+        let line = if line_number % 100_000 == 0 {
+            "token = sk_test_1234567890abcdef\n".to_string()
+        } else {
+            format!("{} lorem ipsum dolor sit amet, consectetur adipiscing elit\n", line_number)
+        };
+        file.write_all(line.as_bytes()).unwrap();
+        written += line.len();
+    }
+    file.flush().unwrap();
+
+    for (name, mmap) in [("buffered", false), ("mmap", true)] {
+        let mut total = Duration::ZERO;
+        let mut leaks = 0;
+        for _ in 0..ROUNDS {
+            let start = Instant::now();
+            leaks = detect_large_file(
+                file.path(),
+                "dump.txt",
+                &scan.ruleslist,
+                &scan.allowlist,
+                mmap,
+            )
+            .unwrap()
+            .len();
+            total += start.elapsed();
+        }
+        let average = total / ROUNDS;
+        println!(
+            "{:>8}: {:>8.1?} per file, {:>6.0} MiB/s, {} leaks",
+            name,
+            average,
+            FILE_SIZE as f64 / (1024.0 * 1024.0) / average.as_secs_f64(),
+            leaks
+        );
+    }
+}
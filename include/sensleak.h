@@ -0,0 +1,44 @@
+/*
+ * C interface of the sensleak detection engine.
+ *
+ * All strings are NUL-terminated UTF-8. Every returned string is a JSON object, either the
+ * scan output or {"error": "..."}, and must be released with sensleak_free.
+ */
+#ifndef SENSLEAK_H
+#define SENSLEAK_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Scans `content` with a TOML ruleset. Returns {"leaks": [...]}. */
+char *sensleak_scan_text(const char *content, const char *config);
+
+/*
+ * Scans a repository. `options` is a JSON object with the command line options as fields,
+ * e.g. {"repo": "path/to/repo", "config": "gitleaks.toml"}; missing fields take their default
+ * values. `options` is only read during the call and stays owned by the caller.
+ *
+ * Returns {"commits_number": n, "leaks": [...], "errors": [...]} on success, `errors` listing
+ * the commits, trees and blobs that couldn't be read and were skipped, each as
+ * {"kind": "commit" | "tree" | "blob", "object": "...", "reason": "..."}, with the "commit"
+ * and "path" the object was read for when they are known.
+ *
+ * On failure, returns {"error": "message"} and no other field: "Invalid argument" if `options`
+ * is NULL or not UTF-8, the parse error if it isn't a JSON object of valid options, or the
+ * error of the scan, e.g. when the repository or the config file can't be loaded. Callers
+ * should check for `error` before reading the other fields.
+ *
+ * Never returns NULL. The returned string belongs to the caller, who must release it with
+ * sensleak_free exactly once, and not with free().
+ */
+char *sensleak_scan(const char *options);
+
+/* Releases a string returned by this library. */
+void sensleak_free(char *s);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* SENSLEAK_H */
@@ -2,6 +2,7 @@ use sensleak::start;
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("The API document is located at http://localhost:7000/swagger-ui/#/");
+    println!("The dashboard is located at http://localhost:7000/dashboard");
     start().await?;
     Ok(())
 }
@@ -0,0 +1,374 @@
+use crate::models::{Config, DateField, Dedup, DiskCleanup, RepoConfigAllow, ReportFormat, ReportOutput, ScanTarget};
+use chrono::{DateTime, FixedOffset};
+use std::path::{Path, PathBuf};
+
+/// Builds the `Config` of a scan from typed values, for library users.
+///
+/// Only the last commit selection (`commit`, `commits`, `between`, ...) applies.
+///
+/// ```
+/// use sensleak::{ReportFormat, ScanOptions};
+///
+/// let config = ScanOptions::new("path/to/repo")
+///     .config("gitleaks.toml")
+///     .branch("main")
+///     .report("leaks.sarif", ReportFormat::Sarif)
+///     .build();
+/// assert_eq!(config.report[0].format, Some(ReportFormat::Sarif));
+/// ```
+#[derive(Debug, Clone)]
+pub struct ScanOptions {
+    repo: String,
+    config: Option<PathBuf>,
+    config_sha256: Option<String>,
+    ignore_path_case: bool,
+    follow_symlinks: bool,
+    allowlist_dry_run: bool,
+    suppression_usage: bool,
+    anonymize: bool,
+    repo_config: bool,
+    repo_config_allow: RepoConfigAllow,
+    threads: Option<usize>,
+    chunk: Option<usize>,
+    max_memory_mb: Option<usize>,
+    mmap: bool,
+    report: Vec<(PathBuf, ReportFormat)>,
+    target: ScanTarget,
+    user: Option<String>,
+    date_field: DateField,
+    disk: Option<PathBuf>,
+    disk_cleanup: DiskCleanup,
+    dedup: Dedup,
+}
+
+impl ScanOptions {
+    /// Scans the repository at a local path.
+    pub fn new(repo: impl AsRef<Path>) -> Self {
+        Self::with_repo(path_to_string(repo.as_ref()))
+    }
+
+    /// Clones and scans a remote repository.
+    pub fn from_url(url: impl Into<String>) -> Self {
+        Self::with_repo(url.into())
+    }
+
+    fn with_repo(repo: String) -> Self {
+        ScanOptions {
+            repo,
+            config: None,
+            config_sha256: None,
+            ignore_path_case: false,
+            follow_symlinks: false,
+            allowlist_dry_run: false,
+            suppression_usage: false,
+            anonymize: false,
+            repo_config: false,
+            repo_config_allow: RepoConfigAllow::Rules,
+            threads: None,
+            chunk: None,
+            max_memory_mb: None,
+            mmap: false,
+            report: Vec::new(),
+            target: ScanTarget::All,
+            user: None,
+            date_field: DateField::Committer,
+            disk: None,
+            disk_cleanup: DiskCleanup::Never,
+            dedup: Dedup::None,
+        }
+    }
+
+    /// Sets the config file with the rules, `gitleaks.toml` by default.
+    pub fn config(mut self, path: impl Into<PathBuf>) -> Self {
+        self.config = Some(path.into());
+        self
+    }
+
+    /// Requires the config to have this SHA-256 checksum, in hex.
+    pub fn config_sha256(mut self, sha256: impl Into<String>) -> Self {
+        self.config_sha256 = Some(sha256.into());
+        self
+    }
+
+    /// Matches allowlist paths case-insensitively.
+    pub fn ignore_path_case(mut self, ignore_path_case: bool) -> Self {
+        self.ignore_path_case = ignore_path_case;
+        self
+    }
+
+    /// Scans what symbolic links point to instead of skipping them.
+    pub fn follow_symlinks(mut self, follow_symlinks: bool) -> Self {
+        self.follow_symlinks = follow_symlinks;
+        self
+    }
+
+    /// Reports the leaks allowlists would skip too, with the entry that would skip each.
+    pub fn allowlist_dry_run(mut self, allowlist_dry_run: bool) -> Self {
+        self.allowlist_dry_run = allowlist_dry_run;
+        self
+    }
+
+    /// Prints how many leaks each allowlist entry and ignored fingerprint skipped.
+    pub fn suppression_usage(mut self, suppression_usage: bool) -> Self {
+        self.suppression_usage = suppression_usage;
+        self
+    }
+
+    /// Hashes the authors and redacts the contents of the leaks, see [`crate::detect_utils::anonymize_leak`].
+    pub fn anonymize(mut self, anonymize: bool) -> Self {
+        self.anonymize = anonymize;
+        self
+    }
+
+    /// Loads the config from the target repository instead.
+    pub fn repo_config(mut self, repo_config: bool) -> Self {
+        self.repo_config = repo_config;
+        self
+    }
+
+    /// Limits which parts of the configs of the target repository are used, only the rules by
+    /// default.
+    pub fn repo_config_allow(mut self, allow: RepoConfigAllow) -> Self {
+        self.repo_config_allow = allow;
+        self
+    }
+
+    /// Sets the maximum number of threads.
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = Some(threads);
+        self
+    }
+
+    /// Sets the number of files processed in each batch.
+    pub fn chunk(mut self, chunk: usize) -> Self {
+        self.chunk = Some(chunk);
+        self
+    }
+
+    /// Bounds the memory used by the contents of commits waiting to be scanned, in MiB.
+    pub fn max_memory_mb(mut self, max_memory_mb: usize) -> Self {
+        self.max_memory_mb = Some(max_memory_mb);
+        self
+    }
+
+    /// Reads large working tree files through memory maps.
+    pub fn mmap(mut self, mmap: bool) -> Self {
+        self.mmap = mmap;
+        self
+    }
+
+    /// Writes a report file in the given format, `-` for stdout. Every call adds a report.
+    pub fn report(mut self, path: impl Into<PathBuf>, format: ReportFormat) -> Self {
+        self.report.push((path.into(), format));
+        self
+    }
+
+    /// Scans a single commit.
+    pub fn commit(mut self, sha: impl Into<String>) -> Self {
+        self.target = ScanTarget::Commit(sha.into());
+        self
+    }
+
+    /// Scans a list of commits.
+    pub fn commits<I, S>(mut self, shas: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.target = ScanTarget::Commits(shas.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Scans the commits listed in a file, one per line.
+    pub fn commits_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.target = ScanTarget::CommitsFile(path_to_string(&path.into()));
+        self
+    }
+
+    /// Scans the commits made between two points in time, both included.
+    pub fn between(mut self, since: DateTime<FixedOffset>, until: DateTime<FixedOffset>) -> Self {
+        self.target = ScanTarget::Between {
+            since: since.to_rfc3339(),
+            until: until.to_rfc3339(),
+        };
+        self
+    }
+
+    /// Scans the commits from `from` to `to`, both included.
+    pub fn commit_range(mut self, from: impl Into<String>, to: impl Into<String>) -> Self {
+        self.target = ScanTarget::Range {
+            from: from.into(),
+            to: to.into(),
+        };
+        self
+    }
+
+    /// Scans the branches whose name contains `name`.
+    pub fn branch(mut self, name: impl Into<String>) -> Self {
+        self.target = ScanTarget::Branch(name.into());
+        self
+    }
+
+    /// Scans the uncommitted changes of the working tree.
+    pub fn uncommitted(mut self) -> Self {
+        self.target = ScanTarget::Uncommitted;
+        self
+    }
+
+    /// Scans files of the working tree, whether they were changed or not, see `--file`.
+    pub fn files<I, S>(mut self, paths: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.target = ScanTarget::Files(paths.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Scans every blob of the object database once instead of the commits, see `--packfiles`.
+    pub fn packfiles(mut self) -> Self {
+        self.target = ScanTarget::Packfiles;
+        self
+    }
+
+    /// Only scans commits by this author.
+    pub fn user(mut self, name: impl Into<String>) -> Self {
+        self.user = Some(name.into());
+        self
+    }
+
+    /// Chooses the timestamp used for time ranges and leak dates.
+    pub fn date_field(mut self, field: DateField) -> Self {
+        self.date_field = field;
+        self
+    }
+
+    /// Clones remote repositories to this directory and keeps them.
+    pub fn disk(mut self, path: impl Into<PathBuf>) -> Self {
+        self.disk = Some(path.into());
+        self
+    }
+
+    /// Deletes the clone in the `disk` directory after the scan, rather than keeping it.
+    pub fn disk_cleanup(mut self, cleanup: DiskCleanup) -> Self {
+        self.disk_cleanup = cleanup;
+        self
+    }
+
+    /// Reports the leaks of the same secret in consecutive commits as one, with the range of the
+    /// commits.
+    pub fn dedup(mut self, dedup: Dedup) -> Self {
+        self.dedup = dedup;
+        self
+    }
+
+    /// Returns the `Config` to pass to `detect` or `Scanner::new`.
+    pub fn build(self) -> Config {
+        let mut config = Config {
+            repo: self.repo,
+            config_sha256: self.config_sha256,
+            ignore_path_case: self.ignore_path_case,
+            follow_symlinks: self.follow_symlinks,
+            allowlist_dry_run: self.allowlist_dry_run,
+            suppression_usage: self.suppression_usage,
+            anonymize: self.anonymize,
+            repo_config: self.repo_config,
+            repo_config_allow: self.repo_config_allow,
+            date_field: self.date_field,
+            max_memory_mb: self.max_memory_mb,
+            mmap: self.mmap,
+            disk: self.disk.as_deref().map(path_to_string),
+            disk_cleanup: self.disk_cleanup,
+            dedup: self.dedup,
+            ..Default::default()
+        };
+        if let Some(path) = &self.config {
+            config.config = path_to_string(path);
+        }
+        if self.threads.is_some() {
+            config.threads = self.threads;
+        }
+        if self.chunk.is_some() {
+            config.chunk = self.chunk;
+        }
+        config.report = self
+            .report
+            .iter()
+            .map(|(path, format)| ReportOutput {
+                format: Some(*format),
+                path: path_to_string(path),
+            })
+            .collect();
+        if self.user.is_some() {
+            config.user = self.user;
+        }
+        config.set_target(self.target);
+        config
+    }
+}
+
+impl From<ScanOptions> for Config {
+    fn from(options: ScanOptions) -> Self {
+        options.build()
+    }
+}
+
+fn path_to_string(path: &Path) -> String {
+    path.to_string_lossy().into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_defaults() {
+        let config = ScanOptions::new("repo").build();
+        let default = Config::default();
+        assert_eq!(config.repo, "repo");
+        assert_eq!(config.config, default.config);
+        assert_eq!(config.threads, default.threads);
+        assert_eq!(config.user, default.user);
+        assert!(config.commit.is_none() && !config.uncommitted);
+    }
+
+    #[test]
+    fn test_build_between() {
+        let since = DateTime::parse_from_rfc3339("2023-05-20T00:00:00+08:00").unwrap();
+        let until = DateTime::parse_from_rfc3339("2023-05-26T00:00:00+08:00").unwrap();
+        let config: Config = ScanOptions::new("repo")
+            .commit("abc")
+            .between(since, until)
+            .threads(4)
+            .into();
+
+        assert!(config.commit.is_none());
+        assert_eq!(config.commit_since.as_deref(), Some("2023-05-20T00:00:00+08:00"));
+        assert_eq!(config.commit_until.as_deref(), Some("2023-05-26T00:00:00+08:00"));
+        assert_eq!(config.threads, Some(4));
+    }
+
+    #[test]
+    fn test_build_commits_and_report() {
+        let config = ScanOptions::new("repo")
+            .commits(["a", "b"])
+            .report("out.csv", ReportFormat::Csv)
+            .report("-", ReportFormat::Table)
+            .build();
+
+        assert_eq!(config.commits.as_deref(), Some("a,b"));
+        assert_eq!(
+            config.report,
+            [
+                ReportOutput {
+                    format: Some(ReportFormat::Csv),
+                    path: "out.csv".to_string()
+                },
+                ReportOutput {
+                    format: Some(ReportFormat::Table),
+                    path: "-".to_string()
+                },
+            ]
+        );
+    }
+}
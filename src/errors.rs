@@ -21,12 +21,46 @@ pub enum CustomError {
     WalkerSortError,
     PushWalkerHeadError,
     InvalidDateFormat,
+    InvalidDateRange,
     InvalidTimeFormat,
     InvalidTomlFile,
+    InvalidRegexTarget,
+    InvalidCharset,
+    InvalidChecksum,
+    InvalidRuleRegex { id: String, reason: String },
+    RuleNotFound,
+    FingerprintWithoutSecret,
+    FileExists { path: String },
+    InvalidConfigExtend { path: String, reason: String },
+    RemoteConfigError { url: String, reason: String },
+    InsecureRemoteConfig,
+    ConfigChecksumMismatch { expected: String, actual: String },
+    ReportToolError { tool: String, reason: String },
+    UnsupportedUploadUrl { url: String },
+    IssueTrackerError { tracker: String, reason: String },
+    PullRequestCommentError { reason: String },
+    GistError { reason: String },
+    PackageError { package: String, reason: String },
+    CheckpointError { path: String, reason: String },
+    CaBundleError { path: String, reason: String },
+    LspError { reason: String },
+    DiagnosticsSocketError { path: String, reason: String },
+    BaseRefError { reason: String },
+    CiError { reason: String },
+    PolicyError { path: String, reason: String },
+    RepoListError { reason: String },
+    GitHubAppError { reason: String },
+    CheckRunError { reason: String },
+    ApiTokensError { path: String, reason: String },
+    ListedFileError { path: String, reason: String },
 
     ExportCsvError,
     ExportSarifError,
     ExportJsonError,
+    ExportTableError,
+    ExportQuickfixError,
+    ExportProblemMatcherError,
+    ExportCodeQualityError,
 }
 
 impl fmt::Display for CustomError {
@@ -39,6 +73,10 @@ impl fmt::Display for CustomError {
             CustomError::ExportCsvError => "Export CSV Error",
             CustomError::ExportSarifError => "Export Sarif Error",
             CustomError::ExportJsonError => "Export Json Error",
+            CustomError::ExportTableError => "Export Table Error",
+            CustomError::ExportQuickfixError => "Export Quickfix Error",
+            CustomError::ExportProblemMatcherError => "Export Problem Matcher Error",
+            CustomError::ExportCodeQualityError => "Export Code Quality Error",
 
             CustomError::FailDeleteDir => "Failed to delete directory",
             CustomError::FailCreateDir => "Failed to create directory",
@@ -59,9 +97,106 @@ impl fmt::Display for CustomError {
             CustomError::PushWalkerHeadError => {
                 "Failed to push the HEAD reference to the revision walker"
             }
-            CustomError::InvalidDateFormat => "Invalid date format",
+            CustomError::InvalidDateFormat => {
+                "Invalid date format, expected YYYY-MM-DD or a date and time with an offset, e.g. 2023-01-02T15:04:05-07:00"
+            }
+            CustomError::InvalidDateRange => "The start of the commit time range is after its end",
             CustomError::InvalidTimeFormat => "Invalid time format",
             CustomError::InvalidTomlFile => "Invalid TOML file",
+            CustomError::InvalidRegexTarget => "Invalid regexTarget, expected \"match\" or \"line\"",
+            CustomError::InvalidCharset => {
+                "Invalid charset, expected \"base64\", \"base64url\", \"hex\", \"alphanumeric\" or \"base32\""
+            }
+            CustomError::InvalidChecksum => "Invalid checksum, expected \"crc32-base62\"",
+            CustomError::RuleNotFound => "No rule with this id in the config",
+            CustomError::FingerprintWithoutSecret => {
+                "Fingerprints can only be added to an ignore file, pass the leaks to add to a config with --triage"
+            }
+            CustomError::InsecureRemoteConfig => {
+                "Configs downloaded over http:// need --config-sha256, or use https://"
+            }
+            CustomError::RemoteConfigError { ref url, ref reason } => {
+                return write!(f, "Failed to download the config from {}: {}", url, reason);
+            }
+            CustomError::ConfigChecksumMismatch { ref expected, ref actual } => {
+                return write!(f, "The config has SHA-256 {}, expected {}", actual, expected);
+            }
+            CustomError::InvalidConfigExtend { ref path, ref reason } => {
+                return write!(f, "Failed to extend the config {}: {}", path, reason);
+            }
+            CustomError::ReportToolError { ref tool, ref reason } => {
+                return write!(f, "Failed to run {}: {}", tool, reason);
+            }
+            CustomError::IssueTrackerError { ref tracker, ref reason } => {
+                return write!(f, "Failed to sync the issues in {}: {}", tracker, reason);
+            }
+            CustomError::PullRequestCommentError { ref reason } => {
+                return write!(f, "Failed to comment on the pull request: {}", reason);
+            }
+            CustomError::GistError { ref reason } => {
+                return write!(f, "Failed to scan the gists: {}", reason);
+            }
+            CustomError::PackageError { ref package, ref reason } => {
+                return write!(f, "Failed to scan the package {}: {}", package, reason);
+            }
+            CustomError::LspError { ref reason } => {
+                return write!(f, "Language server error: {}", reason);
+            }
+            CustomError::DiagnosticsSocketError { ref path, ref reason } => {
+                return write!(f, "Failed to serve the diagnostics on {}: {}", path, reason);
+            }
+            CustomError::BaseRefError { ref reason } => {
+                return write!(f, "Failed to find the base of the new commits: {}", reason);
+            }
+            CustomError::PolicyError { ref path, ref reason } => {
+                return write!(f, "Invalid policy file {}: {}", path, reason);
+            }
+            CustomError::RepoListError { ref reason } => {
+                return write!(f, "Failed to list the repositories: {}", reason);
+            }
+            CustomError::GitHubAppError { ref reason } => {
+                return write!(f, "GitHub App error: {}", reason);
+            }
+            CustomError::CheckRunError { ref reason } => {
+                return write!(f, "Failed to report the check run: {}", reason);
+            }
+            CustomError::ApiTokensError { ref path, ref reason } => {
+                return write!(f, "Invalid API tokens file {}: {}", path, reason);
+            }
+            CustomError::ListedFileError { ref path, ref reason } => {
+                return write!(f, "Can't scan the file {}: {}", path, reason);
+            }
+            CustomError::CiError { ref reason } => {
+                return write!(f, "Failed to read the CI job: {}", reason);
+            }
+            CustomError::CheckpointError { ref path, ref reason } => {
+                return write!(f, "Failed to resume from the checkpoint {}: {}", path, reason);
+            }
+            CustomError::CaBundleError { ref path, ref reason } => {
+                return write!(f, "Failed to load the CA bundle {}: {}", path, reason);
+            }
+            CustomError::UnsupportedUploadUrl { ref url } => {
+                let schemes: Vec<&str> = [
+                    (cfg!(feature = "upload-s3"), "s3://"),
+                    (cfg!(feature = "upload-gcs"), "gs://"),
+                    (cfg!(feature = "upload-azure"), "az://account/container/"),
+                ]
+                .into_iter()
+                .filter_map(|(built, scheme)| built.then_some(scheme))
+                .collect();
+                return write!(
+                    f,
+                    "Can't upload to {}, the URLs this build uploads to start with {}",
+                    url,
+                    schemes.join(", ")
+                );
+            }
+            CustomError::FileExists { ref path } => {
+                return write!(f, "{} already exists, pass --force to overwrite it", path);
+            }
+            CustomError::InvalidRuleRegex { ref id, ref reason } => {
+                return write!(f, "Invalid regex in rule {}: {}", id, reason);
+            }
         };
         write!(f, "{}", error_message)
     }
@@ -0,0 +1,146 @@
+//! C interface for embedding the detection engine as a shared library.
+//!
+//! Every function takes and returns NUL-terminated UTF-8 strings. Results are JSON objects, either
+//! the scan output or `{"error": "..."}`, and must be released with `sensleak_free`. The matching
+//! declarations live in `include/sensleak.h`.
+
+use crate::models::Config;
+use crate::service::detect_service::detect;
+use crate::utils::detect_utils::scan_text;
+use serde_json::{json, Value};
+use std::ffi::{c_char, CStr, CString};
+
+/// Scans a piece of text with a TOML ruleset.
+///
+/// Returns `{"leaks": [...]}` on success.
+///
+/// # Safety
+///
+/// `content` and `config` must be valid NUL-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn sensleak_scan_text(
+    content: *const c_char,
+    config: *const c_char,
+) -> *mut c_char {
+    let output = match (read_str(content), read_str(config)) {
+        (Some(content), Some(config)) => match scan_text(&content, &config) {
+            Ok(leaks) => json!({ "leaks": leaks }),
+            Err(err) => json!({ "error": err.to_string() }),
+        },
+        _ => json!({ "error": "Invalid argument" }),
+    };
+    into_c_string(output)
+}
+
+/// Scans a repository. `options` is a JSON object with the same fields as the command line
+/// options, e.g. `{"repo": "path/to/repo", "config": "gitleaks.toml"}`; missing fields take
+/// their default values.
+///
+/// Returns `{"commits_number": n, "leaks": [...], "errors": [...]}` on success, `errors` listing
+/// the objects that couldn't be read and were skipped, and only `{"error": "..."}` if `options`
+/// can't be read or parsed or the scan fails, see `include/sensleak.h`. The string is never null
+/// and must be released with `sensleak_free`.
+///
+/// # Safety
+///
+/// `options` must be a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn sensleak_scan(options: *const c_char) -> *mut c_char {
+    let output = match read_str(options).map(|s| serde_json::from_str::<Config>(&s)) {
+        Some(Ok(config)) => match run_detect(config) {
+            Ok(value) => value,
+            Err(err) => json!({ "error": err }),
+        },
+        Some(Err(err)) => json!({ "error": err.to_string() }),
+        None => json!({ "error": "Invalid argument" }),
+    };
+    into_c_string(output)
+}
+
+/// Releases a string returned by this library.
+///
+/// # Safety
+///
+/// `s` must have been returned by a `sensleak_*` function and must not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn sensleak_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+/// Runs the async scan on a private runtime, so callers don't need one.
+fn run_detect(config: Config) -> Result<Value, String> {
+    let runtime = tokio::runtime::Runtime::new().map_err(|e| e.to_string())?;
+    let results = runtime
+        .block_on(detect(config))
+        .map_err(|e| e.to_string())?;
+    Ok(json!({
+        "commits_number": results.commits_number,
+        "leaks": results.outputs,
+        "errors": results.errors,
+    }))
+}
+
+unsafe fn read_str(s: *const c_char) -> Option<String> {
+    if s.is_null() {
+        return None;
+    }
+    CStr::from_ptr(s).to_str().ok().map(String::from)
+}
+
+fn into_c_string(value: Value) -> *mut c_char {
+    // serde_json escapes control characters, so the output never contains an interior NUL
+    CString::new(value.to_string()).unwrap().into_raw()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(f: impl FnOnce() -> *mut c_char) -> Value {
+        let ptr = f();
+        let value = unsafe { serde_json::from_str(CStr::from_ptr(ptr).to_str().unwrap()).unwrap() };
+        unsafe { sensleak_free(ptr) };
+        value
+    }
+
+    #[test]
+    fn test_sensleak_scan_text() {
+        let content = CString::new("token = sk_test_1234567890abcdef").unwrap();
+        let config = CString::new(
+            r#"
+            [[rules]]
+            description = "Stripe Access Token"
+            id = "stripe-access-token"
+            regex = "(?i)(sk|pk)_(test|live)_[0-9a-z]{10,32}"
+            keywords = ["sk_test"]
+            "#,
+        )
+        .unwrap();
+
+        let value = call(|| unsafe { sensleak_scan_text(content.as_ptr(), config.as_ptr()) });
+        assert_eq!(value["leaks"][0]["offender"], "sk_test_1234567890abcdef");
+    }
+
+    #[test]
+    fn test_sensleak_scan_text_invalid_config() {
+        let content = CString::new("token").unwrap();
+        let config = CString::new("not toml [").unwrap();
+
+        let value = call(|| unsafe { sensleak_scan_text(content.as_ptr(), config.as_ptr()) });
+        assert!(value["error"].is_string());
+    }
+
+    #[test]
+    fn test_sensleak_scan_invalid_options() {
+        let options = CString::new("{\"repo\": 1}").unwrap();
+
+        let value = call(|| unsafe { sensleak_scan(options.as_ptr()) });
+        assert!(value["error"].is_string());
+        assert_eq!(value.as_object().unwrap().len(), 1);
+
+        let value = call(|| unsafe { sensleak_scan(std::ptr::null()) });
+        assert_eq!(value, json!({ "error": "Invalid argument" }));
+    }
+}
@@ -1,44 +1,140 @@
 mod errors;
 
 mod utils {
+    #[cfg(not(target_arch = "wasm32"))]
+    pub mod archive_util;
+    #[cfg(not(target_arch = "wasm32"))]
+    pub mod checkpoint_util;
+    #[cfg(not(target_arch = "wasm32"))]
+    pub mod clone_util;
+    #[cfg(not(target_arch = "wasm32"))]
+    pub mod compress_util;
+    #[cfg(not(target_arch = "wasm32"))]
+    pub mod connection_string_util;
+    #[cfg(not(target_arch = "wasm32"))]
+    pub mod crypt_util;
+    pub mod date_util;
     pub mod detect_utils;
+    #[cfg(not(target_arch = "wasm32"))]
+    pub mod dotenv_util;
+    #[cfg(not(target_arch = "wasm32"))]
+    pub mod filter_util;
+    #[cfg(not(target_arch = "wasm32"))]
+    pub mod generated_util;
+    #[cfg(not(target_arch = "wasm32"))]
     pub mod git_util;
+    #[cfg(not(target_arch = "wasm32"))]
+    pub mod http_util;
+    #[cfg(not(target_arch = "wasm32"))]
+    pub mod key_path_util;
+    #[cfg(not(target_arch = "wasm32"))]
+    pub mod lfs_util;
+    pub mod log_util;
+    #[cfg(not(target_arch = "wasm32"))]
+    pub mod manifest_util;
+    #[cfg(not(target_arch = "wasm32"))]
+    pub mod memory_util;
+    #[cfg(not(target_arch = "wasm32"))]
+    pub mod mmap_util;
+    #[cfg(not(target_arch = "wasm32"))]
+    pub mod notebook_util;
+    #[cfg(not(target_arch = "wasm32"))]
+    pub mod private_key_util;
+    #[cfg(not(target_arch = "wasm32"))]
+    pub mod progress_util;
+    pub mod rule_packs;
+    pub mod rule_regex;
+    #[cfg(not(target_arch = "wasm32"))]
+    pub mod score_util;
+    #[cfg(not(target_arch = "wasm32"))]
+    pub mod signal_util;
+    #[cfg(not(target_arch = "wasm32"))]
+    pub mod terraform_util;
+    #[cfg(all(not(target_arch = "wasm32"), any(feature = "upload-s3", feature = "upload-gcs", feature = "upload-azure")))]
+    pub mod upload_util;
 }
 
 pub mod entity{
     pub mod models;
+    pub mod options;
 }
 
+#[cfg(not(target_arch = "wasm32"))]
 pub mod service{
+    pub mod app_service;
+    pub mod auth_service;
     pub mod detect_service;
+    pub mod gist_service;
     pub mod git_service;
     pub mod db_service;
+    pub mod dashboard_service;
+    pub mod bench_service;
+    pub mod check_run_service;
+    pub mod ci_service;
+    pub mod init_service;
+    pub mod issue_service;
+    pub mod lsp_service;
+    pub mod package_service;
+    pub mod pr_comment_service;
+    pub mod plan_service;
+    pub mod repos_service;
+    pub mod watch_service;
 }
+
+#[cfg(not(target_arch = "wasm32"))]
+pub mod ffi;
+
+#[cfg(target_arch = "wasm32")]
+pub mod wasm;
  
 pub use entity::models;
 pub use errors::*;
+pub use utils::date_util;
 pub use utils::detect_utils;
+pub use utils::rule_regex;
+#[cfg(not(target_arch = "wasm32"))]
 pub use utils::git_util;
+#[cfg(not(target_arch = "wasm32"))]
+pub use utils::mmap_util;
+#[cfg(not(target_arch = "wasm32"))]
 pub use git_util::*;
 pub use models::*;
+pub use entity::options::ScanOptions;
+
+#[cfg(not(target_arch = "wasm32"))]
+use axum::{middleware, routing, Router};
 
-use axum::{routing, Router};
+#[cfg(not(target_arch = "wasm32"))]
+use service::auth_service::{authorize, ApiTokens, API_TOKENS_VAR};
 
+#[cfg(not(target_arch = "wasm32"))]
 use utoipa::{
      OpenApi,
 };
 
+#[cfg(not(target_arch = "wasm32"))]
 use utoipa_swagger_ui::SwaggerUi;
 
+#[cfg(not(target_arch = "wasm32"))]
 mod routes{
     pub mod scan;
     pub mod rules;
+    pub mod leaks;
+    pub mod dashboard;
 }
+#[cfg(not(target_arch = "wasm32"))]
 pub use routes::scan::*;
+#[cfg(not(target_arch = "wasm32"))]
 pub use routes::rules::*;
+#[cfg(not(target_arch = "wasm32"))]
+pub use routes::leaks::*;
+#[cfg(not(target_arch = "wasm32"))]
+pub use routes::dashboard::*;
 
+#[cfg(not(target_arch = "wasm32"))]
 use crate::routes::*;
 
+#[cfg(not(target_arch = "wasm32"))]
 pub async fn start() -> Result<(), Box<dyn std::error::Error>> {
     #[derive(OpenApi)]
     #[openapi(
@@ -47,32 +143,47 @@ pub async fn start() -> Result<(), Box<dyn std::error::Error>> {
             rules::get_all,
             rules::add_rules,
             rules::delete_rules_by_id,
-            rules::update_rules
+            rules::update_rules,
+            leaks::list_tracked_leaks,
+            leaks::assign_tracked_leak,
+            leaks::set_tracked_leak_status,
+            dashboard::summarize_leaks
         ),
         components(
-            schemas(ConfigDto,ScanResponse,RulesDto,JsonResponse,Rule,Allowlist)
+            schemas(ConfigDto,ScanResponse,RulesDto,JsonResponse,Rule,Allowlist,LeakFilter,LeakStatus,LeakTriageDto,LeaksResponse,models::Model,models::scan_record::Model,DashboardResponse,service::dashboard_service::DashboardSummary,service::dashboard_service::RepoSummary,service::dashboard_service::SeverityCount,service::dashboard_service::TrendPoint)
         ),
      
         tags(
             (name = "scan", description = "Scan Git repositories API"),
             (name = "rules", description = "Rules management API"),
+            (name = "leaks", description = "Leak tracking API"),
+            (name = "dashboard", description = "Dashboard API"),
 
         )
     )]
     struct ApiDoc;
 
- 
+    let tokens = ApiTokens::from_env()?;
+    if tokens.is_none() {
+        eprintln!("Application: {} isn't set, anyone who can reach the server can use the API", API_TOKENS_VAR);
+    }
+
     let app = Router::new()
         .merge(SwaggerUi::new("/swagger-ui").url("/api-docs/openapi.json", ApiDoc::openapi()))
         .route("/scan", routing::post(scan_repo))
         .route("/rules/get_all", routing::post(get_all))
         .route("/rules/add_rules", routing::post(add_rules))
         .route("/rules/delete_rules_by_id", routing::post(delete_rules_by_id))
-        .route("/rules/update", routing::post(update_rules));
+        .route("/rules/update", routing::post(update_rules))
+        .route("/leaks/list", routing::post(list_tracked_leaks))
+        .route("/leaks/assign", routing::post(assign_tracked_leak))
+        .route("/leaks/set_status", routing::post(set_tracked_leak_status))
+        .route("/dashboard", routing::get(dashboard))
+        .route("/dashboard/dashboard.js", routing::get(dashboard_script))
+        .route("/dashboard/summary", routing::post(summarize_leaks))
+        .layer(middleware::from_fn_with_state(std::sync::Arc::new(tokens), authorize));
 
     let listener = tokio::net::TcpListener::bind("0.0.0.0:7000").await.unwrap();
     axum::serve(listener, app.into_make_service()).await?;
     Ok(())
 }
-
- 
\ No newline at end of file
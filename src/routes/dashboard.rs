@@ -0,0 +1,53 @@
+use axum::http::header;
+use axum::response::{Html, IntoResponse};
+use axum::Json;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::service::dashboard_service::{dashboard_summary, DashboardSummary};
+
+/// The page of the dashboard, which renders the summary with the script.
+const DASHBOARD_HTML: &str = include_str!("dashboard/index.html");
+
+/// The script of the dashboard.
+const DASHBOARD_JS: &str = include_str!("dashboard/dashboard.js");
+
+/// The response object
+#[derive(Serialize, ToSchema)]
+pub struct DashboardResponse {
+    /// 200-success, 400-fail
+    code: usize,
+    /// summary
+    summary: Option<DashboardSummary>,
+    /// message
+    message: Option<String>,
+}
+
+/// Serve the dashboard.
+pub async fn dashboard() -> Html<&'static str> {
+    Html(DASHBOARD_HTML)
+}
+
+/// Serve the script of the dashboard.
+pub async fn dashboard_script() -> impl IntoResponse {
+    ([(header::CONTENT_TYPE, "text/javascript; charset=utf-8")], DASHBOARD_JS)
+}
+
+/// Summarize the leaks.
+///
+/// Summarize the leaks and scans stored in the database: the repositories, the scan history, the
+/// unresolved leaks by severity and the trend of the leaks found and resolved.
+#[utoipa::path(
+    post,
+    path = "/dashboard/summary",
+    responses(
+        (status = 200, description = "success", body = DashboardResponse),
+        (status = 400, description = "fail", body = DashboardResponse)
+    )
+)]
+pub async fn summarize_leaks() -> Json<DashboardResponse> {
+    match dashboard_summary().await {
+        Ok(summary) => Json(DashboardResponse { code: 200, summary: Some(summary), message: None }),
+        Err(err) => Json(DashboardResponse { code: 400, summary: None, message: Some(err.to_string()) }),
+    }
+}
@@ -0,0 +1,100 @@
+// Renders the summary of /dashboard/summary. Values are set as text, never as HTML, since
+// repositories and refs come from the scanned repositories.
+
+function row(cells) {
+  const tr = document.createElement("tr");
+  for (const [value, number] of cells) {
+    const td = document.createElement("td");
+    td.textContent = value;
+    if (number) td.className = "number";
+    tr.appendChild(td);
+  }
+  return tr;
+}
+
+function renderSeverities(counts) {
+  const max = Math.max(1, ...counts.map((count) => count.open));
+  const container = document.getElementById("severities");
+  for (const count of counts) {
+    const bar = document.createElement("div");
+    bar.className = "bar " + count.severity;
+    const label = document.createElement("span");
+    label.textContent = count.severity;
+    const fill = document.createElement("div");
+    fill.style.width = (count.open / max) * 60 + "%";
+    const value = document.createElement("span");
+    value.textContent = count.open;
+    bar.append(label, fill, value);
+    container.appendChild(bar);
+  }
+}
+
+function renderTrend(points) {
+  const svg = document.getElementById("trend");
+  const max = Math.max(1, ...points.map((point) => Math.max(point.found, point.resolved)));
+  const x = (i) => (points.length > 1 ? (i / (points.length - 1)) * 980 + 10 : 500);
+  const y = (value) => 170 - (value / max) * 160;
+  for (const [field, color] of [["found", "#cf222e"], ["resolved", "#1a7f37"]]) {
+    const line = document.createElementNS("http://www.w3.org/2000/svg", "polyline");
+    line.setAttribute("points", points.map((point, i) => x(i) + "," + y(point[field])).join(" "));
+    line.setAttribute("fill", "none");
+    line.setAttribute("stroke", color);
+    line.setAttribute("stroke-width", "2");
+    line.setAttribute("vector-effect", "non-scaling-stroke");
+    svg.appendChild(line);
+  }
+  points.forEach((point, i) => {
+    const title = document.createElementNS("http://www.w3.org/2000/svg", "title");
+    title.textContent = point.day + ": " + point.scans + " scans, " + point.found + " found, " + point.resolved + " resolved";
+    const dot = document.createElementNS("http://www.w3.org/2000/svg", "circle");
+    dot.setAttribute("cx", x(i));
+    dot.setAttribute("cy", y(point.found));
+    dot.setAttribute("r", "4");
+    dot.setAttribute("fill", "#cf222e");
+    dot.appendChild(title);
+    svg.appendChild(dot);
+  });
+}
+
+function render(summary) {
+  renderSeverities(summary.open_by_severity);
+  renderTrend(summary.trend);
+  const repos = document.getElementById("repos");
+  for (const repo of summary.repos) {
+    repos.appendChild(row([[repo.repo || "-"], [repo.open, true], [repo.leaks, true], [repo.last_scan || "-"]]));
+  }
+  const scans = document.getElementById("scans");
+  for (const scan of summary.scans) {
+    const duration = (scan.duration_ms / 1000).toFixed(1) + " s";
+    scans.appendChild(row([[scan.started_at], [scan.repo || "-"], [scan.refs], [scan.commits, true], [scan.leaks, true], [duration, true]]));
+  }
+}
+
+// With SENSLEAK_API_TOKENS set, the summary needs a token of a viewer, asked for once and kept
+// in the browser.
+function load(token) {
+  const headers = token ? { Authorization: "Bearer " + token } : {};
+  return fetch("/dashboard/summary", { method: "POST", headers }).then((response) => {
+    if (response.status === 401 && !token) {
+      const asked = window.prompt("Token of the sensleak API");
+      if (asked) {
+        localStorage.setItem("sensleak-token", asked);
+        return load(asked);
+      }
+    }
+    if (response.status === 401 || response.status === 403) {
+      localStorage.removeItem("sensleak-token");
+      throw new Error("the token isn't allowed to read the summary");
+    }
+    return response.json();
+  });
+}
+
+load(localStorage.getItem("sensleak-token"))
+  .then((response) => {
+    if (response.code !== 200) throw new Error(response.message);
+    render(response.summary);
+  })
+  .catch((err) => {
+    document.getElementById("error").textContent = "Failed to load the summary: " + err.message;
+  });
@@ -0,0 +1,108 @@
+use axum::{Extension, Json};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use utoipa::ToSchema;
+
+use crate::models::{LeakFilter, LeakStatus, Model};
+use crate::service::auth_service::{audit, ApiUser};
+use crate::service::db_service::{assign_leak, list_leaks, set_leak_status};
+
+/// The triage of a leak
+#[derive(Deserialize, Serialize, ToSchema)]
+pub struct LeakTriageDto {
+    /// ID of the leak
+    pub id: i32,
+    /// User the leak is assigned to, none to unassign it
+    pub assignee: Option<String>,
+    /// Status of the leak, required to set it
+    pub status: Option<LeakStatus>,
+    /// How the leak was resolved, or why it isn't one
+    pub note: Option<String>,
+}
+
+/// The response object
+#[derive(Serialize, ToSchema)]
+pub struct LeaksResponse {
+    /// 200-success, 400-fail
+    code: usize,
+    /// leaks
+    #[schema(value_type = Option<Vec<TrackedLeak>>)]
+    leaks: Option<Vec<Model>>,
+    /// message
+    message: Option<String>,
+}
+
+impl LeaksResponse {
+    fn from_result(result: Result<Vec<Model>, sea_orm::DbErr>) -> Json<LeaksResponse> {
+        match result {
+            Ok(leaks) => Json(LeaksResponse { code: 200, leaks: Some(leaks), message: None }),
+            Err(err) => Json(LeaksResponse { code: 400, leaks: None, message: Some(err.to_string()) }),
+        }
+    }
+}
+
+/// List the leaks.
+///
+/// List the leaks stored in the database that match the filter.
+#[utoipa::path(
+    post,
+    path = "/leaks/list",
+    request_body = LeakFilter,
+    responses(
+        (status = 200, description = "success", body = LeaksResponse),
+        (status = 400, description = "fail", body = LeaksResponse)
+    )
+)]
+pub async fn list_tracked_leaks(Json(filter): Json<LeakFilter>) -> Json<LeaksResponse> {
+    LeaksResponse::from_result(list_leaks(&filter).await)
+}
+
+/// Assign a leak.
+///
+/// Assign a leak to a user, or unassign it without an assignee.
+#[utoipa::path(
+    post,
+    path = "/leaks/assign",
+    request_body = LeakTriageDto,
+    responses(
+        (status = 200, description = "success", body = LeaksResponse),
+        (status = 400, description = "fail", body = LeaksResponse)
+    )
+)]
+pub async fn assign_tracked_leak(
+    user: Option<Extension<ApiUser>>,
+    Json(body): Json<LeakTriageDto>,
+) -> Json<LeaksResponse> {
+    let assigned = assign_leak(body.id, body.assignee.as_deref()).await;
+    if assigned.is_ok() {
+        audit(user.as_deref(), "assign", json!({"id": body.id, "assignee": body.assignee}));
+    }
+    LeaksResponse::from_result(assigned.map(|leak| vec![leak]))
+}
+
+/// Set the status of a leak.
+///
+/// Set the status of a leak: open, triaged, false-positive or rotated, with a note on how it was
+/// resolved.
+#[utoipa::path(
+    post,
+    path = "/leaks/set_status",
+    request_body = LeakTriageDto,
+    responses(
+        (status = 200, description = "success", body = LeaksResponse),
+        (status = 400, description = "fail", body = LeaksResponse)
+    )
+)]
+pub async fn set_tracked_leak_status(
+    user: Option<Extension<ApiUser>>,
+    Json(body): Json<LeakTriageDto>,
+) -> Json<LeaksResponse> {
+    let Some(status) = body.status else {
+        return Json(LeaksResponse { code: 400, leaks: None, message: Some("The status of the leak is required".to_string()) });
+    };
+    let updated = set_leak_status(body.id, status, body.note.as_deref()).await;
+    if updated.is_ok() {
+        audit(user.as_deref(), "set_status", json!({"id": body.id, "status": status, "note": body.note}));
+    }
+    LeaksResponse::from_result(updated.map(|leak| vec![leak]))
+}
@@ -1,9 +1,12 @@
-use axum::Json;
+use axum::http::StatusCode;
+use axum::{Extension, Json};
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 use utoipa::ToSchema;
 
+use crate::service::auth_service::{audit, ApiRole, ApiUser};
 use crate::service::detect_service::detect;
-use crate::{Config, Leak};
+use crate::{Config, DateField, Dedup, DiskCleanup, Leak, RepoConfigAllow, ReportFormat};
 
 /// The scan configuration
 #[derive(Deserialize, Serialize, ToSchema)]
@@ -12,16 +15,32 @@ pub struct ConfigDto {
     pub repo: String,
     /// Config path
     pub config: String,
+    /// SHA-256 checksum the config must have
+    pub config_sha256: Option<String>,
     /// Maximum number of threads sensleak spawns
     pub report: Option<String>,
     /// The number of git files processed in each batch
-    pub report_format: Option<String>,
+    pub report_format: Option<ReportFormat>,
+    /// Match allowlist paths case-insensitively
+    pub ignore_path_case: Option<bool>,
+    /// Scan what symbolic links point to
+    pub follow_symlinks: Option<bool>,
+    /// Report the leaks allowlists would skip too
+    pub allowlist_dry_run: Option<bool>,
+    /// Hash the authors and redact the contents of the leaks
+    pub anonymize: Option<bool>,
     /// Path to write json leaks file.
     pub repo_config: Option<bool>,
+    /// Parts of the repository configs used: all, rules (the default), allowlist or none
+    pub repo_config_allow: Option<RepoConfigAllow>,
     /// json, csv, sarif
     pub threads: Option<usize>,
     /// Show verbose output from scan.
     pub chunk: Option<usize>,
+    /// Memory limit for the contents of commits waiting to be scanned, in MiB
+    pub max_memory_mb: Option<usize>,
+    /// Read large working tree files through memory maps
+    pub mmap: Option<bool>,
     /// Pretty print json if leaks are present.
     pub commit: Option<String>,
     /// comma separated list of a commits to scan
@@ -39,6 +58,8 @@ pub struct ConfigDto {
     pub commit_to: Option<String>,
     /// Branch to scan
     pub branch: Option<String>,
+    /// author or committer, the timestamp used for commit_since/commit_until and the leak date
+    pub date_field: Option<DateField>,
     /// Run sensleak on uncommitted code
     pub uncommitted: Option<bool>,
     /// Set user to scan
@@ -46,6 +67,10 @@ pub struct ConfigDto {
 
     /// Clones repo(s) to disk.
     pub disk: Option<String>,
+    /// When the clone in disk is deleted: always, never or on-success
+    pub disk_cleanup: Option<DiskCleanup>,
+    /// Which leaks in consecutive commits are reported as one: secret, secret-per-file or none
+    pub dedup: Option<Dedup>,
 
     /// Output to database
     pub to_db: bool,
@@ -75,31 +100,65 @@ pub struct ScanResponse {
     request_body = ConfigDto,
     responses(
         (status = 200, description = "success", body = ScanResponse),
-        (status = 400, description = "fail", body = ScanResponse)
+        (status = 400, description = "fail", body = ScanResponse),
+        (status = 403, description = "the token may not set the paths of the request", body = ScanResponse)
     )
 )]
-pub async fn scan_repo(Json(json_config): Json<ConfigDto>) -> Json<ScanResponse> {
+pub async fn scan_repo(
+    user: Option<Extension<ApiUser>>,
+    Json(json_config): Json<ConfigDto>,
+) -> Result<Json<ScanResponse>, (StatusCode, Json<ScanResponse>)> {
+    let details = json!({"repo": json_config.repo, "branch": json_config.branch, "commit": json_config.commit});
+    audit(user.as_deref(), "scan", details);
+    if let Some(user) = user.as_deref().filter(|user| user.role < ApiRole::Admin) {
+        let fields = admin_fields(&json_config);
+        if !fields.is_empty() {
+            return Err((
+                StatusCode::FORBIDDEN,
+                Json(ScanResponse {
+                    code: 403,
+                    message: Some(format!("a {:?} token may not set {}", user.role, fields.join(", "))),
+                    leaks_number: None,
+                    commits_number: None,
+                    leaks: None,
+                }),
+            ));
+        }
+    }
+
     let mut config: Config = Default::default();
     config.repo = json_config.repo;
     config.config = json_config.config;
-    config.report = json_config.report;
+    config.config_sha256 = json_config.config_sha256;
+    config.report = json_config.report.into_iter().filter_map(|report| report.parse().ok()).collect();
     config.threads = json_config.threads;
     config.chunk = json_config.chunk;
-    config.report_format = json_config.report_format;
+    config.max_memory_mb = json_config.max_memory_mb;
+    config.mmap = json_config.mmap.unwrap_or(false);
+    config.report_format = json_config.report_format.unwrap_or_default();
     config.commit = json_config.commit;
     config.commits = json_config.commits;
     config.commit_from = json_config.commit_from;
     config.commit_to = json_config.commit_to;
     config.commit_since = json_config.commit_since;
+    config.commit_until = json_config.commit_until;
     config.commits_file = json_config.commits_file;
     config.branch = json_config.branch;
+    config.date_field = json_config.date_field.unwrap_or_default();
     config.uncommitted = false;
     config.user = json_config.user;
     config.disk = json_config.disk;
+    config.disk_cleanup = json_config.disk_cleanup.unwrap_or_default();
+    config.dedup = json_config.dedup.unwrap_or_default();
+    config.ignore_path_case = json_config.ignore_path_case.unwrap_or(false);
+    config.follow_symlinks = json_config.follow_symlinks.unwrap_or(false);
+    config.allowlist_dry_run = json_config.allowlist_dry_run.unwrap_or(false);
+    config.anonymize = json_config.anonymize.unwrap_or(false);
     config.repo_config = json_config.repo_config.unwrap_or(false);
+    config.repo_config_allow = json_config.repo_config_allow.unwrap_or_default();
     config.to_db = json_config.to_db;
 
-    match detect(config).await {
+    Ok(match detect(config).await {
         Ok(results) => Json(ScanResponse {
             code: 200,
             leaks_number: Some(results.outputs.len()),
@@ -114,14 +173,37 @@ pub async fn scan_repo(Json(json_config): Json<ConfigDto>) -> Json<ScanResponse>
             commits_number: None,
             leaks: None,
         }),
+    })
+}
+
+/// Returns the fields of a request that name files on the server, which only admins may set: the
+/// config read, other than the default one, the reports and commits file read or written, and
+/// where repositories are cloned and whether they are deleted.
+fn admin_fields(json_config: &ConfigDto) -> Vec<&'static str> {
+    let mut fields = Vec::new();
+    if json_config.config != Config::default().config {
+        fields.push("config");
+    }
+    if json_config.report.is_some() {
+        fields.push("report");
     }
+    if json_config.commits_file.is_some() {
+        fields.push("commits_file");
+    }
+    if json_config.disk.is_some() {
+        fields.push("disk");
+    }
+    if json_config.disk_cleanup.is_some() {
+        fields.push("disk_cleanup");
+    }
+    fields
 }
 
 
 
 #[cfg(test)]
 mod tests {
-    // use super::*;
+    use super::*;
     // use axum::{
     //     extract::Json,
  
@@ -160,4 +242,22 @@ mod tests {
     //     assert_eq!(response.message, None);
    
     // }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_admin_fields() {
+        let dto = |extra: serde_json::Value| -> ConfigDto {
+            let mut value = json!({"repo": "https://github.com/acme/api", "config": "gitleaks.toml", "to_db": false});
+            value.as_object_mut().unwrap().extend(extra.as_object().unwrap().clone());
+            serde_json::from_value(value).unwrap()
+        };
+        assert!(admin_fields(&dto(json!({"branch": "main", "threads": 4}))).is_empty());
+        assert_eq!(
+            admin_fields(&dto(json!({"config": "/etc/passwd", "disk": "/srv", "disk_cleanup": "never"}))),
+            vec!["config", "disk", "disk_cleanup"]
+        );
+        assert_eq!(
+            admin_fields(&dto(json!({"report": "/tmp/leaks.json", "commits_file": "/etc/shadow"}))),
+            vec!["report", "commits_file"]
+        );
+    }
+}
\ No newline at end of file
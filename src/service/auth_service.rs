@@ -0,0 +1,237 @@
+use crate::errors::CustomError;
+use crate::utils::http_util::sha256_hex;
+use axum::extract::{Request, State};
+use axum::http::{header, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::sync::Arc;
+
+/// The variable the path of the API tokens file is read from. The server is open to anyone
+/// without it.
+pub const API_TOKENS_VAR: &str = "SENSLEAK_API_TOKENS";
+
+/// The variable the path of the audit log is read from.
+pub const AUDIT_LOG_VAR: &str = "SENSLEAK_AUDIT_LOG";
+
+/// Where the audit log is appended to without `SENSLEAK_AUDIT_LOG`.
+const DEFAULT_AUDIT_LOG: &str = "audit.log";
+
+/// What the holder of a token may do, each role being allowed what those below it are.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ApiRole {
+    /// Reads the rules, the leaks and the dashboard.
+    Viewer,
+    /// Scans repositories.
+    Scanner,
+    /// Changes the rules, and assigns leaks and sets their status.
+    Admin,
+}
+
+/// A token of the API tokens file.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct ApiToken {
+    /// Who holds the token, as the audit log names them.
+    pub name: String,
+
+    pub role: ApiRole,
+
+    /// The SHA-256 digest of the token in hex, so that the file doesn't hold the token itself.
+    pub sha256: String,
+}
+
+/// The tokens the API accepts, read from a TOML file of `[[tokens]]` tables.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct ApiTokens {
+    pub tokens: Vec<ApiToken>,
+}
+
+/// Who made a request, added to the extensions of the requests that were authenticated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiUser {
+    pub name: String,
+    pub role: ApiRole,
+}
+
+impl ApiTokens {
+    /// Reads the tokens file named by `SENSLEAK_API_TOKENS`, if it is set.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CustomError::ApiTokensError` if the file can't be read or is invalid.
+    pub fn from_env() -> Result<Option<ApiTokens>, CustomError> {
+        let Ok(path) = std::env::var(API_TOKENS_VAR) else {
+            return Ok(None);
+        };
+        let error = |reason: String| CustomError::ApiTokensError { path: path.clone(), reason };
+        let text = fs::read_to_string(&path).map_err(|e| error(e.to_string()))?;
+        ApiTokens::parse(&text).map(Some).map_err(error)
+    }
+
+    /// Parses a tokens file, checking that every digest is one.
+    pub fn parse(text: &str) -> Result<ApiTokens, String> {
+        let mut tokens: ApiTokens = toml::from_str(text).map_err(|e| e.to_string())?;
+        for token in &mut tokens.tokens {
+            token.sha256 = token.sha256.to_ascii_lowercase();
+            if token.sha256.len() != 64 || !token.sha256.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(format!("the sha256 of the token of {} isn't a SHA-256 digest in hex", token.name));
+            }
+        }
+        Ok(tokens)
+    }
+
+    /// Returns who holds the token of an `Authorization: Bearer` header, if it is one of them.
+    pub fn authenticate(&self, authorization: &str) -> Option<ApiUser> {
+        let token = authorization.strip_prefix("Bearer ")?.trim();
+        // Comparing the digests doesn't tell how much of the token matched, unlike the tokens
+        let digest = sha256_hex(token.as_bytes());
+        self.tokens
+            .iter()
+            .find(|known| known.sha256 == digest)
+            .map(|known| ApiUser { name: known.name.clone(), role: known.role })
+    }
+}
+
+/// Returns the role a request to a path needs, or `None` for the pages anyone can load: the
+/// dashboard and the API document, whose data is requested with a token.
+pub fn required_role(path: &str) -> Option<ApiRole> {
+    match path {
+        "/dashboard" | "/dashboard/dashboard.js" => None,
+        _ if path.starts_with("/swagger-ui") || path.starts_with("/api-docs") => None,
+        "/rules/get_all" | "/leaks/list" | "/dashboard/summary" => Some(ApiRole::Viewer),
+        "/scan" => Some(ApiRole::Scanner),
+        // Routes added later are for admins until they are listed above
+        _ => Some(ApiRole::Admin),
+    }
+}
+
+/// Lets a request through if its token has the role its path needs, adding who made it to its
+/// extensions. Every request is let through without tokens.
+pub async fn authorize(
+    State(tokens): State<Arc<Option<ApiTokens>>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let Some(tokens) = tokens.as_ref() else {
+        return next.run(request).await;
+    };
+    let Some(role) = required_role(request.uri().path()) else {
+        return next.run(request).await;
+    };
+    let authorization = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    let Some(user) = tokens.authenticate(authorization) else {
+        return (StatusCode::UNAUTHORIZED, "missing or invalid token").into_response();
+    };
+    if user.role < role {
+        return (StatusCode::FORBIDDEN, "the role of the token isn't allowed to do this").into_response();
+    }
+    request.extensions_mut().insert(user);
+    next.run(request).await
+}
+
+/// Appends what a user did to the audit log named by `SENSLEAK_AUDIT_LOG`, or `audit.log`, as a
+/// line of JSON with the time, the user and the details.
+pub fn audit(user: Option<&ApiUser>, action: &str, details: Value) {
+    let path = std::env::var(AUDIT_LOG_VAR).unwrap_or_else(|_| DEFAULT_AUDIT_LOG.to_string());
+    let line = audit_entry(user, action, details, &Local::now().to_rfc3339());
+    let written = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .and_then(|mut file| file.write_all(format!("{}\n", line).as_bytes()));
+    if let Err(err) = written {
+        eprintln!("Application: Failed to write the audit log {}: {}", path, err);
+    }
+}
+
+/// Returns the entry of the audit log of an action, the user being `null` without tokens.
+fn audit_entry(user: Option<&ApiUser>, action: &str, details: Value, time: &str) -> Value {
+    let mut entry = json!({
+        "time": time,
+        "user": user.map(|user| user.name.as_str()),
+        "role": user.map(|user| user.role),
+        "action": action,
+    });
+    if let (Some(entry), Value::Object(details)) = (entry.as_object_mut(), details) {
+        entry.extend(details);
+    }
+    entry
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOKENS: &str = r#"
+        [[tokens]]
+        name = "alice"
+        role = "admin"
+        sha256 = "9F86D081884C7D659A2FEAA0C55AD015A3BF4F1B2B0B822CD15D6C15B0F00A08"
+
+        [[tokens]]
+        name = "ci"
+        role = "scanner"
+        sha256 = "60303ae22b998861bce3b28f33eec1be758a213c86c93c076dbe9f558c11c752"
+    "#;
+
+    #[test]
+    fn test_authenticate() {
+        let tokens = ApiTokens::parse(TOKENS).unwrap();
+        assert_eq!(
+            tokens.authenticate("Bearer test"),
+            Some(ApiUser { name: "alice".to_string(), role: ApiRole::Admin })
+        );
+        assert_eq!(
+            tokens.authenticate("Bearer test2"),
+            Some(ApiUser { name: "ci".to_string(), role: ApiRole::Scanner })
+        );
+        assert_eq!(tokens.authenticate("Bearer wrong"), None);
+        assert_eq!(tokens.authenticate("test"), None);
+        assert_eq!(tokens.authenticate(""), None);
+
+        let invalid = TOKENS.replace("60303ae22b99", "60303ae22b9");
+        assert!(ApiTokens::parse(&invalid).unwrap_err().contains("ci"));
+        assert!(ApiTokens::parse(&TOKENS.replace("scanner", "owner")).is_err());
+    }
+
+    #[test]
+    fn test_required_role() {
+        assert_eq!(required_role("/dashboard"), None);
+        assert_eq!(required_role("/swagger-ui/index.html"), None);
+        assert_eq!(required_role("/api-docs/openapi.json"), None);
+        assert_eq!(required_role("/leaks/list"), Some(ApiRole::Viewer));
+        assert_eq!(required_role("/scan"), Some(ApiRole::Scanner));
+        assert_eq!(required_role("/leaks/set_status"), Some(ApiRole::Admin));
+        assert_eq!(required_role("/rules/update"), Some(ApiRole::Admin));
+        assert!(ApiRole::Admin > ApiRole::Scanner && ApiRole::Scanner > ApiRole::Viewer);
+    }
+
+    #[test]
+    fn test_audit_entry() {
+        let user = ApiUser { name: "alice".to_string(), role: ApiRole::Admin };
+        let details = json!({"id": 3, "status": "rotated"});
+        assert_eq!(
+            audit_entry(Some(&user), "set_status", details, "2024-06-01T12:00:00+00:00"),
+            json!({
+                "time": "2024-06-01T12:00:00+00:00",
+                "user": "alice",
+                "role": "admin",
+                "action": "set_status",
+                "id": 3,
+                "status": "rotated",
+            })
+        );
+        let entry = audit_entry(None, "scan", json!({"repo": "acme/api"}), "2024-06-01T12:00:00+00:00");
+        assert_eq!(entry["user"], Value::Null);
+        assert_eq!(entry["repo"], "acme/api");
+    }
+}
@@ -0,0 +1,494 @@
+use crate::errors::CustomError;
+use crate::models::{CiMode, Config, Leak, Provider, ReportFormat, ReportOutput, ScanTarget};
+use git2::{Oid, Repository};
+use serde_json::Value;
+use std::collections::HashSet;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+
+/// The commit CIs give as the previous commit of the first push of a branch.
+const NULL_COMMIT: &str = "0000000000000000000000000000000000000000";
+
+/// The commits `--new-only` scans: those HEAD has and the base of the pull request or push doesn't.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NewCommits {
+    /// Where HEAD branched off the base, whose tree has the leaks that aren't new.
+    pub base: Oid,
+
+    /// The new commits, newest first.
+    pub commits: Vec<String>,
+}
+
+/// The pull request or push a GitHub Actions workflow runs for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GithubEvent {
+    /// The name of the event, e.g. `pull_request` or `push`.
+    pub name: String,
+
+    /// The commit the new commits are compared to: the target branch of the pull request, or the
+    /// commit before the push. `None` for the first push of a branch and for other events.
+    pub base: Option<String>,
+
+    /// The newest commit of the pull request or push.
+    pub head: Option<String>,
+
+    /// The number of the pull request.
+    pub pull_request: Option<u64>,
+}
+
+impl GithubEvent {
+    /// Reads the event from the payload in `GITHUB_EVENT_PATH` and the variables of the workflow.
+    ///
+    /// # Arguments
+    ///
+    /// * `var` - Looks up an environment variable, `std::env::var` outside of tests.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CustomError::CiError` outside GitHub Actions, or if the payload can't be read.
+    pub fn from_env(var: impl Fn(&str) -> Option<String>) -> Result<Self, CustomError> {
+        let error = |reason: String| CustomError::CiError { reason };
+        if var("GITHUB_ACTIONS").as_deref() != Some("true") {
+            return Err(error("not running in GitHub Actions".to_string()));
+        }
+        let name = var("GITHUB_EVENT_NAME").ok_or_else(|| error("GITHUB_EVENT_NAME isn't set".to_string()))?;
+        let event: Value = match var("GITHUB_EVENT_PATH") {
+            Some(path) => {
+                let payload = fs::read_to_string(&path).map_err(|e| error(format!("{}: {}", path, e)))?;
+                serde_json::from_str(&payload).map_err(|e| error(format!("{}: {}", path, e)))?
+            }
+            None => Value::Null,
+        };
+        let sha = |value: &Value| {
+            value.as_str().filter(|sha| !sha.is_empty() && *sha != NULL_COMMIT).map(String::from)
+        };
+        let pull_request = &event["pull_request"];
+        Ok(GithubEvent {
+            name,
+            base: sha(&pull_request["base"]["sha"]).or_else(|| sha(&event["before"])),
+            head: sha(&pull_request["head"]["sha"])
+                .or_else(|| sha(&event["after"]))
+                .or_else(|| var("GITHUB_SHA")),
+            pull_request: pull_request["number"].as_u64(),
+        })
+    }
+}
+
+/// Returns the CI a job runs in, for `--ci auto`, from the variables each CI sets.
+pub fn detect_ci(var: impl Fn(&str) -> Option<String>) -> Option<CiMode> {
+    if var("GITHUB_ACTIONS").as_deref() == Some("true") {
+        Some(CiMode::Github)
+    } else if var("GITLAB_CI").as_deref() == Some("true") {
+        Some(CiMode::Gitlab)
+    } else if var("JENKINS_URL").is_some() {
+        Some(CiMode::Jenkins)
+    } else {
+        None
+    }
+}
+
+/// Sets a scan up to run as a step of the CI of `--ci`, replacing `--ci auto` with the CI found:
+///
+/// - unless it is given another target, it only scans the new commits of the pull request or push
+///   the job runs for, as `--new-only` does. Jobs for other events, and first pushes of branches,
+///   scan the whole history;
+/// - `--pr-comment` comments on the pull request of GitHub or GitLab;
+/// - without `--report`, GitLab jobs write a Code Quality report to `gl-code-quality-report.json`
+///   and Jenkins jobs a SARIF report to `sensleak-report.sarif`, for their merge request widget
+///   and the Warnings Next Generation plugin.
+///
+/// # Arguments
+///
+/// * `config` - The configuration, changed in place.
+/// * `var` - Looks up an environment variable, `std::env::var` outside of tests.
+///
+/// # Errors
+///
+/// Returns `CustomError::CiError` if the job doesn't run in the CI or its event can't be read.
+pub fn configure_ci(config: &mut Config, var: impl Fn(&str) -> Option<String>) -> Result<(), CustomError> {
+    let error = |reason: &str| CustomError::CiError { reason: reason.to_string() };
+    let ci = match config.ci {
+        None => return Ok(()),
+        Some(CiMode::Auto) => {
+            detect_ci(&var).ok_or_else(|| error("not running in GitHub Actions, GitLab CI or Jenkins"))?
+        }
+        Some(ci) => ci,
+    };
+    let (base, provider, report) = match ci {
+        CiMode::Github => (GithubEvent::from_env(&var)?.base, Some(Provider::Github), None),
+        CiMode::Gitlab if detect_ci(&var) == Some(CiMode::Gitlab) => (
+            base_ref_from_env(&var),
+            Some(Provider::Gitlab),
+            Some((ReportFormat::CodeQuality, "gl-code-quality-report.json")),
+        ),
+        CiMode::Gitlab => return Err(error("not running in GitLab CI")),
+        CiMode::Jenkins if var("JENKINS_URL").is_some() => (
+            base_ref_from_env(&var),
+            None,
+            Some((ReportFormat::Sarif, "sensleak-report.sarif")),
+        ),
+        CiMode::Jenkins => return Err(error("not running in Jenkins")),
+        CiMode::Auto => unreachable!("the CI was detected above"),
+    };
+    config.ci = Some(ci);
+    if config.target() == ScanTarget::All {
+        if config.base_ref.is_none() {
+            config.base_ref = base;
+        }
+        config.new_only |= config.base_ref.is_some();
+    }
+    if config.pr_comment && config.provider.is_none() {
+        config.provider = provider;
+    }
+    if let (true, Some((format, path))) = (config.report.is_empty(), report) {
+        config.report.push(ReportOutput {
+            format: Some(format),
+            path: path.to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// Reports the leaks of a scan to the CI of `--ci`. On GitHub Actions, each leak becomes an
+/// annotation of its file and line, and the outputs of the step are set.
+///
+/// # Arguments
+///
+/// * `ci` - The CI.
+/// * `leaks` - The leaks of the scan.
+/// * `report` - The report file written, if any.
+/// * `var` - Looks up an environment variable, `std::env::var` outside of tests.
+///
+/// # Errors
+///
+/// This function returns an error if the annotations or outputs can't be written.
+pub fn finish_ci(
+    ci: CiMode,
+    leaks: &[Leak],
+    report: Option<&str>,
+    var: impl Fn(&str) -> Option<String>,
+) -> io::Result<()> {
+    match ci {
+        CiMode::Github => {
+            write_github_annotations_to(io::stdout().lock(), leaks)?;
+            if let Some(path) = var("GITHUB_OUTPUT") {
+                let outputs = OpenOptions::new().create(true).append(true).open(path)?;
+                write_github_outputs_to(outputs, leaks.len(), report)?;
+            }
+            Ok(())
+        }
+        // Their reports are written like any other
+        CiMode::Gitlab | CiMode::Jenkins | CiMode::Auto => Ok(()),
+    }
+}
+
+/// Writes the leaks as workflow commands GitHub Actions turns into annotations of the files and
+/// lines of the pull request, e.g. `::error file=src/config.py,line=3,col=9,title=stripe-access-token::...`.
+/// Every leak is an error, whatever the severity of its rule, since a secret in a pull request is
+/// never only a warning. The annotations name the rule and the commit, never the secret.
+pub fn write_github_annotations_to(mut writer: impl Write, leaks: &[Leak]) -> io::Result<()> {
+    for leak in leaks {
+        let mut message = leak.rule.clone();
+        if !leak.commit.is_empty() {
+            message.push_str(&format!(" in commit {}", leak.commit));
+        }
+        writeln!(
+            writer,
+            "::error file={},line={},col={},title={}::{}",
+            escape_property(leak.file.trim_start_matches('/')),
+            leak.line_number,
+            leak.column.max(1),
+            escape_property(&leak.rule_id),
+            escape_data(&message)
+        )?;
+    }
+    Ok(())
+}
+
+/// Writes the outputs of the step, in the format of `GITHUB_OUTPUT`: `leaks-found`, the number of
+/// leaks, and `report-path`, the report file written, empty without one.
+pub fn write_github_outputs_to(mut writer: impl Write, leaks: usize, report: Option<&str>) -> io::Result<()> {
+    writeln!(writer, "leaks-found={}", leaks)?;
+    writeln!(writer, "report-path={}", report.unwrap_or_default())
+}
+
+/// Escapes the message of a workflow command.
+fn escape_data(data: &str) -> String {
+    data.replace('%', "%25").replace('\r', "%0D").replace('\n', "%0A")
+}
+
+/// Escapes a property of a workflow command, e.g. `file=`.
+fn escape_property(property: &str) -> String {
+    escape_data(property).replace(':', "%3A").replace(',', "%2C")
+}
+
+/// Finds the base the new commits of a pull request or push are compared to, from the variables
+/// of the CI: the target branch of the pull request on GitHub Actions, GitLab CI, Jenkins, Azure
+/// Pipelines and Bitbucket Pipelines, the commit before the push on GitLab CI, or the commit of
+/// the last successful build of the branch on Jenkins.
+///
+/// # Arguments
+///
+/// * `var` - Looks up an environment variable, `std::env::var` outside of tests.
+///
+/// # Returns
+///
+/// Returns the branch, e.g. `origin/main`, or the commit, or `None` outside pull requests and
+/// pushes of known CIs.
+pub fn base_ref_from_env(var: impl Fn(&str) -> Option<String>) -> Option<String> {
+    let var = |name: &str| var(name).filter(|value| !value.is_empty());
+    let branch = |name: &str| {
+        var(name).map(|branch| format!("origin/{}", branch.trim_start_matches("refs/heads/")))
+    };
+    branch("GITHUB_BASE_REF")
+        .or_else(|| var("CI_MERGE_REQUEST_DIFF_BASE_SHA"))
+        .or_else(|| branch("CI_MERGE_REQUEST_TARGET_BRANCH_NAME"))
+        .or_else(|| branch("CHANGE_TARGET"))
+        .or_else(|| branch("SYSTEM_PULLREQUEST_TARGETBRANCH"))
+        .or_else(|| branch("BITBUCKET_PR_DESTINATION_BRANCH"))
+        .or_else(|| var("CI_COMMIT_BEFORE_SHA").filter(|commit| commit != NULL_COMMIT))
+        .or_else(|| var("GIT_PREVIOUS_SUCCESSFUL_COMMIT"))
+}
+
+/// Lists the commits of HEAD since it branched off a base.
+///
+/// # Arguments
+///
+/// * `repo` - The repository.
+/// * `base_ref` - The branch, tag or commit, e.g. `origin/main`. A remote branch that wasn't
+///   fetched is looked up as a local branch too.
+///
+/// # Errors
+///
+/// Returns `CustomError::BaseRefError` if the base isn't in the repository or has no history
+/// in common with HEAD.
+pub fn new_commits(repo: &Repository, base_ref: &str) -> Result<NewCommits, CustomError> {
+    let error = |reason: String| CustomError::BaseRefError { reason };
+    let local = base_ref.strip_prefix("origin/").unwrap_or(base_ref);
+    let base = repo
+        .revparse_single(base_ref)
+        .or_else(|_| repo.revparse_single(local))
+        .and_then(|object| object.peel_to_commit())
+        .map_err(|_| error(format!("{} isn't in the repository", base_ref)))?;
+    let head = repo
+        .head()
+        .and_then(|head| head.peel_to_commit())
+        .map_err(|e| error(e.message().to_string()))?;
+    let base = repo
+        .merge_base(base.id(), head.id())
+        .map_err(|_| error(format!("HEAD has no history in common with {}", base_ref)))?;
+    let mut walk = repo.revwalk().map_err(|e| error(e.message().to_string()))?;
+    walk.push(head.id())
+        .and_then(|_| walk.hide(base))
+        .map_err(|e| error(e.message().to_string()))?;
+    let commits = walk
+        .map(|oid| oid.map(|oid| oid.to_string()))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| error(e.message().to_string()))?;
+    Ok(NewCommits { base, commits })
+}
+
+/// Drops the leaks the base already has, by their rule and secret, so that only leaks the new
+/// commits add are left, wherever they moved to.
+pub fn drop_base_leaks(leaks: &mut Vec<Leak>, base_leaks: &[Leak]) {
+    let known: HashSet<(&str, &str)> = base_leaks
+        .iter()
+        .map(|leak| (leak.rule_id.as_str(), leak.offender.as_str()))
+        .collect();
+    leaks.retain(|leak| !known.contains(&(leak.rule_id.as_str(), leak.offender.as_str())));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::fs;
+    use std::path::Path;
+
+    #[test]
+    fn test_base_ref_from_env() {
+        let base_ref = |vars: &[(&str, &str)]| {
+            let vars: HashMap<&str, String> =
+                vars.iter().map(|(name, value)| (*name, value.to_string())).collect();
+            base_ref_from_env(|name| vars.get(name).cloned())
+        };
+        assert_eq!(base_ref(&[("GITHUB_BASE_REF", "main")]).as_deref(), Some("origin/main"));
+        // Pushes on GitHub Actions set it empty
+        assert_eq!(base_ref(&[("GITHUB_BASE_REF", "")]), None);
+        assert_eq!(
+            base_ref(&[
+                ("CI_MERGE_REQUEST_TARGET_BRANCH_NAME", "develop"),
+                ("CI_MERGE_REQUEST_DIFF_BASE_SHA", "9fceb02"),
+            ])
+            .as_deref(),
+            Some("9fceb02")
+        );
+        assert_eq!(
+            base_ref(&[("SYSTEM_PULLREQUEST_TARGETBRANCH", "refs/heads/release")]).as_deref(),
+            Some("origin/release")
+        );
+        assert_eq!(base_ref(&[("CHANGE_TARGET", "main")]).as_deref(), Some("origin/main"));
+        assert_eq!(base_ref(&[("CI_COMMIT_BEFORE_SHA", NULL_COMMIT)]), None);
+        assert_eq!(base_ref(&[]), None);
+    }
+
+    #[test]
+    fn test_github_event() {
+        let dir = tempfile::tempdir().unwrap();
+        let event = dir.path().join("event.json");
+        fs::write(
+            &event,
+            r#"{"pull_request": {"number": 42, "base": {"sha": "1111111"}, "head": {"sha": "2222222"}}}"#,
+        )
+        .unwrap();
+        let mut vars = HashMap::from([
+            ("GITHUB_ACTIONS", "true".to_string()),
+            ("GITHUB_EVENT_NAME", "pull_request".to_string()),
+            ("GITHUB_EVENT_PATH", event.to_string_lossy().into_owned()),
+        ]);
+        assert_eq!(
+            GithubEvent::from_env(|name| vars.get(name).cloned()).unwrap(),
+            GithubEvent {
+                name: "pull_request".to_string(),
+                base: Some("1111111".to_string()),
+                head: Some("2222222".to_string()),
+                pull_request: Some(42),
+            }
+        );
+
+        let mut config = Config { ci: Some(CiMode::Github), pr_comment: true, ..Default::default() };
+        configure_ci(&mut config, |name| vars.get(name).cloned()).unwrap();
+        assert!(config.new_only);
+        assert_eq!(config.base_ref.as_deref(), Some("1111111"));
+        assert_eq!(config.provider, Some(Provider::Github));
+
+        // The first push of a branch has no commit before it
+        fs::write(&event, format!(r#"{{"before": "{}", "after": "3333333"}}"#, NULL_COMMIT)).unwrap();
+        vars.insert("GITHUB_EVENT_NAME", "push".to_string());
+        let push = GithubEvent::from_env(|name| vars.get(name).cloned()).unwrap();
+        assert_eq!((push.base, push.head.as_deref()), (None, Some("3333333")));
+        let mut config = Config { ci: Some(CiMode::Github), ..Default::default() };
+        configure_ci(&mut config, |name| vars.get(name).cloned()).unwrap();
+        assert!(!config.new_only);
+
+        assert!(GithubEvent::from_env(|_| None).is_err());
+    }
+
+    #[test]
+    fn test_configure_ci() {
+        let vars = HashMap::from([
+            ("GITLAB_CI", "true".to_string()),
+            ("CI_MERGE_REQUEST_TARGET_BRANCH_NAME", "main".to_string()),
+        ]);
+        let mut config = Config { ci: Some(CiMode::Auto), ..Default::default() };
+        configure_ci(&mut config, |name| vars.get(name).cloned()).unwrap();
+        assert_eq!(config.ci, Some(CiMode::Gitlab));
+        assert_eq!((config.new_only, config.base_ref.as_deref()), (true, Some("origin/main")));
+        assert_eq!(
+            config.report,
+            vec![ReportOutput {
+                format: Some(ReportFormat::CodeQuality),
+                path: "gl-code-quality-report.json".to_string(),
+            }]
+        );
+
+        // A scan given its commits and report keeps them
+        let vars = HashMap::from([
+            ("JENKINS_URL", "https://ci.example.com/".to_string()),
+            ("GIT_PREVIOUS_SUCCESSFUL_COMMIT", "9fceb02".to_string()),
+        ]);
+        let report: ReportOutput = "leaks.json".parse().unwrap();
+        let mut config = Config {
+            ci: Some(CiMode::Jenkins),
+            commit: Some("1111111".to_string()),
+            report: vec![report.clone()],
+            ..Default::default()
+        };
+        configure_ci(&mut config, |name| vars.get(name).cloned()).unwrap();
+        assert!(!config.new_only);
+        assert_eq!(config.report, vec![report]);
+
+        // A pull request on GitHub Actions only scans, and fails on, the leaks it brings in
+        let dir = tempfile::tempdir().unwrap();
+        let payload = dir.path().join("event.json");
+        fs::write(&payload, r#"{"pull_request": {"number": 7, "base": {"sha": "9fceb02"}}}"#).unwrap();
+        let github = HashMap::from([
+            ("GITHUB_ACTIONS", "true".to_string()),
+            ("GITHUB_EVENT_NAME", "pull_request".to_string()),
+            ("GITHUB_EVENT_PATH", payload.to_str().unwrap().to_string()),
+        ]);
+        let mut config = Config { ci: Some(CiMode::Github), ..Default::default() };
+        configure_ci(&mut config, |name| github.get(name).cloned()).unwrap();
+        assert_eq!((config.new_only, config.base_ref.as_deref()), (true, Some("9fceb02")));
+
+        let mut config = Config { ci: Some(CiMode::Gitlab), ..Default::default() };
+        assert!(configure_ci(&mut config, |name| vars.get(name).cloned()).is_err());
+        let mut config = Config { ci: Some(CiMode::Auto), ..Default::default() };
+        assert!(configure_ci(&mut config, |_| None).is_err());
+    }
+
+    #[test]
+    fn test_write_github_annotations_to() {
+        let leak = Leak {
+            rule: "Stripe, live key".to_string(),
+            rule_id: "stripe-access-token".to_string(),
+            offender: "sk_live_1234567890".to_string(),
+            file: "/config/app,prod.py".to_string(),
+            commit: "9fceb02".to_string(),
+            line_number: 3,
+            column: 9,
+            severity: Some("critical".to_string()),
+            ..Default::default()
+        };
+        // Leaks of rules without a severity, or a low one, are errors too
+        let unrated = Leak { severity: None, ..leak.clone() };
+        let low = Leak { severity: Some("low".to_string()), ..leak.clone() };
+        let mut annotations = Vec::new();
+        write_github_annotations_to(&mut annotations, &[leak, unrated, low]).unwrap();
+        assert_eq!(
+            String::from_utf8(annotations).unwrap(),
+            "::error file=config/app%2Cprod.py,line=3,col=9,title=stripe-access-token::Stripe, live key in commit 9fceb02\n"
+                .repeat(3)
+        );
+
+        let mut outputs = Vec::new();
+        write_github_outputs_to(&mut outputs, 2, Some("leaks.json")).unwrap();
+        assert_eq!(String::from_utf8(outputs).unwrap(), "leaks-found=2\nreport-path=leaks.json\n");
+    }
+
+    #[test]
+    fn test_new_commits() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let signature = git2::Signature::now("John Doe", "johndoe@example.com").unwrap();
+        let commit = |content: &str| {
+            fs::write(dir.path().join("a.txt"), content).unwrap();
+            let mut index = repo.index().unwrap();
+            index.add_path(Path::new("a.txt")).unwrap();
+            let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+            let parent = repo.head().ok().map(|head| head.peel_to_commit().unwrap());
+            let parents: Vec<&git2::Commit> = parent.iter().collect();
+            repo.commit(Some("HEAD"), &signature, &signature, content, &tree, &parents)
+                .unwrap()
+        };
+        let base = commit("old_secret");
+        repo.branch("main", &repo.find_commit(base).unwrap(), false).unwrap();
+        let first = commit("old_secret new_secret");
+        let second = commit("new_secret");
+
+        let new = new_commits(&repo, "origin/main").unwrap();
+        assert_eq!(new.base, base);
+        assert_eq!(new.commits, vec![second.to_string(), first.to_string()]);
+        assert!(new_commits(&repo, "origin/missing").is_err());
+
+        let leak = |offender: &str| Leak {
+            offender: offender.to_string(),
+            rule_id: "generic".to_string(),
+            ..Default::default()
+        };
+        let mut leaks = vec![leak("old_secret"), leak("new_secret")];
+        drop_base_leaks(&mut leaks, &[leak("old_secret")]);
+        let offenders: Vec<&str> = leaks.iter().map(|leak| leak.offender.as_str()).collect();
+        assert_eq!(offenders, vec!["new_secret"]);
+    }
+}
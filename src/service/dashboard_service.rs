@@ -0,0 +1,225 @@
+use crate::models::{scan_record, LeakFilter, LeakStatus, Model, SEVERITIES};
+use crate::service::db_service::{list_leaks, list_scans};
+use sea_orm::DbErr;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use utoipa::ToSchema;
+
+/// How many of the most recent scans the dashboard lists.
+const SCAN_HISTORY: usize = 20;
+
+/// How many days, of those with scans or resolved leaks, the trend covers.
+const TREND_DAYS: usize = 30;
+
+/// What the dashboard of the server shows, from the leaks and scans stored with `--to-db`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct DashboardSummary {
+    /// The repositories with leaks or scans, by name.
+    pub repos: Vec<RepoSummary>,
+
+    /// The most recent scans, the most recent first.
+    #[schema(value_type = Vec<ScanRecord>)]
+    pub scans: Vec<scan_record::Model>,
+
+    /// The number of unresolved leaks of every severity, from the highest, then of the rules
+    /// without one as `none`.
+    pub open_by_severity: Vec<SeverityCount>,
+
+    /// What was found and resolved each day, from the oldest.
+    pub trend: Vec<TrendPoint>,
+}
+
+/// The leaks of a repository.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct RepoSummary {
+    /// The name of the repository.
+    pub repo: String,
+
+    /// The number of leaks neither rotated nor false positives.
+    pub open: usize,
+
+    /// The number of leaks stored.
+    pub leaks: usize,
+
+    /// When the repository was last scanned, in RFC 3339.
+    pub last_scan: Option<String>,
+}
+
+/// The number of unresolved leaks of a severity.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct SeverityCount {
+    pub severity: String,
+    pub open: usize,
+}
+
+/// What happened on a day.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, ToSchema)]
+pub struct TrendPoint {
+    /// The day, e.g. `2024-06-01`.
+    pub day: String,
+
+    /// The number of scans started.
+    pub scans: usize,
+
+    /// The number of leaks the scans found.
+    pub found: usize,
+
+    /// The number of leaks rotated or set as false positives.
+    pub resolved: usize,
+}
+
+/// Returns the summary of the leaks and scans stored in the database.
+///
+/// # Errors
+///
+/// Returns the error of the database if it can't be reached or queried.
+pub async fn dashboard_summary() -> Result<DashboardSummary, DbErr> {
+    let leaks = list_leaks(&LeakFilter::default()).await?;
+    let scans = list_scans().await?;
+    Ok(summarize(&leaks, &scans))
+}
+
+/// Summarizes stored leaks and scans, the scans being the most recent first.
+pub fn summarize(leaks: &[Model], scans: &[scan_record::Model]) -> DashboardSummary {
+    let is_resolved = |leak: &Model| {
+        leak.status == LeakStatus::Rotated.as_str() || leak.status == LeakStatus::FalsePositive.as_str()
+    };
+
+    let mut repos: BTreeMap<&str, RepoSummary> = BTreeMap::new();
+    let new_repo = |repo: &str| RepoSummary { repo: repo.to_string(), open: 0, leaks: 0, last_scan: None };
+    for leak in leaks {
+        let summary = repos.entry(&leak.repo).or_insert_with(|| new_repo(&leak.repo));
+        summary.leaks += 1;
+        summary.open += usize::from(!is_resolved(leak));
+    }
+    for scan in scans {
+        let summary = repos.entry(&scan.repo).or_insert_with(|| new_repo(&scan.repo));
+        if summary.last_scan.as_ref().is_none_or(|last| last < &scan.started_at) {
+            summary.last_scan = Some(scan.started_at.clone());
+        }
+    }
+
+    let mut open_by_severity: Vec<SeverityCount> = SEVERITIES
+        .iter()
+        .rev()
+        .map(|severity| SeverityCount { severity: severity.to_string(), open: 0 })
+        .collect();
+    for leak in leaks.iter().filter(|leak| !is_resolved(leak)) {
+        let severity = leak
+            .severity
+            .as_deref()
+            .filter(|severity| SEVERITIES.iter().any(|known| known.eq_ignore_ascii_case(severity)))
+            .map_or("none".to_string(), str::to_lowercase);
+        match open_by_severity.iter_mut().find(|count| count.severity == severity) {
+            Some(count) => count.open += 1,
+            None => open_by_severity.push(SeverityCount { severity, open: 1 }),
+        }
+    }
+
+    let mut days = BTreeMap::new();
+    for scan in scans {
+        let point = trend_point(&mut days, &scan.started_at);
+        point.scans += 1;
+        point.found += scan.leaks.max(0) as usize;
+    }
+    for updated_at in leaks.iter().filter(|leak| is_resolved(leak)).filter_map(|leak| leak.updated_at.as_deref()) {
+        trend_point(&mut days, updated_at).resolved += 1;
+    }
+    let trend: Vec<TrendPoint> = days.into_values().collect();
+
+    DashboardSummary {
+        repos: repos.into_values().collect(),
+        scans: scans.iter().take(SCAN_HISTORY).cloned().collect(),
+        open_by_severity,
+        trend: trend[trend.len().saturating_sub(TREND_DAYS)..].to_vec(),
+    }
+}
+
+/// Returns the point of the trend of the day of a date in RFC 3339, which starts with the day.
+fn trend_point<'a, 'b>(days: &'b mut BTreeMap<&'a str, TrendPoint>, date: &'a str) -> &'b mut TrendPoint {
+    let day = date.get(..10).unwrap_or(date);
+    days.entry(day).or_insert_with(|| TrendPoint { day: day.to_string(), ..Default::default() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leak(repo: &str, status: LeakStatus, severity: Option<&str>, updated_at: Option<&str>) -> Model {
+        Model {
+            id: 0,
+            line: String::new(),
+            line_number: 1,
+            offender: String::new(),
+            commit: String::new(),
+            repo: repo.to_string(),
+            rule: String::new(),
+            commit_message: String::new(),
+            author: String::new(),
+            email: String::new(),
+            file: String::new(),
+            date: String::new(),
+            status: status.as_str().to_string(),
+            assignee: None,
+            note: None,
+            updated_at: updated_at.map(String::from),
+            severity: severity.map(String::from),
+        }
+    }
+
+    fn scan(repo: &str, started_at: &str, leaks: i32) -> scan_record::Model {
+        scan_record::Model {
+            id: 0,
+            repo: repo.to_string(),
+            refs: "HEAD".to_string(),
+            started_at: started_at.to_string(),
+            duration_ms: 1200,
+            commits: 10,
+            leaks,
+        }
+    }
+
+    #[test]
+    fn test_summarize() {
+        let leaks = [
+            leak("api", LeakStatus::Open, Some("High"), None),
+            leak("api", LeakStatus::Triaged, None, Some("2024-06-02T09:00:00+00:00")),
+            leak("api", LeakStatus::Rotated, Some("critical"), Some("2024-06-02T10:00:00+00:00")),
+            leak("web", LeakStatus::FalsePositive, Some("low"), Some("2024-06-03T10:00:00+00:00")),
+        ];
+        let scans = [
+            scan("web", "2024-06-02T12:00:00+00:00", 1),
+            scan("api", "2024-06-02T08:00:00+00:00", 0),
+            scan("api", "2024-06-01T08:00:00+00:00", 3),
+        ];
+        let summary = summarize(&leaks, &scans);
+
+        assert_eq!(
+            summary.repos,
+            [
+                RepoSummary {
+                    repo: "api".to_string(),
+                    open: 2,
+                    leaks: 3,
+                    last_scan: Some("2024-06-02T08:00:00+00:00".to_string()),
+                },
+                RepoSummary {
+                    repo: "web".to_string(),
+                    open: 0,
+                    leaks: 1,
+                    last_scan: Some("2024-06-02T12:00:00+00:00".to_string()),
+                },
+            ]
+        );
+        let open: Vec<(&str, usize)> =
+            summary.open_by_severity.iter().map(|count| (count.severity.as_str(), count.open)).collect();
+        assert_eq!(open, [("critical", 0), ("high", 1), ("medium", 0), ("low", 0), ("info", 0), ("none", 1)]);
+        let trend: Vec<(&str, usize, usize, usize)> = summary
+            .trend
+            .iter()
+            .map(|point| (point.day.as_str(), point.scans, point.found, point.resolved))
+            .collect();
+        assert_eq!(trend, [("2024-06-01", 1, 3, 0), ("2024-06-02", 2, 1, 1), ("2024-06-03", 0, 0, 1)]);
+        assert_eq!(summary.scans, scans);
+    }
+}
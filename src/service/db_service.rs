@@ -1,7 +1,12 @@
-use crate::models::{ConnectDbConfig, Entity as Leaks, Leak};
+use crate::models::{
+    scan_record, ActiveModel, Column, ConnectDbConfig, Entity as Leaks, Leak, LeakFilter, LeakStatus, LeaksCommand,
+    ListFormat, Model, ReportMetadata,
+};
+use crate::utils::log_util::is_quiet;
 use chrono::Local;
 use sea_orm::*;
 use std::env;
+use std::error::Error;
 
 /// Sets up the database connection using the application's configuration settings.
 ///
@@ -21,10 +26,38 @@ pub async fn set_up_db() -> Result<DatabaseConnection, DbErr> {
     Ok(db)
 }
 
+/// Creates the `leaks` table if it doesn't exist, and adds the columns of the triage and the
+/// severity to a table created by an earlier version.
+async fn ensure_leaks_table(db: &DatabaseConnection) -> Result<(), DbErr> {
+    let builder = db.get_database_backend();
+    let schema = Schema::new(builder);
+
+    let stmt = schema
+        .create_table_from_entity(Leaks)
+        .if_not_exists()
+        .to_owned();
+    db.execute(builder.build(&stmt)).await?;
+
+    let stmt = sea_query::Table::alter()
+        .table(Leaks)
+        .add_column_if_not_exists(
+            sea_query::ColumnDef::new(Column::Status).string().not_null().default(LeakStatus::Open.as_str()),
+        )
+        .add_column_if_not_exists(sea_query::ColumnDef::new(Column::Assignee).string().null())
+        .add_column_if_not_exists(sea_query::ColumnDef::new(Column::Note).string().null())
+        .add_column_if_not_exists(sea_query::ColumnDef::new(Column::UpdatedAt).string().null())
+        .add_column_if_not_exists(sea_query::ColumnDef::new(Column::Severity).string().null())
+        .to_owned();
+    db.execute(builder.build(&stmt)).await?;
+    Ok(())
+}
+
 /// Inserts a vector of `Leak` entities into the database and ensures that the `Leaks` table exists.
 ///
 /// This function first checks if the `Leaks` table exists in the database and creates it if not.
 /// Then, it proceeds to insert the provided vector of `Leak` entities into the `Leaks` table.
+/// Leaks already stored by an earlier scan, at the same line of the same commit, are left as
+/// they are, so that they keep their assignee and status.
 ///
 /// # Arguments
 ///
@@ -42,41 +75,232 @@ pub async fn insert_leaks(_leaks: &[Leak]) -> Result<(), DbErr> {
     };
 
     // Check if the table Leaks exists and create it if not
-    let builder = db.get_database_backend();
-    let schema = Schema::new(builder);
-
-    let stmt = schema
-        .create_table_from_entity(Leaks)
-        .if_not_exists()
-        .to_owned();
+    ensure_leaks_table(&db).await?;
 
-    let stmt = builder.build(&stmt);
-
-    db.execute(stmt).await?;
-      
-    println!(
-        "\x1b[34m[INFO]\x1b[0m[{}] Create Success ...",
-        Local::now().format("%Y-%m-%d %H:%M:%S"),
-    );
+    if !is_quiet() {
+        println!(
+            "\x1b[34m[INFO]\x1b[0m[{}] Create Success ...",
+            Local::now().format("%Y-%m-%d %H:%M:%S"),
+        );
+    }
 
     // Insert leaks
     for leak in _leaks.iter() {
+        let stored = Leaks::find()
+            .filter(Column::Repo.eq(leak.repo.as_str()))
+            .filter(Column::Commit.eq(leak.commit.as_str()))
+            .filter(Column::File.eq(leak.file.as_str()))
+            .filter(Column::LineNumber.eq(leak.line_number))
+            .filter(Column::Offender.eq(leak.offender.as_str()))
+            .one(&db)
+            .await?;
+        if stored.is_some() {
+            continue;
+        }
         let active_model = leak.to_active_model();
 
         let insert_result = Leaks::insert(active_model)
             .exec(&db)
             .await?;
-        println!("Inserted leak with result: {:?}", insert_result);
+        if !is_quiet() {
+            println!("Inserted leak with result: {:?}", insert_result);
+        }
     }
 
-    println!(
-        "\x1b[34m[INFO]\x1b[0m[{}] Insert Success ...",
-        Local::now().format("%Y-%m-%d %H:%M:%S"),
-    );
+    if !is_quiet() {
+        println!(
+            "\x1b[34m[INFO]\x1b[0m[{}] Insert Success ...",
+            Local::now().format("%Y-%m-%d %H:%M:%S"),
+        );
+    }
 
     Ok(())
 }
 
+/// Stores a scan in the `scans` table, creating it if it doesn't exist, so that the dashboard
+/// shows it in the history of the repository.
+///
+/// # Arguments
+///
+/// * `repo` - The name of the repository, as the leaks found in it have it.
+/// * `metadata` - How the scan was produced, with its commits and duration.
+/// * `leaks` - The number of leaks found.
+pub async fn insert_scan(repo: &str, metadata: &ReportMetadata, leaks: usize) -> Result<(), DbErr> {
+    let db = set_up_db().await?;
+    ensure_scans_table(&db).await?;
+    let scan = scan_record::ActiveModel {
+        repo: ActiveValue::set(repo.to_string()),
+        refs: ActiveValue::set(metadata.refs.clone()),
+        started_at: ActiveValue::set(metadata.started_at.clone()),
+        duration_ms: ActiveValue::set(metadata.duration_ms as i64),
+        commits: ActiveValue::set(metadata.commits as i32),
+        leaks: ActiveValue::set(leaks as i32),
+        ..Default::default()
+    };
+    scan_record::Entity::insert(scan).exec(&db).await?;
+    Ok(())
+}
+
+/// Returns the scans stored in the database, the most recent first.
+pub async fn list_scans() -> Result<Vec<scan_record::Model>, DbErr> {
+    let db = set_up_db().await?;
+    ensure_scans_table(&db).await?;
+    scan_record::Entity::find()
+        .order_by_desc(scan_record::Column::Id)
+        .all(&db)
+        .await
+}
+
+/// Creates the `scans` table if it doesn't exist.
+async fn ensure_scans_table(db: &DatabaseConnection) -> Result<(), DbErr> {
+    let builder = db.get_database_backend();
+    let stmt = Schema::new(builder)
+        .create_table_from_entity(scan_record::Entity)
+        .if_not_exists()
+        .to_owned();
+    db.execute(builder.build(&stmt)).await?;
+    Ok(())
+}
+
+/// Returns the leaks stored in the database that match a filter, by ID.
+///
+/// # Errors
+///
+/// Returns the error of the database if it can't be reached or queried.
+pub async fn list_leaks(filter: &LeakFilter) -> Result<Vec<Model>, DbErr> {
+    let db = set_up_db().await?;
+    ensure_leaks_table(&db).await?;
+    let mut query = Leaks::find().order_by_asc(Column::Id);
+    if let Some(repo) = &filter.repo {
+        query = query.filter(Column::Repo.eq(repo.as_str()));
+    }
+    if let Some(status) = filter.status {
+        query = query.filter(Column::Status.eq(status.as_str()));
+    }
+    if let Some(assignee) = &filter.assignee {
+        query = query.filter(Column::Assignee.eq(assignee.as_str()));
+    }
+    query.all(&db).await
+}
+
+/// Assigns a stored leak to a user, or unassigns it.
+///
+/// # Arguments
+///
+/// * `id` - The ID of the leak.
+/// * `assignee` - The user, or `None` to unassign the leak.
+///
+/// # Returns
+///
+/// The leak, as it is stored once assigned.
+///
+/// # Errors
+///
+/// Returns `DbErr::RecordNotFound` if there is no leak with the ID.
+pub async fn assign_leak(id: i32, assignee: Option<&str>) -> Result<Model, DbErr> {
+    update_leak(id, |leak| leak.assignee = ActiveValue::set(assignee.map(String::from))).await
+}
+
+/// Sets the status of a stored leak, and the note on how it was resolved if one is given.
+///
+/// # Returns
+///
+/// The leak, as it is stored once updated.
+///
+/// # Errors
+///
+/// Returns `DbErr::RecordNotFound` if there is no leak with the ID.
+pub async fn set_leak_status(id: i32, status: LeakStatus, note: Option<&str>) -> Result<Model, DbErr> {
+    update_leak(id, |leak| {
+        leak.status = ActiveValue::set(status.as_str().to_string());
+        if let Some(note) = note {
+            leak.note = ActiveValue::set(Some(note.to_string()));
+        }
+    })
+    .await
+}
+
+/// Updates a stored leak, recording when it was updated.
+async fn update_leak(id: i32, change: impl FnOnce(&mut ActiveModel)) -> Result<Model, DbErr> {
+    let db = set_up_db().await?;
+    ensure_leaks_table(&db).await?;
+    let leak = Leaks::find_by_id(id)
+        .one(&db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound(format!("there is no leak {}", id)))?;
+    let mut leak: ActiveModel = leak.into();
+    change(&mut leak);
+    leak.updated_at = ActiveValue::set(Some(Local::now().to_rfc3339()));
+    leak.update(&db).await
+}
+
+/// Runs a subcommand of `leaks`.
+///
+/// # Returns
+///
+/// What the subcommand prints: the leaks listed, or the leak updated.
+///
+/// # Errors
+///
+/// Returns the error of the database, e.g. `DbErr::RecordNotFound` for a leak that isn't stored.
+pub async fn track_leaks(command: &LeaksCommand) -> Result<String, Box<dyn Error>> {
+    match command {
+        LeaksCommand::List { repo, status, assignee, format } => {
+            let filter = LeakFilter { repo: repo.clone(), status: *status, assignee: assignee.clone() };
+            format_leaks(&list_leaks(&filter).await?, *format)
+        }
+        LeaksCommand::Assign { id, assignee } => {
+            let leak = assign_leak(*id, assignee.as_deref()).await?;
+            format_leaks(&[leak], ListFormat::Table)
+        }
+        LeaksCommand::Status { id, status, note } => {
+            let leak = set_leak_status(*id, *status, note.as_deref()).await?;
+            format_leaks(&[leak], ListFormat::Table)
+        }
+    }
+}
+
+/// Formats stored leaks as `rules list` does its rules, without their secrets in tables.
+pub fn format_leaks(leaks: &[Model], format: ListFormat) -> Result<String, Box<dyn Error>> {
+    if format == ListFormat::Json {
+        return Ok(format!("{}\n", serde_json::to_string_pretty(leaks)?));
+    }
+    let header = ["ID", "STATUS", "ASSIGNEE", "REPO", "FILE", "RULE", "NOTE"].map(String::from);
+    let rows: Vec<[String; 7]> = leaks
+        .iter()
+        .map(|leak| {
+            [
+                leak.id.to_string(),
+                leak.status.clone(),
+                leak.assignee.clone().unwrap_or_else(|| "-".to_string()),
+                if leak.repo.is_empty() { "-".to_string() } else { leak.repo.clone() },
+                format!("{}:{}", leak.file.trim_start_matches('/'), leak.line_number),
+                leak.rule.clone(),
+                leak.note.clone().unwrap_or_default(),
+            ]
+        })
+        .collect();
+
+    let mut widths = [0; 7];
+    for row in std::iter::once(&header).chain(&rows) {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let mut list = String::new();
+    for row in std::iter::once(&header).chain(&rows) {
+        let cells: Vec<String> = row
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect();
+        list.push_str(cells.join("  ").trim_end());
+        list.push('\n');
+    }
+    list.push_str(&format!("{} leaks\n", rows.len()));
+    Ok(list)
+}
+
 /// Retrieves database connection configuration from environment variables.
 ///
 /// This function constructs a `ConnectDbConfig` struct with database connection details
@@ -94,4 +318,44 @@ fn get_db_config() -> ConnectDbConfig {
         password: env::var("PG_PASSWORD").unwrap_or("postgres".to_string()), 
         dbname: env::var("PG_DBNAME").unwrap_or("postgres".to_string()) 
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_leaks() {
+        let leak = Model {
+            id: 3,
+            line: "AWS_SECRET_ACCESS_KEY=wJalrXUtnFEMI/K7MDENG/bPxRfiCYzEXAMPLEKEY".to_string(),
+            line_number: 12,
+            offender: "wJalrXUtnFEMI/K7MDENG/bPxRfiCYzEXAMPLEKEY".to_string(),
+            commit: "9fceb02".to_string(),
+            repo: "acme/api".to_string(),
+            rule: "AWS Secret Access Key".to_string(),
+            commit_message: "Add deploy config".to_string(),
+            author: "dev".to_string(),
+            email: "dev@acme.test".to_string(),
+            file: "/deploy/prod.env".to_string(),
+            date: "2024-06-01 12:00:00 +00:00".to_string(),
+            status: LeakStatus::Rotated.as_str().to_string(),
+            assignee: Some("alice".to_string()),
+            note: Some("Rotated in IAM".to_string()),
+            updated_at: None,
+            severity: Some("high".to_string()),
+        };
+        let table = format_leaks(std::slice::from_ref(&leak), ListFormat::Table).unwrap();
+        assert_eq!(
+            table,
+            "ID  STATUS   ASSIGNEE  REPO      FILE                RULE                   NOTE\n\
+             3   rotated  alice     acme/api  deploy/prod.env:12  AWS Secret Access Key  Rotated in IAM\n\
+             1 leaks\n"
+        );
+        assert!(!table.contains(&leak.offender));
+        let json = format_leaks(&[leak], ListFormat::Json).unwrap();
+        assert!(json.contains("\"status\": \"rotated\""));
+        assert_eq!(LeakStatus::FalsePositive.as_str(), "false-positive");
+        assert_eq!(serde_json::to_value(LeakStatus::FalsePositive).unwrap(), "false-positive");
+    }
+}
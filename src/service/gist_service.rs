@@ -0,0 +1,432 @@
+use crate::errors::CustomError;
+use crate::models::{GistHost, GistsArgs, Leak, ReportMetadata, Scan};
+use crate::service::detect_service::{detect_bytes, write_report};
+use crate::utils::detect_utils::{load_config_file, ruleset_hash, sort_leaks};
+use crate::utils::http_util;
+use crate::utils::log_util::is_quiet;
+use chrono::Local;
+use serde_json::Value;
+use std::env;
+use std::error::Error;
+use std::time::Instant;
+
+/// How many gists, snippets, members or projects are listed per request.
+const PAGE_SIZE: usize = 100;
+
+/// Whose gists or snippets are scanned.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Owner {
+    User(String),
+    /// A GitHub organization or a GitLab group.
+    Org(String),
+}
+
+/// A gist or snippet, with the files in it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snippet {
+    pub id: String,
+    /// The page of the gist or snippet, reported as the repo of its leaks.
+    pub url: String,
+    pub owner: String,
+    pub description: String,
+    pub updated_at: String,
+    /// The name and the raw URL of each file.
+    pub files: Vec<(String, String)>,
+}
+
+/// The API of GitHub or GitLab, listing gists or snippets.
+pub struct SnippetApi {
+    pub host: GistHost,
+    pub url: String,
+    pub token: Option<String>,
+}
+
+impl SnippetApi {
+    /// Connects to the API of a host, with the token in `GITHUB_TOKEN` or `GITLAB_TOKEN` if set.
+    pub fn new(host: GistHost, url: Option<&str>) -> Self {
+        let (default_url, token) = match host {
+            GistHost::Github => ("https://api.github.com", "GITHUB_TOKEN"),
+            GistHost::Gitlab => ("https://gitlab.com/api/v4", "GITLAB_TOKEN"),
+        };
+        SnippetApi {
+            host,
+            url: url.unwrap_or(default_url).trim_end_matches('/').to_string(),
+            token: env::var(token).ok(),
+        }
+    }
+
+    fn headers(&self) -> Vec<(&'static str, String)> {
+        let mut headers = Vec::new();
+        if let Some(token) = &self.token {
+            headers.push(match self.host {
+                GistHost::Github => ("Authorization", format!("Bearer {}", token)),
+                GistHost::Gitlab => ("PRIVATE-TOKEN", token.clone()),
+            });
+        }
+        headers
+    }
+
+    /// Lists every page of an endpoint returning an array, `path` being relative to the API URL.
+    fn list(&self, path: &str) -> Result<Vec<Value>, String> {
+        let headers = self.headers();
+        let headers: Vec<(&str, &str)> =
+            headers.iter().map(|(name, value)| (*name, value.as_str())).collect();
+        let separator = if path.contains('?') { '&' } else { '?' };
+        let mut values = Vec::new();
+        for page in 1.. {
+            let url = format!(
+                "{}{}{}per_page={}&page={}",
+                self.url, path, separator, PAGE_SIZE, page
+            );
+            let listed = match http_util::send_json("GET", &url, &headers, None)? {
+                Value::Array(listed) => listed,
+                _ => return Err(format!("GET {} didn't return a list", url)),
+            };
+            let last = listed.len() < PAGE_SIZE;
+            values.extend(listed);
+            if last {
+                break;
+            }
+        }
+        Ok(values)
+    }
+
+    /// Lists the gists or snippets of an owner.
+    ///
+    /// On GitHub, the gists of an organization are the gists of its members. On GitLab, the
+    /// snippets of a user are their public snippets, as GitLab lists no others, and the snippets
+    /// of a group are the snippets of its projects.
+    ///
+    /// # Errors
+    ///
+    /// Returns the reason a request failed.
+    pub fn snippets(&self, owner: &Owner) -> Result<Vec<Snippet>, String> {
+        match (self.host, owner) {
+            (GistHost::Github, Owner::User(user)) => Ok(self
+                .list(&format!("/users/{}/gists", http_util::encode_query(user)))?
+                .iter()
+                .map(gist)
+                .collect()),
+            (GistHost::Github, Owner::Org(org)) => {
+                let mut gists = Vec::new();
+                let members = self.list(&format!("/orgs/{}/members", http_util::encode_query(org)))?;
+                for login in members.iter().filter_map(|member| member["login"].as_str()) {
+                    gists.extend(self.snippets(&Owner::User(login.to_string()))?);
+                }
+                Ok(gists)
+            }
+            (GistHost::Gitlab, Owner::User(user)) => Ok(self
+                .list("/snippets/public")?
+                .iter()
+                .filter(|snippet| snippet["author"]["username"].as_str() == Some(user))
+                .map(snippet)
+                .collect()),
+            (GistHost::Gitlab, Owner::Org(group)) => {
+                let mut snippets = Vec::new();
+                let path = format!(
+                    "/groups/{}/projects?include_subgroups=true&simple=true",
+                    http_util::encode_query(group)
+                );
+                for project in self.list(&path)? {
+                    let path = format!("/projects/{}/snippets", project["id"]);
+                    snippets.extend(self.list(&path)?.iter().map(snippet));
+                }
+                Ok(snippets)
+            }
+        }
+    }
+
+    /// Lists the repositories of a GitHub organization, or the projects of a GitLab group and its
+    /// subgroups, leaving out the archived ones.
+    ///
+    /// # Returns
+    ///
+    /// The full name of each repository, e.g. `acme/payments-api`, with its HTTPS clone URL.
+    ///
+    /// # Errors
+    ///
+    /// Returns the reason a request failed.
+    pub fn repositories(&self, org: &str) -> Result<Vec<(String, String)>, String> {
+        let (path, name, url) = match self.host {
+            GistHost::Github => (
+                format!("/orgs/{}/repos?type=all", http_util::encode_query(org)),
+                "full_name",
+                "clone_url",
+            ),
+            GistHost::Gitlab => (
+                format!(
+                    "/groups/{}/projects?include_subgroups=true&simple=true&archived=false",
+                    http_util::encode_query(org)
+                ),
+                "path_with_namespace",
+                "http_url_to_repo",
+            ),
+        };
+        Ok(self
+            .list(&path)?
+            .iter()
+            .filter(|repository| repository["archived"] != true)
+            .map(|repository| (text(&repository[name]), text(&repository[url])))
+            .collect())
+    }
+
+    /// Downloads a file of a gist or snippet.
+    ///
+    /// # Errors
+    ///
+    /// Returns the reason the file couldn't be downloaded.
+    pub fn read(&self, raw_url: &str) -> Result<Vec<u8>, String> {
+        // Raw gist files are served from another host, which gets no token
+        let headers = match self.host {
+            GistHost::Github => Vec::new(),
+            GistHost::Gitlab => self.headers(),
+        };
+        let headers: Vec<(&str, &str)> =
+            headers.iter().map(|(name, value)| (*name, value.as_str())).collect();
+        let response = http_util::send("GET", raw_url, &headers, None)?;
+        if response.status != 200 {
+            return Err(format!("GET {} returned status {}", raw_url, response.status));
+        }
+        Ok(response.body)
+    }
+}
+
+fn text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        Value::Null => String::new(),
+        value => value.to_string(),
+    }
+}
+
+/// Reads a gist listed by the GitHub API.
+fn gist(gist: &Value) -> Snippet {
+    let files = gist["files"]
+        .as_object()
+        .map(|files| {
+            files
+                .iter()
+                .map(|(name, file)| (name.clone(), text(&file["raw_url"])))
+                .collect()
+        })
+        .unwrap_or_default();
+    Snippet {
+        id: text(&gist["id"]),
+        url: text(&gist["html_url"]),
+        owner: text(&gist["owner"]["login"]),
+        description: text(&gist["description"]),
+        updated_at: text(&gist["updated_at"]),
+        files,
+    }
+}
+
+/// Reads a snippet listed by the GitLab API. Snippets made before GitLab 13.5 have a single file.
+fn snippet(snippet: &Value) -> Snippet {
+    let files = match snippet["files"].as_array() {
+        Some(files) => files
+            .iter()
+            .map(|file| (text(&file["path"]), text(&file["raw_url"])))
+            .collect(),
+        None => vec![(text(&snippet["file_name"]), text(&snippet["raw_url"]))],
+    };
+    Snippet {
+        id: text(&snippet["id"]),
+        url: text(&snippet["web_url"]),
+        owner: text(&snippet["author"]["username"]),
+        description: text(&snippet["title"]),
+        updated_at: text(&snippet["updated_at"]),
+        files,
+    }
+}
+
+/// Scans the files of gists or snippets with the rules of a config. The leaks are reported with
+/// the page of their gist as the repo, `<id>/<file name>` as the file, and its description as the
+/// commit message.
+///
+/// # Errors
+///
+/// Returns `CustomError::GistError` if a file can't be downloaded.
+pub fn scan_snippets(
+    api: &SnippetApi,
+    snippets: &[Snippet],
+    scan: &Scan,
+) -> Result<Vec<Leak>, Box<dyn Error>> {
+    let mut leaks = Vec::new();
+    for snippet in snippets {
+        for (name, raw_url) in &snippet.files {
+            let contents = api
+                .read(raw_url)
+                .map_err(|reason| CustomError::GistError { reason })?;
+            let path = format!("{}/{}", snippet.id, name);
+            for mut leak in detect_bytes(&contents, &path, &scan.ruleslist, &scan.allowlist, None) {
+                leak.repo = snippet.url.clone();
+                leak.author = snippet.owner.clone();
+                leak.commit_message = snippet.description.clone();
+                leak.date = snippet.updated_at.clone();
+                leaks.push(leak);
+            }
+        }
+    }
+    Ok(leaks)
+}
+
+/// Runs the `gists` subcommand, printing the leaks and writing them to the report if one is given.
+///
+/// # Errors
+///
+/// Returns an error if the config can't be loaded, a request fails or the report can't be written.
+pub fn scan_gists(args: &GistsArgs) -> Result<Vec<Leak>, Box<dyn Error>> {
+    let scan = load_config_file(&args.config)?;
+    let api = SnippetApi::new(args.provider, args.api_url.as_deref());
+    let owner = match (&args.user, &args.org) {
+        (Some(user), _) => Owner::User(user.clone()),
+        (None, Some(org)) => Owner::Org(org.clone()),
+        (None, None) => unreachable!("clap requires --user or --org"),
+    };
+    let account = args.user.as_ref().or(args.org.as_ref()).cloned().unwrap_or_default();
+    let mut metadata = ReportMetadata {
+        ruleset_hash: ruleset_hash(&scan),
+        started_at: Local::now().to_rfc3339(),
+        ..ReportMetadata::new(&args.config, &account)
+    };
+    let start = Instant::now();
+    let snippets = api
+        .snippets(&owner)
+        .map_err(|reason| CustomError::GistError { reason })?;
+    let mut leaks = scan_snippets(&api, &snippets, &scan)?;
+    sort_leaks(&mut leaks);
+
+    for leak in &leaks {
+        if !is_quiet() {
+            println!("{} {}:{} {}", leak.repo, leak.file, leak.line_number, leak.rule_id);
+        }
+    }
+    if let Some(report) = &args.report {
+        metadata.duration_ms = start.elapsed().as_millis() as u64;
+        write_report(report, args.report_format, &leaks, &metadata)?;
+    }
+    if !is_quiet() {
+        println!(
+            "\x1b[38;5;208m[WARN]\x1b[0m[{}]{} leaks detected. {} gists scanned",
+            Local::now().format("%Y-%m-%d %H:%M:%S"),
+            leaks.len(),
+            snippets.len()
+        );
+    }
+    Ok(leaks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::detect_utils::load_config_from_str;
+    use mockito::Matcher;
+    use serde_json::json;
+
+    const CONFIG: &str = r#"
+[[rules]]
+id = "stripe-access-token"
+description = "Stripe"
+regex = '''sk_(test|live)_[0-9a-z]{10,32}'''
+keywords = ["sk_test", "sk_live"]
+"#;
+
+    #[test]
+    fn test_scan_github_org_gists() {
+        let mut server = mockito::Server::new();
+        let api = SnippetApi {
+            host: GistHost::Github,
+            url: server.url(),
+            token: Some("token".to_string()),
+        };
+        server
+            .mock("GET", "/orgs/acme/members")
+            .match_query(Matcher::Any)
+            .match_header("authorization", "Bearer token")
+            .with_body(json!([{ "login": "dev" }]).to_string())
+            .create();
+        let gists = json!([{
+            "id": "g1",
+            "html_url": "https://gist.github.com/dev/g1",
+            "owner": { "login": "dev" },
+            "description": "deploy script",
+            "updated_at": "2024-05-01T00:00:00Z",
+            "files": {
+                "deploy.sh": { "raw_url": format!("{}/raw/g1/deploy.sh", server.url()) },
+                "README.md": { "raw_url": format!("{}/raw/g1/README.md", server.url()) },
+            },
+        }]);
+        server
+            .mock("GET", "/users/dev/gists")
+            .match_query(Matcher::Any)
+            .with_body(gists.to_string())
+            .create();
+        server
+            .mock("GET", "/raw/g1/deploy.sh")
+            .match_header("authorization", Matcher::Missing)
+            .with_body("#!/bin/sh\nexport STRIPE=sk_live_1234567890abcdef\n")
+            .create();
+        server.mock("GET", "/raw/g1/README.md").with_body("# Deploy\n").create();
+
+        let snippets = api.snippets(&Owner::Org("acme".to_string())).unwrap();
+        assert_eq!(snippets.len(), 1);
+        assert_eq!(snippets[0].files.len(), 2);
+
+        let scan = load_config_from_str(CONFIG).unwrap();
+        let leaks = scan_snippets(&api, &snippets, &scan).unwrap();
+        assert_eq!(leaks.len(), 1);
+        assert_eq!(leaks[0].file, "g1/deploy.sh");
+        assert_eq!(leaks[0].line_number, 2);
+        assert_eq!(leaks[0].repo, "https://gist.github.com/dev/g1");
+        assert_eq!(leaks[0].author, "dev");
+    }
+
+    #[test]
+    fn test_gitlab_snippets() {
+        let listed = json!({
+            "id": 7,
+            "title": "ci vars",
+            "web_url": "https://gitlab.com/-/snippets/7",
+            "author": { "username": "dev" },
+            "file_name": "vars.env",
+            "raw_url": "https://gitlab.com/-/snippets/7/raw",
+        });
+        let parsed = snippet(&listed);
+        assert_eq!(parsed.id, "7");
+        assert_eq!(
+            parsed.files,
+            [("vars.env".to_string(), "https://gitlab.com/-/snippets/7/raw".to_string())]
+        );
+
+        let mut server = mockito::Server::new();
+        let api = SnippetApi { host: GistHost::Gitlab, url: server.url(), token: None };
+        server
+            .mock("GET", "/snippets/public")
+            .match_query(Matcher::Any)
+            .with_body(json!([listed, { "id": 8, "author": { "username": "other" } }]).to_string())
+            .create();
+        let snippets = api.snippets(&Owner::User("dev".to_string())).unwrap();
+        assert_eq!(snippets, [parsed]);
+    }
+
+    #[test]
+    fn test_repositories() {
+        let mut server = mockito::Server::new();
+        let api = SnippetApi { host: GistHost::Github, url: server.url(), token: None };
+        server
+            .mock("GET", "/orgs/acme/repos")
+            .match_query(Matcher::UrlEncoded("type".into(), "all".into()))
+            .with_body(
+                json!([
+                    { "full_name": "acme/api", "clone_url": "https://github.com/acme/api.git", "archived": false },
+                    { "full_name": "acme/old", "clone_url": "https://github.com/acme/old.git", "archived": true },
+                ])
+                .to_string(),
+            )
+            .create();
+        assert_eq!(
+            api.repositories("acme").unwrap(),
+            [("acme/api".to_string(), "https://github.com/acme/api.git".to_string())]
+        );
+    }
+}
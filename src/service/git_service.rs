@@ -1,25 +1,44 @@
 extern crate chrono;
 extern crate git2;
-use chrono::{DateTime, FixedOffset, TimeZone, Utc};
+use chrono::{DateTime, FixedOffset};
 
 use git2::{BranchType, Repository, StatusOptions};
-use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
 use rayon::prelude::*;
 
-use crate::models::{CommitInfo, Leak, Results, Scan};
+use crate::models::{
+    Checkpoint, CommitInfo, Config, Leak, Results, Scan, ScanError, ScanTarget, SkipReason, UncommittedFiles,
+};
 use std::collections::HashSet;
 use std::error::Error;
 use std::fs;
-use std::fs::File;
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
 
 use crate::errors::CustomError;
-use crate::service::detect_service::{detect_file, detect_uncommitted_file};
+use crate::service::detect_service::{
+    apply_nested_configs, detect_blob, detect_bytes, detect_file, detect_large_file, drop_allowlisted_authors,
+    detect_uncommitted_file, external_link_leak,
+};
+use crate::utils::checkpoint_util::save_checkpoint;
+use crate::utils::date_util::{parse_commit_time, parse_time_range};
+use crate::utils::detect_utils::{
+    decode_content, load_nested_configs, resolve_link_target, NESTED_CONFIG_FILE, STREAM_THRESHOLD,
+};
+use crate::utils::generated_util::{is_generated, is_generated_path};
+use crate::utils::lfs_util::parse_pointer;
+use crate::utils::memory_util::MemoryBudget;
+use crate::utils::progress_util;
+use crate::utils::signal_util::is_cancelled;
 use crate::utils::git_util::{
-    config_commit_info, is_valid_date_format, load_all_commits, load_commits_by_conditions,
-    parse_date_to_datetime,
+    commit_time, config_commit_info, config_repo_name, load_all_commits, load_commit,
+    load_commits_by_conditions, load_lfs_pointer, load_object_blobs, load_unreachable_commits,
+    skip_unreadable, ObjectBlob,
 };
 
-use std::io::{BufRead, BufReader, Read};
+use std::io::{BufRead, BufReader};
 
 /// Handles a single commit by scanning its content.
 ///
@@ -39,7 +58,10 @@ pub fn handle_single_commit(
     scan: Scan,
     user: &str,
 ) -> Result<Results, Box<dyn Error>> {
-    let commit = repo.find_commit(git2::Oid::from_str(commit_id)?)?;
+    let mut errors = Vec::new();
+    let Some(commit) = load_commit(&repo, git2::Oid::from_str(commit_id)?, &scan, &mut errors)? else {
+        return Ok(Results { errors, ..Results::new() });
+    };
     if !user.is_empty() && user != commit.author().name().unwrap_or("") {
         return Ok(Results::new());
     }
@@ -47,7 +69,7 @@ pub fn handle_single_commit(
     let commits_list = vec![commit_info];
 
     // Handle the commit information and perform the scan
-    handle_commit_info(&commits_list, scan)
+    handle_commit_info(&repo, &commits_list, &scan)
 }
 
 /// Handles multiple commits by scanning their content.
@@ -69,20 +91,29 @@ pub fn handle_multiple_commits(
     user: &str,
 ) -> Result<Results, Box<dyn Error>> {
     let mut commits_list = vec![];
+    let mut errors = Vec::new();
 
-    // Iterate over each commit ID
+    // Iterate over each commit ID, skipping those that can't be read
     for commit_id in commit_ids {
-        let commit = repo.find_commit(git2::Oid::from_str(commit_id)?)?;
+        if is_cancelled() {
+            break;
+        }
+        let oid = git2::Oid::from_str(commit_id)?;
+        let Some(commit) = load_commit(&repo, oid, &scan, &mut errors)? else {
+            continue;
+        };
         if user.is_empty() || user == commit.author().name().unwrap_or("") {
             let commit_info = config_commit_info(&repo, &commit, &scan)?;
             commits_list.push(commit_info);
         }
     }
     if commits_list.is_empty() {
-        return Ok(Results::new());
+        return Ok(Results { errors, ..Results::new() });
     }
     // Handle the commit information and perform the scan
-    handle_commit_info(&commits_list, scan)
+    let mut results = handle_commit_info(&repo, &commits_list, &scan)?;
+    results.errors.append(&mut errors);
+    Ok(results)
 }
 
 /// Handles commits from a file by scanning their content.
@@ -134,7 +165,6 @@ pub fn handle_commits_file(
 ///
 /// A `Result` containing the scanning results (`Results`) if successful,
 /// otherwise an error (`Box<dyn Error>`).
-#[allow(deprecated)]
 pub fn handle_commit_range_by_time(
     repo: Repository,
     since: &str,
@@ -143,37 +173,9 @@ pub fn handle_commit_range_by_time(
     user: &str,
 ) -> Result<Results, Box<dyn Error>> {
     let excluded_commits: Vec<git2::Oid> = vec![];
-    let is_since_rfc3339 = DateTime::parse_from_rfc3339(since).is_ok();
-    let is_until_rfc3339 = DateTime::parse_from_rfc3339(until).is_ok();
-
-    let is_since_date = is_valid_date_format(since);
-    let is_until_date = is_valid_date_format(until);
-
-    if is_since_date && is_until_date {
-        // Convert since and until to start_time and end_time
-        let start_time = match parse_date_to_datetime(since, "start") {
-            Ok(datetime) => datetime.with_timezone(&FixedOffset::east(0)),
-            Err(err) => {
-                return Err(err);
-            }
-        };
+    let (start_time, end_time) = parse_time_range(since, until)?;
 
-        let end_time = match parse_date_to_datetime(until, "until") {
-            Ok(datetime) => datetime.with_timezone(&FixedOffset::east(0)),
-            Err(err) => {
-                return Err(err);
-            }
-        };
-
-        handle_multiple_commits_by_time(&repo, &excluded_commits, start_time, end_time, scan, user)
-    } else if is_since_rfc3339 && is_until_rfc3339 {
-        let start_time = DateTime::parse_from_rfc3339(since).unwrap();
-        let end_time = DateTime::parse_from_rfc3339(until).unwrap();
-
-        handle_multiple_commits_by_time(&repo, &excluded_commits, start_time, end_time, scan, user)
-    } else {
-        return Err(Box::new(CustomError::InvalidDateFormat));
-    }
+    handle_multiple_commits_by_time(&repo, &excluded_commits, start_time, end_time, scan, user)
 }
 
 /// Handles multiple commits within a specified time range by scanning their content.
@@ -190,7 +192,6 @@ pub fn handle_commit_range_by_time(
 ///
 /// A `Result` containing the scanning results (`Results`) if successful,
 /// otherwise an error (`Box<dyn Error>`).
-#[allow(deprecated)]
 pub fn handle_multiple_commits_by_time(
     repo: &Repository,
     excluded_commits: &[git2::Oid],
@@ -214,6 +215,7 @@ pub fn handle_multiple_commits_by_time(
     revwalk.set_sorting(git2::Sort::TOPOLOGICAL)?;
 
     let mut commits = Vec::new();
+    let mut errors = Vec::new();
     let excluded_commits: HashSet<_> = excluded_commits.iter().cloned().collect();
 
     // Iterate over each commit ID in the revision walker
@@ -223,13 +225,13 @@ pub fn handle_multiple_commits_by_time(
             continue; // Skip excluded commits
         }
 
-        let commit = repo.find_commit(oid)?;
+        let Some(commit) = load_commit(repo, oid, &scan, &mut errors)? else {
+            continue;
+        };
 
         if user.is_empty() || user == commit.author().name().unwrap_or("") {
-            // Get the commit's time and convert it to the appropriate time zone
-            let commit_time = Utc.timestamp(commit.time().seconds(), 0);
-            let commit_offset = FixedOffset::west(commit.time().offset_minutes() * 60);
-            let commit_date = commit_offset.from_utc_datetime(&commit_time.naive_utc());
+            // Get the commit's time in the appropriate time zone
+            let commit_date = commit_time(&commit, scan.date_field);
 
             // Check if the commit is within the specified time range
             if commit_date >= start_time && commit_date <= end_time {
@@ -240,7 +242,9 @@ pub fn handle_multiple_commits_by_time(
     }
 
     // Handle the commit information and perform the scan
-    handle_commit_info(&commits, scan)
+    let mut results = handle_commit_info(repo, &commits, &scan)?;
+    results.errors.append(&mut errors);
+    Ok(results)
 }
 
 /// Handles branches by name, scanning the commits in the matching branches.
@@ -260,31 +264,20 @@ pub fn handle_branches_by_name(
     branch_name: &str,
     scan: Scan,
 ) -> Result<Results, Box<dyn Error>> {
-    let branches = repo.branches(Some(BranchType::Local))?;
-
     let mut commits = Vec::new();
+    let mut errors = Vec::new();
 
-    // Iterate over each branch in the repository
-    for branch in branches {
-        let (branch, _) = branch?;
-        let branch_reference = branch.into_reference();
-        let branch_name_str = branch_reference.name().unwrap_or("");
-
-        // Check if the branch name contains the provided name or partial name
-        if branch_name_str.contains(branch_name) {
-            let commit_oid = branch_reference
-                .target()
-                .ok_or_else(|| git2::Error::from_str("Failed to get branch commit"))?;
-
-            let commit = repo.find_commit(commit_oid)?;
-            let commit_info = config_commit_info(&repo, &commit, &scan)?;
-
-            commits.push(commit_info);
+    // Scan the head of each branch whose name contains the provided name or partial name
+    for (_, commit_oid) in matching_branches(&repo, Some(branch_name))? {
+        if let Some(commit) = load_commit(&repo, commit_oid, &scan, &mut errors)? {
+            commits.push(config_commit_info(&repo, &commit, &scan)?);
         }
     }
 
     // Handle the commit information and perform the scan
-    handle_commit_info(&commits, scan)
+    let mut results = handle_commit_info(&repo, &commits, &scan)?;
+    results.errors.append(&mut errors);
+    Ok(results)
 }
 
 /// Handles a commit range, scanning the commits between the specified commit IDs.
@@ -323,12 +316,14 @@ pub fn handle_commit_range(
     handle_multiple_commits(repo, &commit_ids, scan, user)
 }
 
-/// Handles uncommitted files in the repository and performs a scan for potential leaks.
+/// Handles the files of the working tree a scan target covers and performs a scan for potential
+/// leaks: the uncommitted files, or those listed by `--file` or `--files-from`.
 ///
 /// # Arguments
 ///
 /// * `repo` - A `Repository` object representing the repository.
 /// * `repo_path` - The path to the repository.
+/// * `target` - The target of the scan, `ScanTarget::Uncommitted` for any but the listed files.
 /// * `scan` - A `Scan` object containing the rules, keywords, and allowlist for the scan.
 ///
 /// # Returns
@@ -343,51 +338,366 @@ pub fn handle_commit_range(
 /// * Reading the contents of a file.
 /// * Detecting uncommitted files using `detect_uncommitted_file` function.
 ///
-pub fn handle_uncommitted_files(
+pub fn handle_working_tree_files(
     repo: Repository,
     repo_path: &str,
+    target: &ScanTarget,
     scan: Scan,
 ) -> Result<Results, Box<dyn Error>> {
+    let mut outputs = Vec::new();
+    let mut results = scan_working_tree_files(&repo, repo_path, target, &scan, |mut leaks| {
+        outputs.append(&mut leaks);
+        ControlFlow::Continue(())
+    })?;
+    results.outputs = outputs;
+    Ok(results)
+}
+
+/// Scans the files of the working tree a scan target covers, see `handle_working_tree_files`,
+/// handing the leaks of each file to `on_leaks`; returning `ControlFlow::Break` stops the scan.
+///
+/// # Returns
+///
+/// Returns the number of files to scan and those skipped as generated, in `Results` without leaks.
+///
+/// # Errors
+///
+/// This function returns an error if the files can't be listed or read.
+pub fn scan_working_tree_files<F>(
+    repo: &Repository,
+    repo_path: &str,
+    target: &ScanTarget,
+    scan: &Scan,
+    mut on_leaks: F,
+) -> Result<Results, Box<dyn Error>>
+where
+    F: FnMut(Vec<Leak>) -> ControlFlow<()>,
+{
+    let mut uncommitted_files = load_working_tree_files(repo, repo_path, target, scan.follow_symlinks)?;
+    progress_util::set_phase("scan");
+    let mut generated = Vec::new();
+    if !scan.include_generated {
+        uncommitted_files.large_files.retain(|path| {
+            let kept = !is_generated_path(path);
+            if !kept {
+                generated.push(((path.clone(), SkipReason::Generated), 1));
+            }
+            kept
+        });
+        uncommitted_files.files.retain(|(path, content)| {
+            let kept = !is_generated(path, content.as_bytes());
+            if !kept {
+                generated.push(((path.clone(), SkipReason::Generated), 1));
+            }
+            kept
+        });
+    }
+    let mut results = Results {
+        files_number: uncommitted_files.files.len() + uncommitted_files.large_files.len(),
+        ..Results::new()
+    };
+    results.skip_files(generated);
+    let nested = load_nested_configs(&uncommitted_files.configs, scan.repo_config_allow);
+    let allowlist = &scan.allowlist;
+
+    let mut links: Vec<Leak> = uncommitted_files
+        .links
+        .iter()
+        .map(|(path, target)| external_link_leak(path, target, None))
+        .collect();
+    apply_nested_configs(&mut links, &nested, &[], allowlist, None);
+    if on_leaks(links).is_break() {
+        return Ok(results);
+    }
+    for path in &uncommitted_files.large_files {
+        progress_util::scanning_file(path);
+        let file_path = Path::new(repo_path).join(path);
+        let mut leaks = detect_large_file(&file_path, path, &scan.ruleslist, allowlist, scan.mmap)?;
+        apply_nested_configs(&mut leaks, &nested, &[], allowlist, None);
+        if on_leaks(leaks).is_break() {
+            return Ok(results);
+        }
+    }
+    for file in &uncommitted_files.files {
+        let (path, content) = file;
+        progress_util::scanning_file(path);
+        let mut leaks = detect_uncommitted_file(content, path, &scan.ruleslist, allowlist, scan.threads)?;
+        progress_util::leaks_found(leaks.len());
+        apply_nested_configs(&mut leaks, &nested, std::slice::from_ref(file), allowlist, None);
+        if on_leaks(leaks).is_break() {
+            break;
+        }
+    }
+    Ok(results)
+}
+
+/// Reads the files of the working tree a scan target covers: those listed by `ScanTarget::Files`
+/// or `ScanTarget::FilesFrom`, otherwise the modified and untracked ones.
+///
+/// # Errors
+///
+/// This function returns an error if the list, the status or a file can't be read, or a listed
+/// file isn't a file of the working tree.
+pub fn load_working_tree_files(
+    repo: &Repository,
+    repo_path: &str,
+    target: &ScanTarget,
+    follow_symlinks: bool,
+) -> Result<UncommittedFiles, Box<dyn Error>> {
+    match target {
+        ScanTarget::Files(paths) => load_listed_files(repo, repo_path, paths, follow_symlinks),
+        ScanTarget::FilesFrom(list) => {
+            load_listed_files(repo, repo_path, &read_file_list(list)?, follow_symlinks)
+        }
+        _ => load_uncommitted_files(repo, repo_path, follow_symlinks),
+    }
+}
+
+/// Reads the modified and untracked files of the working tree.
+///
+/// Symbolic links are handled as in `traverse_tree`: with `follow_symlinks`, the file or
+/// directory a link points to is read under the path of the link, unless it leads back into a
+/// directory being read. Files larger than `STREAM_THRESHOLD` are not read, only listed. Files
+/// missing from the working tree, deleted or left out by a sparse checkout, are skipped.
+///
+/// # Arguments
+///
+/// * `repo` - A reference to the `Repository` object.
+/// * `repo_path` - The path to the working tree.
+/// * `follow_symlinks` - Whether the targets of symbolic links are read.
+///
+/// # Returns
+///
+/// Returns the `(path, contents)` pairs of the uncommitted files, the paths of the large ones, and
+/// the links that point outside the working tree.
+///
+/// # Errors
+///
+/// This function returns an error if the status can't be read or a file can't be opened or read.
+pub fn load_uncommitted_files(
+    repo: &Repository,
+    repo_path: &str,
+    follow_symlinks: bool,
+) -> Result<UncommittedFiles, Box<dyn Error>> {
     let mut options = StatusOptions::new();
     options.include_untracked(true);
+    // Untracked directories are listed file by file rather than as a whole
+    options.recurse_untracked_dirs(true);
     options.include_unmodified(false);
     options.exclude_submodules(true);
 
     let statuses = repo.statuses(Some(&mut options))?;
 
-    let mut uncommitted_files = Vec::new();
+    let mut uncommitted_files = UncommittedFiles::default();
     for entry in statuses.iter() {
         if let Some(path) = entry.path() {
             let ab_path = format!("{}/{}", repo_path, path);
-            let mut file = File::open(ab_path)?;
-            let mut contents = String::new();
-            file.read_to_string(&mut contents)?;
-            uncommitted_files.push((path.to_string(), contents));
+            // Deleted files, and those a sparse checkout leaves out, have nothing to scan
+            let Ok(metadata) = fs::symlink_metadata(&ab_path) else {
+                continue;
+            };
+            load_entry(repo_path, path, &metadata, follow_symlinks, &mut uncommitted_files)?;
         }
     }
-    let mut results = Vec::new();
-    for (path, content) in uncommitted_files.iter() {
-        let result = detect_uncommitted_file(
-            content,
-            path,
-            &scan.ruleslist,
-            &scan.allowlist,
-            scan.threads,
-        );
-        if let Ok(output) = result {
-            if !output.is_empty() {
-                results.push(output);
-            }
-        } else if let Err(err) = result {
-            return Err(err);
+
+    let untracked = statuses.iter().filter_map(|entry| entry.path().map(str::to_string));
+    load_configs(repo, repo_path, untracked, &mut uncommitted_files)?;
+    Ok(uncommitted_files)
+}
+
+/// Reads the files of the working tree listed by `--file` or `--files-from`, whether they were
+/// changed or not.
+///
+/// The paths are relative to the working tree, or absolute within it, and each file is read once
+/// under its path relative to the working tree. Symbolic links and large files are handled as in
+/// `load_uncommitted_files`.
+///
+/// # Errors
+///
+/// This function returns `CustomError::ListedFileError` if a path doesn't name a file of the
+/// working tree, and an error if a file can't be read.
+pub fn load_listed_files(
+    repo: &Repository,
+    repo_path: &str,
+    paths: &[String],
+    follow_symlinks: bool,
+) -> Result<UncommittedFiles, Box<dyn Error>> {
+    let root = fs::canonicalize(repo_path)?;
+    let mut listed_files = UncommittedFiles::default();
+    let mut seen = HashSet::new();
+    for listed in paths {
+        let error = |reason: &str| CustomError::ListedFileError {
+            path: listed.clone(),
+            reason: reason.to_string(),
+        };
+        let full_path = root.join(listed);
+        // The parent is resolved, but not the file, which may be a symbolic link
+        let (Some(parent), Some(name)) = (full_path.parent(), full_path.file_name()) else {
+            return Err(error("not a file").into());
+        };
+        let parent = fs::canonicalize(parent).map_err(|err| error(&err.to_string()))?;
+        let Ok(relative) = parent.join(name).strip_prefix(&root).map(Path::to_path_buf) else {
+            return Err(error("outside of the working tree of --repo").into());
+        };
+        let path = relative
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        let metadata = fs::symlink_metadata(root.join(&relative)).map_err(|err| error(&err.to_string()))?;
+        if metadata.is_dir() {
+            return Err(error("a directory, not a file").into());
+        }
+        if seen.insert(path.clone()) {
+            load_entry(repo_path, &path, &metadata, follow_symlinks, &mut listed_files)?;
         }
     }
-    let flattened: Vec<Leak> = results.into_iter().flatten().collect();
-    let returns = Results {
-        commits_number: 0,
-        outputs: flattened,
+    load_configs(repo, repo_path, seen.into_iter(), &mut listed_files)?;
+    Ok(listed_files)
+}
+
+/// Reads the paths listed in a file, one per line, or on stdin for `-`. Blank lines are skipped.
+///
+/// # Errors
+///
+/// This function returns `CustomError::ListedFileError` if the list can't be read.
+pub fn read_file_list(list: &str) -> Result<Vec<String>, CustomError> {
+    let error = |err: std::io::Error| CustomError::ListedFileError {
+        path: list.to_string(),
+        reason: err.to_string(),
     };
-    Ok(returns)
+    let lines: Vec<String> = if list == "-" {
+        std::io::stdin().lock().lines().collect::<Result<_, _>>().map_err(error)?
+    } else {
+        let reader = BufReader::new(fs::File::open(list).map_err(error)?);
+        reader.lines().collect::<Result<_, _>>().map_err(error)?
+    };
+    Ok(lines
+        .iter()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .map(String::from)
+        .collect())
+}
+
+/// Reads a file or symbolic link of the working tree, with the metadata of the path itself.
+fn load_entry(
+    repo_path: &str,
+    path: &str,
+    metadata: &fs::Metadata,
+    follow_symlinks: bool,
+    files: &mut UncommittedFiles,
+) -> Result<(), Box<dyn Error>> {
+    let ab_path = format!("{}/{}", repo_path, path);
+    if metadata.file_type().is_symlink() {
+        let target = fs::read_link(&ab_path)?.to_string_lossy().into_owned();
+        if resolve_link_target(&format!("/{}", path), &target).is_none() {
+            files.links.push((path.to_string(), target));
+        } else if follow_symlinks {
+            load_link_target(Path::new(repo_path), path, files)?;
+        }
+        return Ok(());
+    }
+    load_file(Path::new(&ab_path), path.to_string(), files)
+}
+
+/// Reads the nested configs of the working tree, the tracked ones and those of `untracked`.
+///
+/// Nested configs apply whether they were modified or not, so tracked ones are read as well.
+fn load_configs(
+    repo: &Repository,
+    repo_path: &str,
+    untracked: impl Iterator<Item = String>,
+    files: &mut UncommittedFiles,
+) -> Result<(), Box<dyn Error>> {
+    let tracked = repo.index()?;
+    let tracked = tracked
+        .iter()
+        .map(|entry| String::from_utf8_lossy(&entry.path).into_owned());
+    let mut config_paths: Vec<String> = tracked
+        .chain(untracked)
+        .filter(|path| path.ends_with(&format!("/{}", NESTED_CONFIG_FILE)))
+        .collect();
+    config_paths.sort();
+    config_paths.dedup();
+    for path in config_paths {
+        // Deleted configs no longer apply
+        if let Ok(contents) = fs::read(Path::new(repo_path).join(&path)) {
+            let contents = decode_content(&contents).into_owned();
+            files.configs.push((path, contents));
+        }
+    }
+    Ok(())
+}
+
+/// Reads a file of the working tree, or only lists it if it is too large to be read at once.
+fn load_file(
+    real_path: &Path,
+    path: String,
+    files: &mut UncommittedFiles,
+) -> Result<(), Box<dyn Error>> {
+    if fs::metadata(real_path)?.len() > STREAM_THRESHOLD {
+        files.large_files.push(path);
+    } else {
+        let contents = decode_content(&fs::read(real_path)?).into_owned();
+        files.files.push((path, contents));
+    }
+    Ok(())
+}
+
+/// Reads what a symbolic link of the working tree points to, under the path of the link.
+fn load_link_target(
+    repo_path: &Path,
+    path: &str,
+    files: &mut UncommittedFiles,
+) -> Result<(), Box<dyn Error>> {
+    let root = fs::canonicalize(repo_path)?;
+    // Dangling links and links that loop are skipped
+    let Ok(real_path) = fs::canonicalize(root.join(path)) else {
+        return Ok(());
+    };
+    if !real_path.starts_with(&root) {
+        // The link points to another link that leaves the working tree
+        let target = real_path.to_string_lossy().into_owned();
+        files.links.push((path.to_string(), target));
+    } else if real_path.is_file() {
+        load_file(&real_path, path.to_string(), files)?;
+    } else if real_path.is_dir() && real_path != root {
+        let mut ancestors = vec![root.clone(), real_path.clone()];
+        load_linked_dir(&root, &real_path, path, &mut ancestors, files)?;
+    }
+    Ok(())
+}
+
+/// Reads the files of a directory reached through a symbolic link, following the links in it.
+fn load_linked_dir(
+    root: &Path,
+    dir: &Path,
+    path: &str,
+    ancestors: &mut Vec<PathBuf>,
+    files: &mut UncommittedFiles,
+) -> Result<(), Box<dyn Error>> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name.starts_with('.') {
+            continue;
+        }
+        let entry_path = format!("{}/{}", path, name);
+        let Ok(real_path) = fs::canonicalize(entry.path()) else {
+            continue;
+        };
+        if !real_path.starts_with(root) {
+            files.links.push((entry_path, real_path.to_string_lossy().into_owned()));
+        } else if real_path.is_file() {
+            load_file(&real_path, entry_path, files)?;
+        } else if real_path.is_dir() && !ancestors.contains(&real_path) {
+            ancestors.push(real_path.clone());
+            load_linked_dir(root, &real_path, &entry_path, ancestors, files)?;
+            ancestors.pop();
+        }
+    }
+    Ok(())
 }
 
 /// Handles all commits in the repository and performs a scan for potential leaks.
@@ -425,11 +735,398 @@ pub fn handle_all_commits(
     handle_multiple_commits(repo, &commit_ids, scan, user)
 }
 
+/// How many bytes of blobs `scan_object_database` reads before scanning them.
+const OBJECT_BATCH_SIZE: usize = 64 << 20;
+
+/// Scans every blob of the object database of a repository, see `load_object_blobs`, instead of
+/// the trees of its commits.
+///
+/// Each blob is scanned once however many commits hold it, and blobs only reachable from deleted
+/// branches or not reachable at all are scanned too. Blobs are read a batch at a time on the
+/// calling thread and the batch is scanned in parallel. The leaks have the id of their blob and no
+/// commit, and the path their blob was found under, or its id when no tree lists it. Git LFS
+/// pointers are skipped or replaced by their objects as in commit scans, see `load_lfs_pointer`.
+///
+/// # Arguments
+///
+/// * `repo` - The repository to scan.
+/// * `scan` - A `Scan` object containing the rules and allowlist for secret detection.
+/// * `on_leaks` - Called with the leaks of each batch; returning `ControlFlow::Break` stops the scan.
+///
+/// # Returns
+///
+/// Returns the objects that couldn't be read and the LFS pointers that were skipped, in `Results`
+/// without leaks and commits.
+///
+/// # Errors
+///
+/// This function returns an error if the object database can't be read, or a blob or an LFS
+/// object with `scan.strict`.
+pub fn scan_object_database<F>(
+    repo: &Repository,
+    scan: &Scan,
+    on_leaks: F,
+) -> Result<Results, Box<dyn Error>>
+where
+    F: FnMut(Vec<Leak>) -> ControlFlow<()>,
+{
+    let blobs = load_object_blobs(repo, scan)?;
+    scan_blobs(repo, scan, &blobs, on_leaks)
+}
+
+/// Scans the blobs of the object database no tree holds, e.g. those added to the index but never
+/// committed, for `--include-unreachable`. Their leaks are reported as in `scan_object_database`.
+///
+/// # Errors
+///
+/// This function returns an error if the object database can't be read, or a blob or an LFS
+/// object with `scan.strict`.
+pub fn scan_dangling_blobs<F>(
+    repo: &Repository,
+    scan: &Scan,
+    on_leaks: F,
+) -> Result<Results, Box<dyn Error>>
+where
+    F: FnMut(Vec<Leak>) -> ControlFlow<()>,
+{
+    let mut blobs = load_object_blobs(repo, scan)?;
+    blobs.retain(|(_, path)| path.is_none());
+    scan_blobs(repo, scan, &blobs, on_leaks)
+}
+
+/// Scans blobs a batch at a time, see `scan_object_database`.
+fn scan_blobs<F>(
+    repo: &Repository,
+    scan: &Scan,
+    blobs: &[ObjectBlob],
+    mut on_leaks: F,
+) -> Result<Results, Box<dyn Error>>
+where
+    F: FnMut(Vec<Leak>) -> ControlFlow<()>,
+{
+    let repo_name = config_repo_name(repo).map_err(|_| CustomError::InvalidRepoName)?;
+    let mut skipped = Results::new();
+    let mut batch = Vec::new();
+    let mut size = 0;
+    for (i, (id, path)) in blobs.iter().enumerate() {
+        let error = ScanError {
+            path: path.clone(),
+            ..ScanError::new(git2::ObjectType::Blob, *id, "")
+        };
+        match repo.find_blob(*id) {
+            Ok(blob) => {
+                let content = match parse_pointer(blob.content()) {
+                    Some(pointer) => load_lfs_pointer(repo, scan, &pointer, error, &mut skipped.errors)?,
+                    None => Some(blob.content().to_vec()),
+                };
+                let generated = |content: &[u8]| {
+                    !scan.include_generated && path.as_deref().is_some_and(|path| is_generated(path, content))
+                };
+                let path = path.clone().unwrap_or_else(|| id.to_string());
+                match content {
+                    Some(content) if generated(&content) => {
+                        skipped.skip_files([((path, SkipReason::Generated), 1)]);
+                    }
+                    Some(content) => {
+                        size += content.len();
+                        skipped.files_number += 1;
+                        batch.push((id.to_string(), path, content));
+                    }
+                    None => {
+                        skipped.lfs_pointers.insert(id.to_string());
+                        skipped.skip_files([((path, SkipReason::LfsPointer), 1)]);
+                    }
+                }
+            }
+            Err(err) => {
+                let error = ScanError {
+                    reason: err.message().to_string(),
+                    ..error
+                };
+                skip_unreadable(scan.strict, &mut skipped.errors, error, err)?;
+            }
+        }
+        if size < OBJECT_BATCH_SIZE && i + 1 < blobs.len() {
+            continue;
+        }
+        let leaks: Vec<Leak> = batch
+            .par_iter()
+            .flat_map_iter(|(id, path, content)| {
+                // Blobs read with commits are decoded the same way, unless they are large
+                let content = if content.len() as u64 > STREAM_THRESHOLD {
+                    std::borrow::Cow::Borrowed(content.as_slice())
+                } else {
+                    std::borrow::Cow::Owned(decode_content(content).into_owned().into_bytes())
+                };
+                let leaks = detect_bytes(&content, path, &scan.ruleslist, &scan.allowlist, None);
+                leaks.into_iter().map(|mut leak| {
+                    leak.blob = Some(id.clone());
+                    leak.repo = repo_name.clone();
+                    leak
+                })
+            })
+            .collect();
+        batch.clear();
+        size = 0;
+        if on_leaks(leaks).is_break() {
+            break;
+        }
+    }
+    Ok(skipped)
+}
+
+/// Scans every blob of the object database of a repository, see `scan_object_database`.
+///
+/// # Errors
+///
+/// This function returns an error if the object database or a blob can't be read.
+pub fn handle_packfiles(repo: Repository, scan: Scan) -> Result<Results, Box<dyn Error>> {
+    progress_util::set_phase("scan");
+    let mut outputs = Vec::new();
+    let mut results = scan_object_database(&repo, &scan, |mut leaks| {
+        outputs.append(&mut leaks);
+        ControlFlow::Continue(())
+    })?;
+    results.outputs = outputs;
+    Ok(results)
+}
+
+/// Scans the target of `config`, handing the leaks found to `on_leaks` as they are found: those of
+/// each file of the working tree, of every `batch` commits, or of each batch of blobs of the object
+/// database, then with `include_unreachable` those of the blobs no tree holds. Returning
+/// `ControlFlow::Break` stops the scan.
+///
+/// This is the scan loop of both the CLI, which collects every leak of the commits scanned at once,
+/// and `Scanner`, which hands them out a commit at a time.
+///
+/// # Returns
+///
+/// Returns the commits and files scanned, the objects that couldn't be read and the files that
+/// were skipped, in `Results` without leaks.
+///
+/// # Errors
+///
+/// This function returns an error if the commits or files to scan can't be selected or read, see
+/// `select_commits`, `scan_working_tree_files` and `scan_object_database`.
+pub fn scan_target<F>(
+    repo: &Repository,
+    config: &Config,
+    scan: &Scan,
+    batch: usize,
+    mut on_leaks: F,
+) -> Result<Results, Box<dyn Error>>
+where
+    F: FnMut(Vec<Leak>) -> ControlFlow<()>,
+{
+    let stopped = std::cell::Cell::new(false);
+    let mut on_leaks = |leaks| {
+        let flow = on_leaks(leaks);
+        stopped.set(flow.is_break());
+        flow
+    };
+    let target = config.target();
+    if target.is_working_tree() {
+        return scan_working_tree_files(repo, &config.repo, &target, scan, on_leaks);
+    }
+    if target == ScanTarget::Packfiles {
+        progress_util::set_phase("scan");
+        return scan_object_database(repo, scan, on_leaks);
+    }
+    let mut results = scan_commits(repo, &select_commits(repo, config)?, scan, batch, &mut on_leaks)?;
+    if config.include_unreachable && target == ScanTarget::All && !stopped.get() {
+        results.merge(scan_dangling_blobs(repo, scan, on_leaks)?);
+    }
+    Ok(results)
+}
+
+/// Scans commits `batch` at a time, see `handle_commit_info`, handing the leaks of each batch to
+/// `on_leaks`; returning `ControlFlow::Break` stops the scan. The commits of a batch are loaded
+/// together and scanned in parallel.
+///
+/// # Returns
+///
+/// Returns the commits and files scanned and the objects that couldn't be read, in `Results`
+/// without leaks.
+///
+/// # Errors
+///
+/// This function returns an error if a commit can't be loaded, see `load_commit`.
+pub fn scan_commits<F>(
+    repo: &Repository,
+    commit_ids: &[git2::Oid],
+    scan: &Scan,
+    batch: usize,
+    mut on_leaks: F,
+) -> Result<Results, Box<dyn Error>>
+where
+    F: FnMut(Vec<Leak>) -> ControlFlow<()>,
+{
+    let mut results = Results::new();
+    for batch in commit_ids.chunks(batch.max(1)) {
+        if is_cancelled() {
+            break;
+        }
+        let mut commit_info_list = Vec::new();
+        for oid in batch {
+            if let Some(commit) = load_commit(repo, *oid, scan, &mut results.errors)? {
+                commit_info_list.push(config_commit_info(repo, &commit, scan)?);
+            }
+        }
+        let mut scanned = handle_commit_info(repo, &commit_info_list, scan)?;
+        let leaks = std::mem::take(&mut scanned.outputs);
+        results.merge(scanned);
+        if on_leaks(leaks).is_break() {
+            break;
+        }
+    }
+    Ok(results)
+}
+
+/// Selects the commits to scan from the target of `config`.
+///
+/// Except for branches, only commits by `user` are kept when it is set. The uncommitted, files
+/// and packfiles targets select no commits. With `max_depth`, the whole history, the history of
+/// branches and the commits of a time range are cut to the most recent ones, and with
+/// `include_unreachable` the whole history also has the commits HEAD doesn't reach, see
+/// `load_unreachable_commits`.
+///
+/// # Arguments
+///
+/// * `repo` - A reference to the `Repository` object.
+/// * `config` - A reference to the `Config` object holding the commit options.
+///
+/// # Returns
+///
+/// Returns the ids of the selected commits.
+///
+/// # Errors
+///
+/// This function returns an error if a commit id is invalid or can't be found, the commits file
+/// can't be read, or the time range is malformed.
+pub fn select_commits(repo: &Repository, config: &Config) -> Result<Vec<git2::Oid>, Box<dyn Error>> {
+    let user = config.user.as_deref().unwrap_or("");
+    let ids: Vec<String> = match config.target() {
+        ScanTarget::Uncommitted | ScanTarget::Packfiles | ScanTarget::Files(_) | ScanTarget::FilesFrom(_) => {
+            return Ok(Vec::new())
+        }
+        ScanTarget::Commit(commit) => vec![commit],
+        ScanTarget::Commits(commits) => commits,
+        ScanTarget::CommitsFile(file_name) => {
+            let reader = BufReader::new(fs::File::open(file_name)?);
+            reader.lines().map_while(Result::ok).collect()
+        }
+        ScanTarget::Between { since, until } => {
+            let (start_time, end_time) = parse_time_range(&since, &until)?;
+            let mut revwalk = repo.revwalk()?;
+            revwalk.push_head()?;
+            revwalk.set_sorting(git2::Sort::TOPOLOGICAL)?;
+            let mut ids = Vec::new();
+            for oid in revwalk {
+                let commit = repo.find_commit(oid?)?;
+                let date = commit_time(&commit, config.date_field);
+                if date >= start_time && date <= end_time {
+                    ids.push(commit.id().to_string());
+                }
+            }
+            if let Some(max_depth) = config.max_depth {
+                ids.truncate(max_depth as usize);
+            }
+            ids
+        }
+        ScanTarget::History { branch, since, until } => {
+            let start_time = since.as_deref().map(|since| parse_commit_time(since, false)).transpose()?;
+            let end_time = until.as_deref().map(|until| parse_commit_time(until, true)).transpose()?;
+            if let (Some(start_time), Some(end_time)) = (start_time, end_time) {
+                if start_time > end_time {
+                    return Err(Box::new(CustomError::InvalidDateRange));
+                }
+            }
+            let mut revwalk = repo.revwalk()?;
+            match branch.as_deref() {
+                Some(name) => {
+                    for (_, oid) in matching_branches(repo, Some(name))? {
+                        revwalk.push(oid)?;
+                    }
+                }
+                None => revwalk.push_head()?,
+            }
+            // The history of several branches is merged, the most recent commits first
+            revwalk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::TIME)?;
+            let mut ids = Vec::new();
+            for oid in revwalk {
+                let commit = repo.find_commit(oid?)?;
+                let date = commit_time(&commit, config.date_field);
+                if start_time.is_none_or(|start| date >= start) && end_time.is_none_or(|end| date <= end) {
+                    ids.push(commit.id().to_string());
+                }
+                if config.max_depth.is_some_and(|max_depth| ids.len() as u64 >= max_depth) {
+                    break;
+                }
+            }
+            ids
+        }
+        ScanTarget::Range { from, to } => {
+            let all_commits = load_all_commits(repo).map_err(|_| CustomError::ObjectConvertFail)?;
+            load_commits_by_conditions(Some(from), Some(to), &all_commits)
+        }
+        ScanTarget::Branch(branch_name) => {
+            let branches = matching_branches(repo, Some(&branch_name))?;
+            return Ok(branches.into_iter().map(|(_, oid)| oid).collect());
+        }
+        ScanTarget::All => {
+            let mut ids = load_all_commits(repo).map_err(|_| CustomError::ObjectConvertFail)?;
+            if let Some(max_depth) = config.max_depth {
+                ids.truncate(max_depth as usize);
+            }
+            if config.include_unreachable {
+                ids.extend(load_unreachable_commits(repo)?);
+            }
+            ids
+        }
+    };
+
+    let mut commits = Vec::new();
+    for id in ids {
+        let oid = git2::Oid::from_str(&id)?;
+        // Commits that can't be read are kept, for the scan to skip or fail on
+        match repo.find_commit(oid) {
+            Ok(commit) if !user.is_empty() && user != commit.author().name().unwrap_or("") => {}
+            _ => commits.push(oid),
+        }
+    }
+    Ok(commits)
+}
+
+/// Returns the names of the local branches whose name contains `name`, with the commits they
+/// point at, or every local branch without a name.
+///
+/// # Errors
+///
+/// This function returns an error if the branches can't be listed or a branch isn't a direct
+/// reference.
+pub fn matching_branches(repo: &Repository, name: Option<&str>) -> Result<Vec<(String, git2::Oid)>, Box<dyn Error>> {
+    let mut branches = Vec::new();
+    for branch in repo.branches(Some(BranchType::Local))? {
+        let branch_reference = branch?.0.into_reference();
+        let branch_name = branch_reference.name().unwrap_or("");
+        if name.is_none_or(|name| branch_name.contains(name)) {
+            let commit_oid = branch_reference
+                .target()
+                .ok_or_else(|| git2::Error::from_str("Failed to get branch commit"))?;
+            branches.push((branch_name.to_string(), commit_oid));
+        }
+    }
+    Ok(branches)
+}
+
 /// Handle the commit information by searching for secrets in the commit files.
 ///
+/// Large files are streamed from the repository one at a time, before the other files are
+/// scanned in parallel.
 ///
 /// # Arguments
 ///
+/// * `repo` - The repository the commits belong to.
 /// * `commit_info_list` - A slice of `CommitInfo` objects representing the commit information.
 /// * `scan` - A `Scan` object containing the rules, keywords, and allowlist for secret detection.
 ///
@@ -439,32 +1136,83 @@ pub fn handle_all_commits(
 /// The error type is a boxed `dyn Error`, which allows for returning different types of error objects.
 ///
 pub fn handle_commit_info(
+    repo: &Repository,
     commit_info_list: &[CommitInfo],
-    scan: Scan,
+    scan: &Scan,
 ) -> Result<Results, Box<dyn Error>> {
-    let ruleslist = scan.ruleslist;
-    let allowlist = scan.allowlist;
+    let ruleslist = &scan.ruleslist;
+    let allowlist = &scan.allowlist;
     let threads = scan.threads;
     let chunk=scan.chunk.unwrap_or(10);
+    let allow = scan.repo_config_allow;
+    let strict = scan.strict;
     let results: Arc<Mutex<Vec<Leak>>> = Arc::new(Mutex::new(Vec::new()));
+    progress_util::set_phase("scan");
+
+    let nested: Vec<_> = commit_info_list
+        .iter()
+        .map(|commit_info| load_nested_configs(&commit_info.configs, allow))
+        .collect();
+
+    let mut counted = Results::new();
+    let mut errors = Vec::new();
+    let mut lfs_pointers = HashSet::new();
+    for (commit_info, nested) in commit_info_list.iter().zip(&nested) {
+        counted.count_files(commit_info);
+        errors.extend(commit_info.errors.iter().cloned());
+        lfs_pointers.extend(commit_info.lfs_pointers.iter().map(git2::Oid::to_string));
+        for (file, oid) in &commit_info.large_files {
+            progress_util::scanning_file(file);
+            let blob = match repo.find_blob(*oid) {
+                Ok(blob) => blob,
+                Err(err) => {
+                    let skipped = ScanError {
+                        commit: Some(commit_info.commit.to_string()),
+                        path: Some(file.clone()),
+                        ..ScanError::new(git2::ObjectType::Blob, *oid, err.message())
+                    };
+                    skip_unreadable(strict, &mut errors, skipped, err)?;
+                    continue;
+                }
+            };
+            let content = blob.content();
+            let mut leaks = detect_bytes(content, file, ruleslist, allowlist, Some(commit_info));
+            apply_nested_configs(&mut leaks, nested, &[], allowlist, Some(commit_info));
+            drop_allowlisted_authors(&mut leaks, ruleslist, allowlist);
+            progress_util::leaks_found(leaks.len());
+            results.lock().unwrap().extend(leaks);
+        }
+    }
 
-    commit_info_list.par_iter().for_each(|commit_info| {
-        let commit_results: Vec<Leak> = commit_info
+    let scanned = AtomicUsize::new(0);
+    commit_info_list.par_iter().zip(&nested).for_each(|(commit_info, nested)| {
+        if is_cancelled() {
+            return;
+        }
+        scanned.fetch_add(1, Ordering::Relaxed);
+        let link_results = commit_info
+            .links
+            .par_iter()
+            .map(|(path, target)| external_link_leak(path, target, Some(commit_info)));
+        let mut commit_results: Vec<Leak> = commit_info
             .files
             .par_chunks(chunk)
             .flat_map(|files_chunk| {
                 files_chunk
                     .iter()
                     .filter_map(|(file, content)| {
-                        match detect_file(content, file, &ruleslist, &allowlist, commit_info, threads) {
-                            Ok(output) => Some(output),
-                            Err(_) => None,
-                        }
+                        progress_util::scanning_file(file);
+                        detect_file(content, file, ruleslist, allowlist, commit_info, threads).ok()
                     })
                     .flatten()
                     .collect::<Vec<Leak>>()
             })
+            .chain(link_results)
             .collect();
+        let files = &commit_info.files;
+        apply_nested_configs(&mut commit_results, nested, files, allowlist, Some(commit_info));
+        drop_allowlisted_authors(&mut commit_results, ruleslist, allowlist);
+        progress_util::commits_scanned(1, commit_results.len());
 
         let mut results = results.lock().unwrap();
         results.extend(commit_results);
@@ -476,13 +1224,238 @@ pub fn handle_commit_info(
         .clone();
 
     let returns = Results {
-        commits_number: commit_info_list.len(),
+        commits_number: scanned.into_inner(),
         outputs: flattened,
+        errors,
+        lfs_pointers,
+        ..counted
     };
 
     Ok(returns)
 }
 
+/// Scans commits a batch of `every` commits at a time, saving the progress to a checkpoint file
+/// after each batch, see `--checkpoint`.
+///
+/// The commits the checkpoint already lists are skipped and its leaks are kept, so that an
+/// interrupted scan resumes where it was saved. The checkpoint file is removed once every commit
+/// is scanned, and kept when the scan is cancelled, see `is_cancelled`.
+///
+/// # Arguments
+///
+/// * `repo` - The repository the commits belong to.
+/// * `commit_ids` - The commits to scan, including those already scanned.
+/// * `scan` - A `Scan` object containing the rules, keywords, and allowlist for secret detection.
+/// * `path` - Where the checkpoint is saved.
+/// * `every` - How many commits are scanned between two saves.
+/// * `checkpoint` - The progress to resume from, empty for a new scan.
+///
+/// # Errors
+///
+/// This function returns an error if a commit can't be loaded, see `load_commit`, or the checkpoint
+/// can't be saved.
+pub fn handle_commits_with_checkpoint(
+    repo: &Repository,
+    commit_ids: &[git2::Oid],
+    scan: Scan,
+    path: &str,
+    every: usize,
+    mut checkpoint: Checkpoint,
+) -> Result<Results, Box<dyn Error>> {
+    let scanned: HashSet<&str> = checkpoint.commits.iter().map(String::as_str).collect();
+    let remaining: Vec<git2::Oid> = commit_ids
+        .iter()
+        .filter(|oid| !scanned.contains(oid.to_string().as_str()))
+        .copied()
+        .collect();
+    progress_util::commits_scanned(commit_ids.len() - remaining.len(), checkpoint.leaks.len());
+    for batch in remaining.chunks(every.max(1)) {
+        if is_cancelled() {
+            break;
+        }
+        let mut commit_info_list = Vec::new();
+        for oid in batch {
+            if let Some(commit) = load_commit(repo, *oid, &scan, &mut checkpoint.errors)? {
+                commit_info_list.push(config_commit_info(repo, &commit, &scan)?);
+            }
+        }
+        let results = handle_commit_info(repo, &commit_info_list, &scan)?;
+        if is_cancelled() {
+            // Leave the commits of the batch to the scan that resumes
+            break;
+        }
+        checkpoint.commits.extend(batch.iter().map(git2::Oid::to_string));
+        checkpoint.last_commit = batch.last().map(git2::Oid::to_string);
+        checkpoint.leaks.extend(results.outputs);
+        checkpoint.errors.extend(results.errors);
+        checkpoint.lfs_pointers.extend(results.lfs_pointers);
+        checkpoint.files_number += results.files_number;
+        let mut skipped = Results::new();
+        skipped.skip_files(checkpoint.skipped_files.drain(..).map(|file| ((file.path, file.reason), file.commits)));
+        skipped.skip_files(results.skipped_files);
+        checkpoint.skipped_files = skipped.skipped_file_list();
+        save_checkpoint(path, &checkpoint)?;
+    }
+    if !is_cancelled() && Path::new(path).exists() {
+        fs::remove_file(path)?;
+    }
+    let mut results = Results {
+        commits_number: checkpoint.commits.len(),
+        outputs: checkpoint.leaks,
+        errors: checkpoint.errors,
+        lfs_pointers: checkpoint.lfs_pointers,
+        files_number: checkpoint.files_number,
+        ..Results::new()
+    };
+    results.skip_files(checkpoint.skipped_files.into_iter().map(|file| ((file.path, file.reason), file.commits)));
+    Ok(results)
+}
+
+/// How many batches of findings scanning threads can hand over before waiting for them to be
+/// collected.
+const FINDINGS_QUEUE_LEN: usize = 64;
+
+/// Scans commits while keeping the contents of those loaded but not scanned yet under
+/// `max_memory` bytes.
+///
+/// Commits are loaded one at a time on the calling thread and scanned by one thread per CPU.
+/// Loading waits while the commits waiting to be scanned hold more than `max_memory` bytes, and
+/// scanning waits while the queue of findings is full. A single commit larger than the limit is
+/// still scanned, on its own.
+///
+/// # Arguments
+///
+/// * `repo` - The repository the commits belong to.
+/// * `commit_ids` - The commits to scan.
+/// * `scan` - A `Scan` object containing the rules, keywords, and allowlist for secret detection.
+/// * `max_memory` - The limit, in bytes.
+///
+/// # Errors
+///
+/// This function returns an error if a commit can't be loaded, see `load_commit`.
+pub fn handle_commits_within_memory(
+    repo: &Repository,
+    commit_ids: &[git2::Oid],
+    scan: Scan,
+    max_memory: usize,
+) -> Result<Results, Box<dyn Error>> {
+    let budget = MemoryBudget::new(max_memory);
+    let workers = thread::available_parallelism().map_or(1, |workers| workers.get());
+    let (commit_sender, commit_receiver) = mpsc::sync_channel::<(CommitInfo, usize)>(workers);
+    let commit_receiver = Mutex::new(commit_receiver);
+    let (leak_sender, leak_receiver) = mpsc::sync_channel::<Vec<Leak>>(FINDINGS_QUEUE_LEN);
+    let repo_path = repo.path();
+    let scan = &scan;
+    progress_util::set_phase("scan");
+
+    thread::scope(|s| {
+        let collector = s.spawn(move || leak_receiver.into_iter().flatten().collect::<Vec<Leak>>());
+        for _ in 0..workers {
+            let leak_sender = leak_sender.clone();
+            let (budget, commit_receiver) = (&budget, &commit_receiver);
+            s.spawn(move || {
+                // Large files are streamed from a repository handle of the thread's own
+                let worker_repo = Repository::open(repo_path).ok();
+                loop {
+                    let received = commit_receiver.lock().unwrap().recv();
+                    let Ok((commit_info, size)) = received else {
+                        break;
+                    };
+                    let mut leaks: Vec<Leak> = commit_info
+                        .links
+                        .iter()
+                        .map(|(path, target)| external_link_leak(path, target, Some(&commit_info)))
+                        .collect();
+                    for (file, content) in &commit_info.files {
+                        progress_util::scanning_file(file);
+                        if let Ok(found) = detect_file(
+                            content,
+                            file,
+                            &scan.ruleslist,
+                            &scan.allowlist,
+                            &commit_info,
+                            scan.threads,
+                        ) {
+                            leaks.extend(found);
+                        }
+                    }
+                    for (file, oid) in &commit_info.large_files {
+                        let Some(worker_repo) = &worker_repo else {
+                            break;
+                        };
+                        let (ruleslist, allowlist) = (&scan.ruleslist, &scan.allowlist);
+                        if let Ok(found) =
+                            detect_blob(worker_repo, *oid, file, ruleslist, allowlist, &commit_info)
+                        {
+                            leaks.extend(found);
+                        }
+                    }
+                    let nested = load_nested_configs(&commit_info.configs, scan.repo_config_allow);
+                    let (files, allowlist) = (&commit_info.files, &scan.allowlist);
+                    apply_nested_configs(&mut leaks, &nested, files, allowlist, Some(&commit_info));
+                    drop_allowlisted_authors(&mut leaks, &scan.ruleslist, allowlist);
+                    progress_util::commits_scanned(1, leaks.len());
+                    drop(commit_info);
+                    budget.release(size);
+                    if leak_sender.send(leaks).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(leak_sender);
+
+        let mut loaded = Ok(());
+        let mut counted = Results::new();
+        let mut errors = Vec::new();
+        let mut lfs_pointers = HashSet::new();
+        let mut loaded_commits = 0;
+        for oid in commit_ids {
+            if is_cancelled() {
+                break;
+            }
+            loaded_commits += 1;
+            let commit_info = match load_commit(repo, *oid, scan, &mut errors) {
+                Ok(Some(commit)) => config_commit_info(repo, &commit, scan),
+                Ok(None) => continue,
+                Err(err) => Err(err),
+            };
+            match commit_info {
+                Ok(mut commit_info) => {
+                    counted.count_files(&commit_info);
+                    errors.append(&mut commit_info.errors);
+                    let pointers = commit_info.lfs_pointers.drain(..);
+                    lfs_pointers.extend(pointers.map(|oid| oid.to_string()));
+                    let size = commit_info
+                        .files
+                        .iter()
+                        .map(|(file, content)| file.len() + content.len())
+                        .sum();
+                    budget.acquire(size);
+                    if commit_sender.send((commit_info, size)).is_err() {
+                        break;
+                    }
+                }
+                Err(err) => {
+                    loaded = Err(err);
+                    break;
+                }
+            }
+        }
+        drop(commit_sender);
+
+        let outputs = collector.join().unwrap();
+        loaded?;
+        Ok(Results {
+            commits_number: loaded_commits,
+            outputs,
+            errors,
+            lfs_pointers,
+            ..counted
+        })
+    })
+}
+
 // NOTE: The commented-out function can be tested after specifying the repo file
 // #[cfg(test)]
 // mod tests {
@@ -0,0 +1,428 @@
+use crate::errors::CustomError;
+use crate::models::{InitArgs, PathExclusion, ReportFormat, RulePack};
+use crate::utils::detect_utils::DEFAULT_CONFIG;
+use crate::utils::git_util::common_dir;
+use clap::ValueEnum;
+use std::error::Error;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+
+/// The first line of the hooks `init` installs, so they can be told apart from other hooks.
+const HOOK_MARKER: &str = "# Installed by sensleak init";
+
+/// The rule id prefixes of every pack. Rules matching none of them are in [`RulePack::Saas`].
+const PACK_PREFIXES: &[(RulePack, &[&str])] = &[
+    (
+        RulePack::Generic,
+        &["generic-api-key", "private-key", "jwt", "age secret key"],
+    ),
+    (
+        RulePack::Cloud,
+        &[
+            "alibaba", "authress", "aws", "confluent", "databricks", "datadog", "defined-networking",
+            "digitalocean", "doppler", "dynatrace", "fastly", "gcp", "grafana", "hashicorp",
+            "heroku", "netlify", "new-relic", "okta", "planetscale", "prefect", "pulumi", "sentry",
+            "sumologic", "vault", "yandex",
+        ],
+    ),
+    (
+        RulePack::Code,
+        &[
+            "atlassian", "bitbucket", "clojars", "codecov", "droneci", "github", "gitlab", "npm",
+            "pypi", "rubygems", "travisci",
+        ],
+    ),
+    (
+        RulePack::Payment,
+        &[
+            "bittrex", "coinbase", "easypost", "finicity", "finnhub", "flutterwave", "gocardless",
+            "kraken", "kucoin", "plaid", "shippo", "shopify", "square-", "stripe",
+        ],
+    ),
+    (
+        RulePack::Messaging,
+        &[
+            "discord", "gitter", "intercom", "mailchimp", "mailgun", "mattermost", "messagebird",
+            "microsoft-teams", "sendbird", "sendgrid", "sendinblue", "slack", "telegram", "twilio",
+            "zendesk",
+        ],
+    ),
+];
+
+/// What `init` generates, once the flags and answers are combined.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InitOptions {
+    /// The rule packs to include.
+    pub packs: Vec<RulePack>,
+    /// The groups of paths to add to the global allowlist.
+    pub exclusions: Vec<PathExclusion>,
+    /// The format of the reports written by the hook.
+    pub report_format: ReportFormat,
+    /// Whether to install the pre-commit hook.
+    pub hook: bool,
+}
+
+impl InitOptions {
+    /// Takes the options from the flags, using the defaults for those not given.
+    pub fn from_args(args: &InitArgs) -> Self {
+        InitOptions {
+            packs: if args.packs.is_empty() {
+                RulePack::value_variants().to_vec()
+            } else {
+                args.packs.clone()
+            },
+            exclusions: if args.exclude.is_empty() {
+                default_exclusions()
+            } else {
+                args.exclude.clone()
+            },
+            report_format: args.report_format.unwrap_or_default(),
+            hook: args.hook.unwrap_or(false),
+        }
+    }
+
+    /// Asks for the options not given as flags. An empty answer or the end of the input picks the
+    /// default.
+    ///
+    /// # Errors
+    ///
+    /// This function returns an error if the input can't be read or the questions can't be written.
+    pub fn prompt(
+        args: &InitArgs,
+        input: &mut impl BufRead,
+        output: &mut impl Write,
+    ) -> io::Result<Self> {
+        let mut options = InitOptions::from_args(args);
+        if args.packs.is_empty() {
+            options.packs = ask(input, output, "Rule packs", "all", |answer| {
+                if answer == "all" {
+                    Some(RulePack::value_variants().to_vec())
+                } else {
+                    parse_list(answer)
+                }
+            })?;
+        }
+        if args.exclude.is_empty() {
+            let default = names(&default_exclusions()).join(",");
+            options.exclusions = ask(input, output, "Paths to exclude", &default, |answer| {
+                if answer == "none" {
+                    Some(Vec::new())
+                } else {
+                    parse_list(answer)
+                }
+            })?;
+        }
+        if args.report_format.is_none() {
+            options.report_format = ask(input, output, "Report format", "json", |answer| {
+                ReportFormat::from_str(answer, true).ok()
+            })?;
+        }
+        if args.hook.is_none() {
+            options.hook = ask(input, output, "Install a pre-commit hook (yes, no)", "no", |answer| {
+                match answer {
+                    "y" | "yes" => Some(true),
+                    "n" | "no" => Some(false),
+                    _ => None,
+                }
+            })?;
+        }
+        Ok(options)
+    }
+}
+
+fn default_exclusions() -> Vec<PathExclusion> {
+    vec![
+        PathExclusion::Binaries,
+        PathExclusion::Lockfiles,
+        PathExclusion::Dependencies,
+    ]
+}
+
+fn names<T: ValueEnum>(values: &[T]) -> Vec<String> {
+    values
+        .iter()
+        .filter_map(|value| value.to_possible_value())
+        .map(|value| value.get_name().to_string())
+        .collect()
+}
+
+fn parse_list<T: ValueEnum>(answer: &str) -> Option<Vec<T>> {
+    answer
+        .split(',')
+        .map(|name| T::from_str(name.trim(), true).ok())
+        .collect()
+}
+
+/// Asks a question until the answer parses, returning the parsed default on an empty answer.
+fn ask<T>(
+    input: &mut impl BufRead,
+    output: &mut impl Write,
+    question: &str,
+    default: &str,
+    parse: impl Fn(&str) -> Option<T>,
+) -> io::Result<T> {
+    loop {
+        write!(output, "{} [{}]: ", question, default)?;
+        output.flush()?;
+        let mut answer = String::new();
+        let answer = match input.read_line(&mut answer)? {
+            0 => default,
+            _ if answer.trim().is_empty() => default,
+            _ => answer.trim(),
+        };
+        match parse(&answer.to_lowercase()) {
+            Some(value) => return Ok(value),
+            None => writeln!(output, "Invalid answer {:?}", answer)?,
+        }
+    }
+}
+
+/// Returns the pack a rule of the default config is in.
+pub fn rule_pack(id: &str) -> RulePack {
+    PACK_PREFIXES
+        .iter()
+        .find(|(_, prefixes)| prefixes.iter().any(|prefix| id.starts_with(prefix)))
+        .map_or(RulePack::Saas, |(pack, _)| *pack)
+}
+
+/// Returns the allowlist paths of a group of paths.
+pub fn exclusion_paths(exclusion: PathExclusion) -> &'static [&'static str] {
+    match exclusion {
+        PathExclusion::Binaries => {
+            &[r"(.*?)(jpg|jpeg|png|gif|ico|doc|docx|zip|gz|xls|xlsx|pdf|bin|svg|socket)$"]
+        }
+        PathExclusion::Lockfiles => &[
+            r"(^|/)(gradle\.lockfile|package-lock\.json|pnpm-lock\.yaml|yarn\.lock|Cargo\.lock|go\.sum|poetry\.lock|Gemfile\.lock|composer\.lock)$",
+        ],
+        PathExclusion::Dependencies => &[r"(^|/)(node_modules|vendor|third_party)/.*$"],
+        PathExclusion::Tests => &[r"(^|/)(tests?|__tests__|spec|fixtures|testdata)/.*$"],
+        PathExclusion::Docs => &[r"(.*?)\.(md|rst|adoc)$"],
+    }
+}
+
+/// Generates a config with the rules of the default config in the given packs.
+///
+/// The global allowlist has the paths of the given groups, and the config files themselves.
+///
+/// # Errors
+///
+/// This function returns an error if a rule of the default config can't be parsed.
+pub fn generate_config(
+    packs: &[RulePack],
+    exclusions: &[PathExclusion],
+) -> Result<String, Box<dyn Error>> {
+    let mut config = String::from(
+        "# Generated by `sensleak init`, see https://github.com/gitleaks/gitleaks#configuration.\n\
+         # The paths need to start with a \"/\".\n\
+         title = \"gitleaks config\"\n\n\
+         [allowlist]\n\
+         description = \"global allow lists\"\n\
+         paths = [\n",
+    );
+    let paths = exclusions
+        .iter()
+        .flat_map(|exclusion| exclusion_paths(*exclusion).iter())
+        .chain(&["/gitleaks.toml", "/.gitleaks.toml"]);
+    for path in paths {
+        config.push_str(&format!("    '''{}''',\n", path));
+    }
+    config.push_str("]\n");
+
+    let rules = DEFAULT_CONFIG
+        .find("\n[[rules]]")
+        .map_or("", |start| &DEFAULT_CONFIG[start..]);
+    for rule in rules.split("\n[[rules]]").filter(|rule| !rule.trim().is_empty()) {
+        let rule = format!("[[rules]]{}", rule);
+        let id = rule_id(&rule)?;
+        if packs.contains(&rule_pack(&id)) {
+            config.push('\n');
+            config.push_str(rule.trim_end());
+            config.push('\n');
+        }
+    }
+    Ok(config)
+}
+
+fn rule_id(rule: &str) -> Result<String, Box<dyn Error>> {
+    let value: toml::Value = toml::from_str(rule)?;
+    value
+        .get("rules")
+        .and_then(|rules| rules.get(0))
+        .and_then(|rule| rule.get("id"))
+        .and_then(|id| id.as_str())
+        .map(|id| id.to_string())
+        .ok_or_else(|| Box::new(CustomError::InvalidTomlFile) as Box<dyn Error>)
+}
+
+/// Installs a pre-commit hook scanning the uncommitted files of a repository with a config.
+///
+/// The hook writes its report next to the hooks, e.g. `.git/sensleak-report.json`, and fails the
+/// commit if the scan reports leaks.
+///
+/// # Returns
+///
+/// Returns the path of the hook.
+///
+/// # Errors
+///
+/// This function returns an error if the repository can't be opened, a hook not installed by `init`
+/// exists and `force` isn't set, or the hook can't be written.
+pub fn install_hook(
+    repo: &str,
+    config: &Path,
+    report_format: ReportFormat,
+    force: bool,
+) -> Result<PathBuf, Box<dyn Error>> {
+    let repo = git2::Repository::discover(repo)?;
+    // Linked worktrees run the hooks of the repository they were added to
+    let hooks = common_dir(&repo).join("hooks");
+    let hook = hooks.join("pre-commit");
+    if hook.exists() && !force {
+        let installed = fs::read_to_string(&hook).is_ok_and(|hook| hook.contains(HOOK_MARKER));
+        if !installed {
+            return Err(Box::new(CustomError::FileExists {
+                path: hook.to_string_lossy().to_string(),
+            }));
+        }
+    }
+
+    let scan = std::env::current_exe()?;
+    let report = common_dir(&repo).join(format!("sensleak-report.{}", report_format.as_str()));
+    let script = format!(
+        "#!/bin/sh\n\
+         {marker}\n\
+         # The scan itself always exits with 0, the leaks are counted in its summary line.\n\
+         output=$(\"{scan}\" --repo . --config \"{config}\" --uncommitted \
+         --report \"{report}\" --report-format {format}) || exit 1\n\
+         echo \"$output\"\n\
+         if ! echo \"$output\" | grep -q ']0 leaks detected'; then\n\
+         \x20   echo \"sensleak found leaks, see {report}\" >&2\n\
+         \x20   exit 1\n\
+         fi\n",
+        marker = HOOK_MARKER,
+        scan = scan.display(),
+        config = config.display(),
+        report = report.display(),
+        format = report_format.as_str(),
+    );
+    fs::create_dir_all(&hooks)?;
+    fs::write(&hook, script)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&hook, fs::Permissions::from_mode(0o755))?;
+    }
+    Ok(hook)
+}
+
+/// Writes the config and installs the hook `init` was asked for.
+///
+/// # Errors
+///
+/// This function returns an error if the config exists and `args.force` isn't set, or the config or
+/// the hook can't be written.
+pub fn init(args: &InitArgs, options: &InitOptions) -> Result<(), Box<dyn Error>> {
+    let output = Path::new(&args.output);
+    if output.exists() && !args.force {
+        return Err(Box::new(CustomError::FileExists {
+            path: args.output.clone(),
+        }));
+    }
+    let config = generate_config(&options.packs, &options.exclusions)?;
+    let rules = config.matches("[[rules]]").count();
+    fs::write(output, config)?;
+    println!("Wrote {} rules to {}", rules, args.output);
+
+    if options.hook {
+        let config = fs::canonicalize(output)?;
+        let hook = install_hook(&args.repo, &config, options.report_format, args.force)?;
+        println!("Installed the pre-commit hook {}", hook.display());
+    } else {
+        println!(
+            "Scan with: scan --repo . --config {} --report report.{} --report-format {}",
+            args.output,
+            options.report_format.as_str(),
+            options.report_format.as_str()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::detect_utils::load_config_from_str;
+    use clap::Parser;
+
+    fn init_args(flags: &[&str]) -> InitArgs {
+        let args = crate::models::Config::parse_from([&["scan", "init"], flags].concat());
+        match args.command {
+            Some(crate::models::Command::Init(args)) => args,
+            _ => panic!("expected the init subcommand"),
+        }
+    }
+
+    #[test]
+    fn test_rule_pack() {
+        assert_eq!(rule_pack("aws-access-token"), RulePack::Cloud);
+        assert_eq!(rule_pack("github-pat"), RulePack::Code);
+        assert_eq!(rule_pack("square-access-token"), RulePack::Payment);
+        assert_eq!(rule_pack("squarespace-access-token"), RulePack::Saas);
+        assert_eq!(rule_pack("private-key"), RulePack::Generic);
+    }
+
+    #[test]
+    fn test_generate_config() {
+        let all = generate_config(RulePack::value_variants(), &[]).unwrap();
+        let all = load_config_from_str(&all).unwrap();
+        let default = load_config_from_str(DEFAULT_CONFIG).unwrap();
+        assert_eq!(all.ruleslist.len(), default.ruleslist.len());
+
+        let config = generate_config(&[RulePack::Payment], &[PathExclusion::Lockfiles]).unwrap();
+        let scan = load_config_from_str(&config).unwrap();
+        assert!(scan.ruleslist.iter().any(|rule| rule.id == "stripe-access-token"));
+        assert!(scan
+            .ruleslist
+            .iter()
+            .all(|rule| rule_pack(&rule.id) == RulePack::Payment));
+        assert_eq!(scan.allowlist.paths.len(), 3);
+        assert!(regex::Regex::new(&scan.allowlist.paths[0])
+            .unwrap()
+            .is_match("/web/package-lock.json"));
+    }
+
+    #[test]
+    fn test_prompt() {
+        let args = init_args(&["--report-format", "sarif"]);
+        let mut input = io::Cursor::new("cloud, code\nbogus\nnone\nyes\n");
+        let mut output = Vec::new();
+        let options = InitOptions::prompt(&args, &mut input, &mut output).unwrap();
+        assert_eq!(options.packs, vec![RulePack::Cloud, RulePack::Code]);
+        assert!(options.exclusions.is_empty());
+        assert_eq!(options.report_format, ReportFormat::Sarif);
+        assert!(options.hook);
+        assert!(String::from_utf8(output).unwrap().contains("Invalid answer \"bogus\""));
+
+        let options = InitOptions::prompt(&args, &mut io::Cursor::new(""), &mut Vec::new()).unwrap();
+        assert_eq!(options, InitOptions::from_args(&args));
+    }
+
+    #[test]
+    fn test_install_hook() {
+        let dir = tempfile::tempdir().unwrap();
+        git2::Repository::init(dir.path()).unwrap();
+        let repo = dir.path().to_str().unwrap();
+        let config = dir.path().join("gitleaks.toml");
+
+        let hook = install_hook(repo, &config, ReportFormat::Json, false).unwrap();
+        let script = fs::read_to_string(&hook).unwrap();
+        assert!(script.contains(HOOK_MARKER));
+        assert!(script.contains("--uncommitted"));
+        // Reinstalling replaces the hook, other hooks are kept.
+        install_hook(repo, &config, ReportFormat::Csv, false).unwrap();
+        fs::write(&hook, "#!/bin/sh\n").unwrap();
+        assert!(install_hook(repo, &config, ReportFormat::Json, false).is_err());
+        assert!(install_hook(repo, &config, ReportFormat::Json, true).is_ok());
+    }
+}
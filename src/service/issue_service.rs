@@ -0,0 +1,491 @@
+use crate::errors::CustomError;
+use crate::models::{IssueConfig, IssueDedup, IssueTracker, Leak};
+use crate::utils::http_util::{self, sha256_hex};
+use base64::Engine;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::error::Error;
+
+/// How many issues are listed per request.
+const PAGE_SIZE: usize = 100;
+
+/// What [`sync_issues`] changed in the tracker.
+#[derive(Debug, Default, PartialEq)]
+pub struct IssueSync {
+    /// The ids of the issues opened for new leaks.
+    pub opened: Vec<String>,
+    /// The ids of the issues closed because their leaks are no longer found.
+    pub closed: Vec<String>,
+}
+
+/// An issue tracker sensleak opens and closes tickets in.
+///
+/// Every issue carries a marker, `sensleak-` and the first 16 hex digits of a SHA-256 digest of
+/// what identifies its leaks, which is how later scans find the issue again.
+pub trait Tracker {
+    /// Returns the open issues with a marker, by marker.
+    fn open_issues(&self) -> Result<HashMap<String, String>, String>;
+
+    /// Opens an issue and returns its id.
+    fn create_issue(&self, marker: &str, title: &str, body: &str) -> Result<String, String>;
+
+    /// Closes an issue as completed.
+    fn close_issue(&self, id: &str) -> Result<(), String>;
+}
+
+/// Reads the `[issues]` table of a config.
+///
+/// # Errors
+///
+/// Returns `CustomError::InvalidTomlFile` if the config can't be parsed or the table is invalid,
+/// and `CustomError::IssueTrackerError` if there is no table.
+pub fn issue_config(toml_str: &str) -> Result<IssueConfig, Box<dyn Error>> {
+    let config: toml::Value = toml::from_str(toml_str).map_err(|_| CustomError::InvalidTomlFile)?;
+    let table = config
+        .get("issues")
+        .ok_or_else(|| CustomError::IssueTrackerError {
+            tracker: "the config".to_string(),
+            reason: "there is no [issues] table".to_string(),
+        })?;
+    Ok(table
+        .clone()
+        .try_into()
+        .map_err(|_| CustomError::InvalidTomlFile)?)
+}
+
+/// Syncs the issues of a scan with the tracker set up by the `[issues]` table of a config, see
+/// [`sync_issues`].
+///
+/// # Arguments
+///
+/// * `tracker` - The tracker given to `--create-issues`.
+/// * `toml_str` - The config with the `[issues]` table.
+/// * `leaks` - The leaks found by the scan.
+/// * `whole_history` - Whether the scan saw every leak, so that issues without one can be closed.
+///
+/// # Errors
+///
+/// Returns the errors of [`issue_config`] and [`connect`], and `CustomError::IssueTrackerError`
+/// if the tracker rejects a request.
+pub fn create_issues(
+    tracker: IssueTracker,
+    toml_str: &str,
+    leaks: &[Leak],
+    whole_history: bool,
+) -> Result<IssueSync, Box<dyn Error>> {
+    let config = issue_config(toml_str)?;
+    let client = connect(tracker, &config)?;
+    let close_resolved = config.close_resolved && whole_history;
+    Ok(sync_issues(client.as_ref(), &config, leaks, close_resolved)
+        .map_err(|reason| tracker_error(tracker, reason))?)
+}
+
+/// Connects to a tracker with the credentials in the environment.
+///
+/// # Errors
+///
+/// Returns `CustomError::IssueTrackerError` if a credential isn't set, or Jira has no `url`.
+pub fn connect(
+    tracker: IssueTracker,
+    config: &IssueConfig,
+) -> Result<Box<dyn Tracker>, Box<dyn Error>> {
+    let var = |name: &str| {
+        env::var(name).map_err(|_| tracker_error(tracker, format!("{} isn't set", name)))
+    };
+    Ok(match tracker {
+        IssueTracker::Github => Box::new(GitHub {
+            api: config
+                .url
+                .clone()
+                .unwrap_or_else(|| "https://api.github.com".to_string()),
+            repo: config.project.clone(),
+            labels: config.labels.clone(),
+            token: var("GITHUB_TOKEN")?,
+        }),
+        IssueTracker::Jira => Box::new(Jira {
+            url: config
+                .url
+                .clone()
+                .ok_or_else(|| tracker_error(tracker, "the [issues] table has no url".to_string()))?,
+            project: config.project.clone(),
+            labels: config.labels.clone(),
+            issue_type: config.issue_type.clone(),
+            auth: base64::engine::general_purpose::STANDARD
+                .encode(format!("{}:{}", var("JIRA_EMAIL")?, var("JIRA_API_TOKEN")?)),
+        }),
+    })
+}
+
+/// Opens an issue for every new leak, and with `close_resolved`, closes the issues of the leaks
+/// that are no longer found. Leaks share an issue as `config.dedup` says.
+///
+/// # Errors
+///
+/// Returns the reason the tracker rejected a request. The issues changed until then stay changed,
+/// and are found again by the next scan.
+pub fn sync_issues(
+    tracker: &dyn Tracker,
+    config: &IssueConfig,
+    leaks: &[Leak],
+    close_resolved: bool,
+) -> Result<IssueSync, String> {
+    let mut found: BTreeMap<String, &Leak> = BTreeMap::new();
+    for leak in leaks {
+        found.entry(marker(leak, config.dedup)).or_insert(leak);
+    }
+
+    let open = tracker.open_issues()?;
+    let mut sync = IssueSync::default();
+    for (marker, leak) in &found {
+        if !open.contains_key(marker) {
+            let id = tracker.create_issue(marker, &issue_title(leak), &issue_body(leak))?;
+            sync.opened.push(id);
+        }
+    }
+    if close_resolved {
+        let mut resolved: Vec<&String> = open
+            .iter()
+            .filter(|(marker, _)| !found.contains_key(*marker))
+            .map(|(_, id)| id)
+            .collect();
+        resolved.sort();
+        for id in resolved {
+            tracker.close_issue(id)?;
+            sync.closed.push(id.clone());
+        }
+    }
+    Ok(sync)
+}
+
+/// Returns the marker of the issue a leak belongs to, see [`Tracker`].
+pub fn marker(leak: &Leak, dedup: IssueDedup) -> String {
+    let key = match dedup {
+        IssueDedup::Fingerprint => leak.fingerprint(),
+        IssueDedup::Secret => format!("{}:{}", leak.rule_id, leak.offender),
+    };
+    format!("sensleak-{}", &sha256_hex(key.as_bytes())[..16])
+}
+
+fn issue_title(leak: &Leak) -> String {
+    format!("{} leaked in {}", leak.rule, leak.file.trim_start_matches('/'))
+}
+
+/// Describes where a leak is, without the secret, which the issue must not spread further.
+fn issue_body(leak: &Leak) -> String {
+    let mut body = format!(
+        "sensleak found a secret matching the rule {} ({}).\n\n",
+        leak.rule, leak.rule_id
+    );
+    body.push_str(&format!(
+        "- File: {}, line {}\n",
+        leak.file.trim_start_matches('/'),
+        leak.line_number
+    ));
+    if !leak.commit.is_empty() {
+        body.push_str(&format!(
+            "- Commit: {} by {} on {}\n",
+            leak.commit, leak.author, leak.date
+        ));
+        body.push_str(&format!(
+            "- Still in HEAD: {}\n",
+            if leak.present_in_head { "yes" } else { "no" }
+        ));
+    }
+    body.push_str(&format!("- Fingerprint: {}\n\n", leak.fingerprint()));
+    body.push_str(
+        "Rotate the secret, then remove it or allowlist it, and the issue is closed by the next scan.\n",
+    );
+    body
+}
+
+fn tracker_error(tracker: IssueTracker, reason: String) -> CustomError {
+    CustomError::IssueTrackerError {
+        tracker: match tracker {
+            IssueTracker::Github => "GitHub".to_string(),
+            IssueTracker::Jira => "Jira".to_string(),
+        },
+        reason,
+    }
+}
+
+/// GitHub issues, marked with an HTML comment in the body.
+struct GitHub {
+    api: String,
+    repo: String,
+    labels: Vec<String>,
+    token: String,
+}
+
+impl GitHub {
+    fn send(&self, method: &str, path: &str, body: Option<&Value>) -> Result<Value, String> {
+        let url = format!("{}/repos/{}{}", self.api.trim_end_matches('/'), self.repo, path);
+        let auth = format!("Bearer {}", self.token);
+        let headers = [("Authorization", auth.as_str()), ("Accept", "application/vnd.github+json")];
+        http_util::send_json(method, &url, &headers, body)
+    }
+}
+
+impl Tracker for GitHub {
+    fn open_issues(&self) -> Result<HashMap<String, String>, String> {
+        let mut issues = HashMap::new();
+        for page in 1.. {
+            let path = format!(
+                "/issues?state=open&labels={}&per_page={}&page={}",
+                http_util::encode_query(&self.labels.join(",")),
+                PAGE_SIZE,
+                page
+            );
+            let listed = self.send("GET", &path, None)?;
+            let listed = listed.as_array().cloned().unwrap_or_default();
+            for issue in &listed {
+                // Pull requests are listed as issues too
+                if issue.get("pull_request").is_some() {
+                    continue;
+                }
+                let body = issue["body"].as_str().unwrap_or_default();
+                if let (Some(marker), Some(number)) = (find_marker(body), issue["number"].as_u64()) {
+                    issues.insert(marker, number.to_string());
+                }
+            }
+            if listed.len() < PAGE_SIZE {
+                break;
+            }
+        }
+        Ok(issues)
+    }
+
+    fn create_issue(&self, marker: &str, title: &str, body: &str) -> Result<String, String> {
+        let request = json!({
+            "title": title,
+            "body": format!("{}\n<!-- {} -->\n", body, marker),
+            "labels": self.labels,
+        });
+        let created = self.send("POST", "/issues", Some(&request))?;
+        created["number"]
+            .as_u64()
+            .map(|number| number.to_string())
+            .ok_or_else(|| "the created issue has no number".to_string())
+    }
+
+    fn close_issue(&self, id: &str) -> Result<(), String> {
+        let request = json!({ "state": "closed", "state_reason": "completed" });
+        self.send("PATCH", &format!("/issues/{}", id), Some(&request))?;
+        Ok(())
+    }
+}
+
+/// Returns the marker in the body of a GitHub issue.
+fn find_marker(body: &str) -> Option<String> {
+    let start = body.find("<!-- sensleak-")? + "<!-- ".len();
+    let end = start + body[start..].find(" -->")?;
+    Some(body[start..end].to_string())
+}
+
+/// Jira issues, marked with a label.
+struct Jira {
+    url: String,
+    project: String,
+    labels: Vec<String>,
+    issue_type: String,
+    auth: String,
+}
+
+impl Jira {
+    fn send(&self, method: &str, path: &str, body: Option<&Value>) -> Result<Value, String> {
+        let url = format!("{}/rest/api/2{}", self.url.trim_end_matches('/'), path);
+        let auth = format!("Basic {}", self.auth);
+        let headers = [("Authorization", auth.as_str()), ("Accept", "application/json")];
+        http_util::send_json(method, &url, &headers, body)
+    }
+}
+
+impl Tracker for Jira {
+    fn open_issues(&self) -> Result<HashMap<String, String>, String> {
+        let mut jql = format!("project = \"{}\" AND statusCategory != Done", self.project);
+        for label in &self.labels {
+            jql.push_str(&format!(" AND labels = \"{}\"", label));
+        }
+        let mut issues = HashMap::new();
+        let mut start_at = 0;
+        loop {
+            let request = json!({
+                "jql": jql,
+                "startAt": start_at,
+                "maxResults": PAGE_SIZE,
+                "fields": ["labels"],
+            });
+            let found = self.send("POST", "/search", Some(&request))?;
+            let listed = found["issues"].as_array().cloned().unwrap_or_default();
+            for issue in &listed {
+                let key = issue["key"].as_str().unwrap_or_default();
+                let labels = issue["fields"]["labels"].as_array().cloned().unwrap_or_default();
+                if let Some(marker) = labels
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .find(|label| label.starts_with("sensleak-"))
+                {
+                    issues.insert(marker.to_string(), key.to_string());
+                }
+            }
+            start_at += listed.len();
+            if listed.len() < PAGE_SIZE {
+                break;
+            }
+        }
+        Ok(issues)
+    }
+
+    fn create_issue(&self, marker: &str, title: &str, body: &str) -> Result<String, String> {
+        let mut labels = self.labels.clone();
+        labels.push(marker.to_string());
+        let request = json!({
+            "fields": {
+                "project": { "key": self.project },
+                "issuetype": { "name": self.issue_type },
+                "summary": title,
+                "description": body,
+                "labels": labels,
+            }
+        });
+        let created = self.send("POST", "/issue", Some(&request))?;
+        created["key"]
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| "the created issue has no key".to_string())
+    }
+
+    fn close_issue(&self, id: &str) -> Result<(), String> {
+        // Workflows differ, any transition to a done status closes the issue
+        let path = format!("/issue/{}/transitions", id);
+        let transitions = self.send("GET", &path, None)?;
+        let transition = transitions["transitions"]
+            .as_array()
+            .and_then(|transitions| {
+                transitions
+                    .iter()
+                    .find(|transition| transition["to"]["statusCategory"]["key"] == "done")
+            })
+            .and_then(|transition| transition["id"].as_str())
+            .ok_or_else(|| format!("{} has no transition to a done status", id))?;
+        let request = json!({ "transition": { "id": transition } });
+        self.send("POST", &path, Some(&request))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::Matcher;
+
+    fn leak(file: &str, offender: &str) -> Leak {
+        Leak {
+            file: file.to_string(),
+            offender: offender.to_string(),
+            commit: "abc123".to_string(),
+            rule: "Stripe Access Token".to_string(),
+            rule_id: "stripe-access-token".to_string(),
+            line_number: 1,
+            ..Default::default()
+        }
+    }
+
+    fn config(url: &str) -> IssueConfig {
+        issue_config(&format!(
+            "[issues]\nproject = \"org/app\"\nurl = \"{}\"\nlabels = [\"secret leak\"]\n",
+            url
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_issue_config() {
+        let config = config("https://example.com");
+        assert_eq!(config.project, "org/app");
+        assert_eq!(config.labels, ["secret leak"]);
+        assert_eq!(config.dedup, IssueDedup::Fingerprint);
+        assert!(config.close_resolved);
+        assert!(issue_config("title = \"no issues\"").is_err());
+
+        let config = issue_config("[issues]\nproject = \"SEC\"\ndedup = \"secret\"").unwrap();
+        assert_eq!(config.labels, ["sensleak"]);
+        assert_eq!(config.dedup, IssueDedup::Secret);
+    }
+
+    #[test]
+    fn test_marker() {
+        let leaks = [leak("a.txt", "sk_test_1"), leak("b.txt", "sk_test_1")];
+        assert_ne!(
+            marker(&leaks[0], IssueDedup::Fingerprint),
+            marker(&leaks[1], IssueDedup::Fingerprint)
+        );
+        assert_eq!(
+            marker(&leaks[0], IssueDedup::Secret),
+            marker(&leaks[1], IssueDedup::Secret)
+        );
+        assert!(marker(&leaks[0], IssueDedup::Secret).starts_with("sensleak-"));
+        assert_eq!(
+            find_marker("text\n<!-- sensleak-0123456789abcdef -->\n").as_deref(),
+            Some("sensleak-0123456789abcdef")
+        );
+    }
+
+    #[test]
+    fn test_sync_github_issues() {
+        let mut server = mockito::Server::new();
+        let config = config(&server.url());
+        let leaks = [leak("a.txt", "sk_test_1"), leak("b.txt", "sk_test_2")];
+        let existing = marker(&leaks[0], IssueDedup::Fingerprint);
+        let created = marker(&leaks[1], IssueDedup::Fingerprint);
+
+        let listed = json!([
+            { "number": 1, "body": format!("old\n<!-- {} -->\n", existing) },
+            { "number": 2, "body": "<!-- sensleak-00000000000000ff -->" },
+            { "number": 3, "body": "<!-- sensleak-00000000000000ee -->", "pull_request": {} },
+        ]);
+        let list = server
+            .mock("GET", "/repos/org/app/issues")
+            .match_query(Matcher::UrlEncoded("labels".into(), "secret leak".into()))
+            .match_header("authorization", "Bearer token")
+            .with_body(listed.to_string())
+            .create();
+        let create = server
+            .mock("POST", "/repos/org/app/issues")
+            .match_body(Matcher::Regex(format!("{}.*b\\.txt|b\\.txt.*{}", created, created)))
+            .with_status(201)
+            .with_body(r#"{"number": 4}"#)
+            .expect(1)
+            .create();
+        let close = server
+            .mock("PATCH", "/repos/org/app/issues/2")
+            .match_body(Matcher::PartialJson(json!({ "state": "closed" })))
+            .with_body("{}")
+            .expect(1)
+            .create();
+
+        let tracker = GitHub {
+            api: server.url(),
+            repo: config.project.clone(),
+            labels: config.labels.clone(),
+            token: "token".to_string(),
+        };
+        let sync = sync_issues(&tracker, &config, &leaks, true).unwrap();
+        assert_eq!(sync, IssueSync { opened: vec!["4".into()], closed: vec!["2".into()] });
+        list.assert();
+        create.assert();
+        close.assert();
+
+        // Scans of part of the history leave the other issues open
+        let sync = sync_issues(&tracker, &config, &leaks[..1], false).unwrap();
+        assert_eq!(sync, IssueSync::default());
+    }
+
+    #[test]
+    fn test_issue_body_omits_secret() {
+        let body = issue_body(&leak("/src/a.txt", "sk_test_1"));
+        assert!(body.contains("src/a.txt, line 1"));
+        assert!(body.contains("abc123:src/a.txt:stripe-access-token:1"));
+        assert!(!body.contains("sk_test_1"));
+    }
+}
@@ -0,0 +1,265 @@
+use crate::errors::CustomError;
+use crate::models::{Leak, LspArgs, Scan};
+use crate::service::detect_service::detect_bytes;
+use crate::utils::detect_utils::{diagnostic_level, load_config_file};
+use serde_json::{json, Value};
+use std::error::Error;
+use std::io::{self, BufRead, Write};
+
+/// The code of the error answering requests the server doesn't handle.
+const METHOD_NOT_FOUND: i64 = -32601;
+
+/// Runs the language server of `lsp` on stdin and stdout, until the editor exits it.
+///
+/// # Errors
+///
+/// Returns an error if the config can't be loaded, or a message can't be read or written.
+pub fn serve_lsp(args: &LspArgs) -> Result<(), Box<dyn Error>> {
+    let scan = load_config_file(&args.config)?;
+    serve(&scan, &mut io::stdin().lock(), &mut io::stdout().lock())
+}
+
+/// Answers the messages of an editor, a minimal language server that only publishes diagnostics.
+///
+/// Documents are synced in full: every time one is opened or changed, its text is scanned with
+/// the rules and allowlist of `scan` and its diagnostics are replaced with the leaks found, and
+/// they are cleared when it is closed. Requests other than `initialize` and `shutdown` are
+/// answered with an error.
+///
+/// # Errors
+///
+/// Returns `CustomError::LspError` if a message isn't framed with a `Content-Length` header or
+/// isn't JSON, and the error of `input` or `output` if they fail.
+pub fn serve(scan: &Scan, input: &mut impl BufRead, output: &mut impl Write) -> Result<(), Box<dyn Error>> {
+    while let Some(message) = read_message(input)? {
+        let id = message.get("id").cloned();
+        let params = &message["params"];
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+        match message["method"].as_str().unwrap_or_default() {
+            "initialize" => {
+                let capabilities = json!({
+                    "textDocumentSync": { "openClose": true, "change": 1 },
+                });
+                let result = json!({
+                    "capabilities": capabilities,
+                    "serverInfo": { "name": "sensleak", "version": env!("CARGO_PKG_VERSION") },
+                });
+                write_message(output, &json!({ "jsonrpc": "2.0", "id": id, "result": result }))?;
+            }
+            "shutdown" => write_message(output, &json!({ "jsonrpc": "2.0", "id": id, "result": null }))?,
+            "exit" => return Ok(()),
+            "textDocument/didOpen" => {
+                let text = params["textDocument"]["text"].as_str().unwrap_or_default();
+                publish(output, uri, diagnostics(scan, uri, text))?;
+            }
+            "textDocument/didChange" => {
+                // With full sync, the last change holds the whole text
+                let text = params["contentChanges"]
+                    .as_array()
+                    .and_then(|changes| changes.last())
+                    .and_then(|change| change["text"].as_str());
+                if let Some(text) = text {
+                    publish(output, uri, diagnostics(scan, uri, text))?;
+                }
+            }
+            "textDocument/didClose" => publish(output, uri, Vec::new())?,
+            method if id.is_some() => {
+                let error = json!({ "code": METHOD_NOT_FOUND, "message": format!("{} isn't supported", method) });
+                write_message(output, &json!({ "jsonrpc": "2.0", "id": id, "error": error }))?;
+            }
+            // Other notifications, e.g. initialized
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Returns the diagnostics of the leaks in the text of a document.
+///
+/// Leaks in the cells of notebooks are left out, as their lines count from the start of their
+/// cell rather than of the file.
+pub fn diagnostics(scan: &Scan, uri: &str, text: &str) -> Vec<Value> {
+    let path = uri_path(uri);
+    detect_bytes(text.as_bytes(), &path, &scan.ruleslist, &scan.allowlist, None)
+        .iter()
+        .filter(|leak| leak.cell.is_none())
+        .map(diagnostic)
+        .collect()
+}
+
+/// Returns the diagnostic of a leak, spanning its secret on its line. Positions are counted in
+/// UTF-16 code units, as LSP counts them.
+pub fn diagnostic(leak: &Leak) -> Value {
+    let utf16 = |text: &str| text.chars().map(char::len_utf16).sum::<usize>();
+    let before: String = leak.line.chars().take(leak.column.saturating_sub(1) as usize).collect();
+    let start = utf16(&before);
+    let end = start + utf16(leak.offender.lines().next().unwrap_or_default());
+    let line = leak.line_number.saturating_sub(1);
+    let severity = match diagnostic_level(leak.severity.as_deref()) {
+        "error" => 1,
+        "warning" => 2,
+        _ => 3,
+    };
+    json!({
+        "range": {
+            "start": { "line": line, "character": start },
+            "end": { "line": line, "character": end },
+        },
+        "severity": severity,
+        "code": leak.rule_id,
+        "source": "sensleak",
+        "message": leak.rule,
+    })
+}
+
+/// Replaces the diagnostics of a document.
+fn publish(output: &mut impl Write, uri: &str, diagnostics: Vec<Value>) -> Result<(), Box<dyn Error>> {
+    write_message(
+        output,
+        &json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/publishDiagnostics",
+            "params": { "uri": uri, "diagnostics": diagnostics },
+        }),
+    )
+}
+
+/// Returns the path of a `file://` URI, which rule and allowlist paths are matched against.
+fn uri_path(uri: &str) -> String {
+    let path = uri.strip_prefix("file://").unwrap_or(uri).as_bytes();
+    let mut decoded = Vec::with_capacity(path.len());
+    let mut i = 0;
+    while i < path.len() {
+        let escaped = path.get(i + 1..i + 3).and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match (path[i], escaped) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Reads a message framed by its headers, e.g. `Content-Length: 52\r\n\r\n{...}`.
+///
+/// # Returns
+///
+/// Returns `None` once the input ends.
+fn read_message(input: &mut impl BufRead) -> Result<Option<Value>, Box<dyn Error>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+    let length = length.ok_or_else(|| CustomError::LspError {
+        reason: "a message has no Content-Length header".to_string(),
+    })?;
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+    let message = serde_json::from_slice(&body).map_err(|err| CustomError::LspError {
+        reason: format!("a message isn't JSON: {}", err),
+    })?;
+    Ok(Some(message))
+}
+
+fn write_message(output: &mut impl Write, message: &Value) -> Result<(), Box<dyn Error>> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::detect_utils::load_config_from_str;
+
+    const CONFIG: &str = r#"
+[[rules]]
+id = "stripe-access-token"
+description = "Stripe"
+regex = '''sk_(test|live)_[0-9a-z]{10,32}'''
+keywords = ["sk_test", "sk_live"]
+severity = "high"
+"#;
+
+    fn frame(message: Value) -> String {
+        let body = message.to_string();
+        format!("Content-Length: {}\r\n\r\n{}", body.len(), body)
+    }
+
+    fn messages(output: &[u8]) -> Vec<Value> {
+        let mut output = output;
+        let mut messages = Vec::new();
+        while let Some(message) = read_message(&mut output).unwrap() {
+            messages.push(message);
+        }
+        messages
+    }
+
+    #[test]
+    fn test_serve() {
+        let scan = load_config_from_str(CONFIG).unwrap();
+        let uri = "file:///work/my%20app/config.py";
+        let input = [
+            frame(json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {} })),
+            frame(json!({ "jsonrpc": "2.0", "method": "initialized", "params": {} })),
+            frame(json!({ "jsonrpc": "2.0", "method": "textDocument/didOpen", "params": {
+                "textDocument": { "uri": uri, "languageId": "python", "version": 1, "text": "import os\n\u{1f511} = 'sk_live_1234567890abcdef'\n" },
+            }})),
+            frame(json!({ "jsonrpc": "2.0", "method": "textDocument/didChange", "params": {
+                "textDocument": { "uri": uri, "version": 2 },
+                "contentChanges": [{ "text": "import os\n" }],
+            }})),
+            frame(json!({ "jsonrpc": "2.0", "id": 2, "method": "textDocument/hover", "params": {} })),
+            frame(json!({ "jsonrpc": "2.0", "id": 3, "method": "shutdown" })),
+            frame(json!({ "jsonrpc": "2.0", "method": "exit" })),
+        ]
+        .concat();
+        let mut output = Vec::new();
+        serve(&scan, &mut input.as_bytes(), &mut output).unwrap();
+
+        let messages = messages(&output);
+        assert_eq!(messages.len(), 5);
+        assert_eq!(messages[0]["id"], 1);
+        assert_eq!(messages[0]["result"]["capabilities"]["textDocumentSync"]["change"], 1);
+        let opened = &messages[1]["params"];
+        assert_eq!(opened["uri"], uri);
+        assert_eq!(
+            opened["diagnostics"],
+            json!([{
+                "range": { "start": { "line": 1, "character": 6 }, "end": { "line": 1, "character": 30 } },
+                "severity": 1,
+                "code": "stripe-access-token",
+                "source": "sensleak",
+                "message": "Stripe",
+            }])
+        );
+        assert_eq!(messages[2]["params"]["diagnostics"], json!([]));
+        assert_eq!(messages[3]["error"]["code"], METHOD_NOT_FOUND);
+        assert_eq!(messages[4], json!({ "jsonrpc": "2.0", "id": 3, "result": null }));
+        assert_eq!(uri_path(uri), "/work/my app/config.py");
+    }
+
+    #[test]
+    fn test_read_message_without_length() {
+        let mut input = "Content-Type: application/json\r\n\r\n{}".as_bytes();
+        assert!(read_message(&mut input).is_err());
+    }
+}
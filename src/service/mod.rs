@@ -1,3 +1,18 @@
+pub mod app_service;
+pub mod auth_service;
 pub mod detect_service;
+pub mod gist_service;
 pub mod git_service;
-pub mod db_service;
\ No newline at end of file
+pub mod db_service;
+pub mod dashboard_service;
+pub mod bench_service;
+pub mod check_run_service;
+pub mod ci_service;
+pub mod init_service;
+pub mod issue_service;
+pub mod lsp_service;
+pub mod package_service;
+pub mod pr_comment_service;
+pub mod plan_service;
+pub mod repos_service;
+pub mod watch_service;
\ No newline at end of file
@@ -0,0 +1,211 @@
+use crate::errors::CustomError;
+use crate::models::{Leak, PackageArgs, PackageRegistry, ReportMetadata, Scan};
+use crate::service::detect_service::{detect_bytes, write_report};
+use crate::utils::archive_util::read_archive;
+use crate::utils::detect_utils::{load_config_file, ruleset_hash, sort_leaks};
+use crate::utils::http_util;
+use crate::utils::log_util::is_quiet;
+use chrono::Local;
+use serde_json::Value;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use std::time::Instant;
+
+/// Splits `name@version`, keeping the `@` of scoped npm packages such as `@scope/name@1.0.0`.
+pub fn parse_package(spec: &str) -> Option<(&str, &str)> {
+    let (name, version) = spec.rsplit_once('@')?;
+    if name.is_empty() || version.is_empty() {
+        return None;
+    }
+    Some((name, version))
+}
+
+/// Returns the URL the archive of a crate or npm package is downloaded from. PyPI packages are
+/// looked up first, see [`pypi_archive_url`].
+pub fn archive_url(registry: PackageRegistry, name: &str, version: &str) -> Option<String> {
+    match registry {
+        PackageRegistry::Crates => Some(format!(
+            "https://static.crates.io/crates/{}/{}-{}.crate",
+            name, name, version
+        )),
+        PackageRegistry::Npm => {
+            // The tarballs of scoped packages are named without the scope
+            let basename = name.rsplit('/').next().unwrap_or(name);
+            Some(format!(
+                "https://registry.npmjs.org/{}/-/{}-{}.tgz",
+                name, basename, version
+            ))
+        }
+        PackageRegistry::Pypi => None,
+    }
+}
+
+/// Picks the archive of a release from the PyPI JSON API: the source distribution, which has
+/// every file of the package, or the first wheel if there is none.
+pub fn pypi_archive_url(release: &Value) -> Option<String> {
+    let urls = release["urls"].as_array()?;
+    urls.iter()
+        .find(|url| url["packagetype"] == "sdist")
+        .or_else(|| urls.iter().find(|url| url["packagetype"] == "bdist_wheel"))
+        .and_then(|url| url["url"].as_str())
+        .map(str::to_string)
+}
+
+/// Reads a package archive from disk, or downloads it from a registry.
+///
+/// # Errors
+///
+/// Returns `CustomError::PackageError` if the package isn't a file nor `name@version`, no
+/// registry is given for it, or it can't be downloaded.
+pub fn load_package(
+    spec: &str,
+    registry: Option<PackageRegistry>,
+) -> Result<Vec<u8>, Box<dyn Error>> {
+    let error = |reason: String| CustomError::PackageError {
+        package: spec.to_string(),
+        reason,
+    };
+    if Path::new(spec).is_file() {
+        return Ok(fs::read(spec)?);
+    }
+    let (name, version) = parse_package(spec)
+        .ok_or_else(|| error("expected an archive or name@version".to_string()))?;
+    let registry = registry
+        .ok_or_else(|| error("pass --registry to download it from".to_string()))?;
+    let url = match archive_url(registry, name, version) {
+        Some(url) => url,
+        None => {
+            let release = http_util::fetch(&format!("https://pypi.org/pypi/{}/{}/json", name, version))?;
+            let release: Value = serde_json::from_slice(&release)?;
+            pypi_archive_url(&release)
+                .ok_or_else(|| error("the release has no source distribution or wheel".to_string()))?
+        }
+    };
+    http_util::fetch(&url)
+}
+
+/// Scans the files of a package archive, reported with the package as the repo and their paths
+/// in the archive as the file.
+///
+/// # Errors
+///
+/// Returns `CustomError::PackageError` if the archive can't be read.
+pub fn scan_archive(spec: &str, archive: Vec<u8>, scan: &Scan) -> Result<Vec<Leak>, Box<dyn Error>> {
+    let files = read_archive(archive).map_err(|err| CustomError::PackageError {
+        package: spec.to_string(),
+        reason: err.to_string(),
+    })?;
+    let mut leaks = Vec::new();
+    for (path, contents) in files {
+        for mut leak in detect_bytes(&contents, &path, &scan.ruleslist, &scan.allowlist, None) {
+            leak.repo = spec.to_string();
+            leaks.push(leak);
+        }
+    }
+    Ok(leaks)
+}
+
+/// Runs the `package` subcommand, printing the leaks and writing them to the report if one is
+/// given.
+///
+/// # Errors
+///
+/// Returns an error if the config can't be loaded, the package can't be read or downloaded, or
+/// the report can't be written.
+pub fn scan_package(args: &PackageArgs) -> Result<Vec<Leak>, Box<dyn Error>> {
+    let scan = load_config_file(&args.config)?;
+    let mut metadata = ReportMetadata {
+        ruleset_hash: ruleset_hash(&scan),
+        started_at: Local::now().to_rfc3339(),
+        ..ReportMetadata::new(&args.config, &args.package)
+    };
+    let start = Instant::now();
+    let archive = load_package(&args.package, args.registry)?;
+    let mut leaks = scan_archive(&args.package, archive, &scan)?;
+    sort_leaks(&mut leaks);
+
+    for leak in &leaks {
+        if !is_quiet() {
+            println!("{}:{} {}", leak.file, leak.line_number, leak.rule_id);
+        }
+    }
+    if let Some(report) = &args.report {
+        metadata.duration_ms = start.elapsed().as_millis() as u64;
+        write_report(report, args.report_format, &leaks, &metadata)?;
+    }
+    if !is_quiet() {
+        println!(
+            "\x1b[38;5;208m[WARN]\x1b[0m[{}]{} leaks detected in {}",
+            Local::now().format("%Y-%m-%d %H:%M:%S"),
+            leaks.len(),
+            args.package
+        );
+    }
+    Ok(leaks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::archive_util::tests::tar;
+    use crate::utils::detect_utils::load_config_from_str;
+    use serde_json::json;
+
+    #[test]
+    fn test_archive_url() {
+        assert_eq!(parse_package("serde@1.0.0"), Some(("serde", "1.0.0")));
+        assert_eq!(parse_package("@types/node@20.1.0"), Some(("@types/node", "20.1.0")));
+        assert_eq!(parse_package("serde"), None);
+        assert_eq!(parse_package("@types/node"), None);
+
+        assert_eq!(
+            archive_url(PackageRegistry::Crates, "serde", "1.0.0").unwrap(),
+            "https://static.crates.io/crates/serde/serde-1.0.0.crate"
+        );
+        assert_eq!(
+            archive_url(PackageRegistry::Npm, "@types/node", "20.1.0").unwrap(),
+            "https://registry.npmjs.org/@types/node/-/node-20.1.0.tgz"
+        );
+
+        let release = json!({ "urls": [
+            { "packagetype": "bdist_wheel", "url": "https://files/a.whl" },
+            { "packagetype": "sdist", "url": "https://files/a.tar.gz" },
+        ]});
+        assert_eq!(pypi_archive_url(&release).unwrap(), "https://files/a.tar.gz");
+        assert!(pypi_archive_url(&json!({ "urls": [] })).is_none());
+    }
+
+    #[test]
+    fn test_scan_package_archive() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pkg-1.0.0.tgz");
+        fs::write(
+            &path,
+            tar(&[
+                ("package/index.js", b"module.exports = {}\n"),
+                ("package/.env", b"STRIPE=sk_live_1234567890abcdef\n"),
+            ]),
+        )
+        .unwrap();
+        let spec = path.to_str().unwrap();
+
+        let scan = load_config_from_str(
+            r#"
+[[rules]]
+id = "stripe-access-token"
+description = "Stripe"
+regex = '''sk_(test|live)_[0-9a-z]{10,32}'''
+keywords = ["sk_live"]
+"#,
+        )
+        .unwrap();
+        let leaks = scan_archive(spec, load_package(spec, None).unwrap(), &scan).unwrap();
+        assert_eq!(leaks.len(), 1);
+        assert_eq!(leaks[0].file, "package/.env");
+        assert_eq!(leaks[0].repo, spec);
+
+        assert!(load_package("left-pad@1.3.0", None).is_err());
+        assert!(load_package("left-pad", Some(PackageRegistry::Npm)).is_err());
+    }
+}
@@ -0,0 +1,356 @@
+use crate::models::{strip_credentials, Config, Scan, ScanTarget};
+use crate::service::git_service::{load_working_tree_files, matching_branches, select_commits};
+use crate::utils::detect_utils::{
+    author_allowlist_entry, is_commit_in_allowlist, is_path_in_allowlist, load_config, ruleset_hash,
+};
+use crate::utils::generated_util::{is_generated, is_generated_path};
+use crate::utils::git_util::is_ignored_path;
+use git2::{Oid, Repository};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+/// What a scan would cover, resolved from its options without scanning, printed by `--dry-run`.
+///
+/// The options narrow the commits in this order: `--branch` picks the history, or HEAD, the date
+/// window of `--commit-since` and `--commit-until` keeps the commits made within it, and
+/// `--max-commits` keeps the most recent of those.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ScanPlan {
+    /// The repository, without the credentials of its URL.
+    pub repo: String,
+
+    /// The config file the rules are loaded from.
+    pub config: String,
+
+    /// The number of rules the scan runs.
+    pub rules: usize,
+
+    /// The SHA-256 digest of the rules and the allowlist, as in the metadata of reports.
+    pub ruleset_hash: String,
+
+    /// The number of paths and commits the allowlist skips.
+    pub allowlisted_paths: usize,
+    pub allowlisted_commits: usize,
+
+    /// Whether lockfiles, minified bundles and generated sources are scanned.
+    pub include_generated: bool,
+
+    /// What is scanned, see [`ScanTarget::describe`].
+    pub refs: String,
+
+    /// The base the commits of `--new-only` are new since.
+    pub base: Option<String>,
+
+    /// The local branches `--branch` matched, whose heads or history are scanned.
+    pub branches: Vec<String>,
+
+    /// The start of the date window, as given.
+    pub since: Option<String>,
+
+    /// The end of the date window, as given, or now.
+    pub until: Option<String>,
+
+    /// How many of the most recent commits are kept.
+    pub max_commits: Option<u64>,
+
+    /// The number of commits to scan.
+    pub commits: usize,
+
+    /// The first and last commits to scan, the most recent first for histories.
+    pub commit_range: Option<(String, String)>,
+
+    /// The estimated number of files the scan reads, a file being counted in every commit it is
+    /// read in, or `None` when it isn't estimated, for `--packfiles`.
+    pub files: Option<usize>,
+
+    /// The estimated number of bytes the scan reads.
+    pub bytes: Option<u64>,
+
+    /// Why the scan would find nothing to scan, if it would.
+    pub notes: Vec<String>,
+}
+
+/// Resolves the options of a scan of a repository into the config, the rules and the commits or
+/// files it would scan, and estimates how much it would read.
+///
+/// The files are estimated from the trees of the commits, leaving out the paths the scan skips by
+/// their name, so that generated files are only left out by their name. Trees are counted once per
+/// path however many commits share them.
+///
+/// # Errors
+///
+/// This function returns an error if the config can't be loaded, the branches, commits or files
+/// can't be listed, e.g. for a malformed date window, or a tree can't be read.
+pub fn plan_scan(repo: &Repository, config: &Config) -> Result<ScanPlan, Box<dyn Error>> {
+    let scan = load_config(repo, config)?;
+    let target = config.target();
+    let (branch, since, until) = match &target {
+        ScanTarget::Branch(branch) => (Some(branch.as_str()), None, None),
+        ScanTarget::Between { since, until } => (None, Some(since), Some(until)),
+        ScanTarget::History { branch, since, until } => (branch.as_deref(), since.as_ref(), until.as_ref()),
+        _ => (None, None, None),
+    };
+    let branches: Vec<String> = match branch {
+        Some(branch) => matching_branches(repo, Some(branch))?.into_iter().map(|(name, _)| name).collect(),
+        None => Vec::new(),
+    };
+    let commits = select_commits(repo, config)?;
+    let commit_range = match (commits.first(), commits.last()) {
+        (Some(first), Some(last)) => Some((first.to_string(), last.to_string())),
+        _ => None,
+    };
+    let capped = matches!(target, ScanTarget::All | ScanTarget::Between { .. } | ScanTarget::History { .. });
+
+    // The files of the working tree are read, the commits only walked
+    let mut unchanged = false;
+    let work = if target.is_working_tree() {
+        let files = load_working_tree_files(repo, &config.repo, &target, config.follow_symlinks)?;
+        unchanged = files.files.is_empty() && files.large_files.is_empty();
+        let mut work = (0, 0);
+        for (path, contents) in &files.files {
+            if config.include_generated || !is_generated(path, contents.as_bytes()) {
+                work = (work.0 + 1, work.1 + contents.len() as u64);
+            }
+        }
+        for path in files.large_files.iter().filter(|path| config.include_generated || !is_generated_path(path)) {
+            let size = std::fs::metadata(std::path::Path::new(&config.repo).join(path))?.len();
+            work = (work.0 + 1, work.1 + size);
+        }
+        Some(work)
+    } else if target == ScanTarget::Packfiles {
+        None
+    } else {
+        let mut estimate = WorkEstimate { repo, odb: repo.odb()?, scan: &scan, config, trees: HashMap::new() };
+        let mut work = (0, 0);
+        for oid in &commits {
+            let (files, bytes) = estimate.commit(*oid)?;
+            work = (work.0 + files, work.1 + bytes);
+        }
+        Some(work)
+    };
+
+    let mut notes = Vec::new();
+    if scan.ruleslist.is_empty() {
+        notes.push(format!("{} has no rules", config.config));
+    }
+    let scans_commits = !target.is_working_tree() && target != ScanTarget::Packfiles;
+    if let Some(branch) = branch.filter(|_| branches.is_empty()) {
+        notes.push(format!("no local branch name contains {}", branch));
+    } else if scans_commits && commits.is_empty() {
+        notes.push(match since {
+            Some(_) => "no commit was made within the date window".to_string(),
+            None => "no commit matches the options".to_string(),
+        });
+    } else if target == ScanTarget::Uncommitted && unchanged {
+        notes.push("git status reports no modified or untracked files".to_string());
+    } else if work.is_some_and(|(files, _)| files == 0) {
+        notes.push("every file is skipped by the allowlist or as hidden or generated".to_string());
+    }
+
+    Ok(ScanPlan {
+        repo: strip_credentials(&config.repo),
+        config: config.config.clone(),
+        rules: scan.ruleslist.len(),
+        ruleset_hash: ruleset_hash(&scan),
+        allowlisted_paths: scan.allowlist.paths.len(),
+        allowlisted_commits: scan.allowlist.commits.len(),
+        include_generated: config.include_generated,
+        refs: target.describe(),
+        base: None,
+        branches,
+        since: since.cloned(),
+        until: until.cloned(),
+        max_commits: config.max_depth.filter(|_| capped),
+        commits: commits.len(),
+        commit_range,
+        files: work.map(|(files, _)| files),
+        bytes: work.map(|(_, bytes)| bytes),
+        notes,
+    })
+}
+
+/// Counts the files and bytes the scans of commits read, with the counts of the trees they share.
+struct WorkEstimate<'a, 'repo> {
+    repo: &'repo Repository,
+    odb: git2::Odb<'repo>,
+    scan: &'a Scan,
+    config: &'a Config,
+    /// The files and bytes of the trees counted so far, by tree and path.
+    trees: HashMap<(Oid, String), (usize, u64)>,
+}
+
+impl WorkEstimate<'_, '_> {
+    /// Counts what the scan of a commit reads, nothing for the commits the allowlist skips.
+    fn commit(&mut self, oid: Oid) -> Result<(usize, u64), Box<dyn Error>> {
+        // Commits that can't be read are skipped by the scan too
+        let Ok(commit) = self.repo.find_commit(oid) else {
+            return Ok((0, 0));
+        };
+        let author = commit.author();
+        let allowlist = &self.scan.allowlist;
+        if is_commit_in_allowlist(&oid.to_string(), &allowlist.commits)
+            || author_allowlist_entry(author.name().unwrap_or(""), author.email().unwrap_or(""), None, allowlist)
+                .is_some()
+        {
+            return Ok((0, 0));
+        }
+        self.tree(commit.tree_id(), "")
+    }
+
+    fn tree(&mut self, oid: Oid, path: &str) -> Result<(usize, u64), Box<dyn Error>> {
+        let key = (oid, path.to_string());
+        if let Some(work) = self.trees.get(&key) {
+            return Ok(*work);
+        }
+        let mut work = (0, 0);
+        let tree = self.repo.find_tree(oid)?;
+        for entry in tree.iter() {
+            let entry_path = format!("{}/{}", path, entry.name().unwrap_or(""));
+            if is_path_in_allowlist(&entry_path, &self.scan.allowlist.paths) || is_ignored_path(&entry_path) {
+                continue;
+            }
+            match entry.kind() {
+                Some(git2::ObjectType::Tree) => {
+                    let (files, bytes) = self.tree(entry.id(), &entry_path)?;
+                    work = (work.0 + files, work.1 + bytes);
+                }
+                Some(git2::ObjectType::Blob)
+                    if entry.filemode() != i32::from(git2::FileMode::Link)
+                        && (self.config.include_generated || !is_generated_path(&entry_path)) =>
+                {
+                    let (size, _) = self.odb.read_header(entry.id())?;
+                    work = (work.0 + 1, work.1 + size as u64);
+                }
+                _ => {}
+            }
+        }
+        self.trees.insert(key, work);
+        Ok(work)
+    }
+}
+
+impl fmt::Display for ScanPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let short = |id: &str| id.get(..7).unwrap_or(id).to_string();
+        writeln!(f, "Scan plan of {}", self.repo)?;
+        writeln!(
+            f,
+            "  config:      {}, {} rules (ruleset {})",
+            self.config,
+            self.rules,
+            self.ruleset_hash.get(..12).unwrap_or(&self.ruleset_hash)
+        )?;
+        writeln!(
+            f,
+            "  filters:     {} allowlisted paths, {} allowlisted commits, generated files {}",
+            self.allowlisted_paths,
+            self.allowlisted_commits,
+            if self.include_generated { "scanned" } else { "skipped" }
+        )?;
+        writeln!(f, "  target:      {}", self.refs)?;
+        if let Some(base) = &self.base {
+            writeln!(f, "  new since:   {}", short(base))?;
+        }
+        if !self.branches.is_empty() {
+            writeln!(f, "  branches:    {}", self.branches.join(", "))?;
+        }
+        if let Some(since) = &self.since {
+            writeln!(f, "  date window: {} to {}", since, self.until.as_deref().unwrap_or("now"))?;
+        }
+        if let Some(max_commits) = self.max_commits {
+            writeln!(f, "  max commits: {}", max_commits)?;
+        }
+        match &self.commit_range {
+            Some((first, last)) if first != last => {
+                writeln!(f, "  commits:     {}, from {} to {}", self.commits, short(first), short(last))?
+            }
+            Some((first, _)) => writeln!(f, "  commits:     {}, {}", self.commits, short(first))?,
+            None => writeln!(f, "  commits:     {}", self.commits)?,
+        }
+        match (self.files, self.bytes) {
+            (Some(files), Some(bytes)) => {
+                writeln!(f, "  work:        {} files, {:.1} MiB", files, bytes as f64 / (1 << 20) as f64)?
+            }
+            _ => writeln!(f, "  work:        not estimated")?,
+        }
+        for note in &self.notes {
+            writeln!(f, "  note:        {}", note)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    /// Commits a file at noon UTC of a day of January 2024 on top of `parent`, updating `branch`.
+    fn commit(repo: &Repository, branch: &str, day: i64, parent: Option<git2::Oid>) -> git2::Oid {
+        let time = git2::Time::new(1_704_067_200 + (day - 1) * 86_400 + 43_200, 0);
+        let signature = git2::Signature::new("John Doe", "johndoe@example.com", &time).unwrap();
+        let mut index = repo.index().unwrap();
+        let path = format!("day{}.txt", day);
+        std::fs::write(repo.workdir().unwrap().join(&path), day.to_string()).unwrap();
+        index.add_path(std::path::Path::new(&path)).unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let parents: Vec<git2::Commit> = parent.iter().map(|oid| repo.find_commit(*oid).unwrap()).collect();
+        let parents: Vec<&git2::Commit> = parents.iter().collect();
+        repo.commit(Some(branch), &signature, &signature, &path, &tree, &parents).unwrap()
+    }
+
+    #[test]
+    fn test_plan_scan() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let first = commit(&repo, "HEAD", 1, None);
+        let second = commit(&repo, "HEAD", 2, Some(first));
+        let third = commit(&repo, "HEAD", 3, Some(second));
+        let feature = commit(&repo, "refs/heads/feature", 4, Some(second));
+
+        let plan = |args: &[&str]| {
+            let config = Config::try_parse_from([&["scan", "--repo", dir.path().to_str().unwrap()], args].concat())
+                .unwrap();
+            plan_scan(&repo, &config).unwrap()
+        };
+        let range = |first: git2::Oid, last: git2::Oid| Some((first.to_string(), last.to_string()));
+
+        // The branch picks the history, the window narrows it, and the cap keeps the most recent
+        let windowed = plan(&["--branch", "feature", "--commit-since", "2024-01-02"]);
+        assert_eq!(windowed.refs, "branch feature since 2024-01-02");
+        assert_eq!(windowed.branches, ["refs/heads/feature"]);
+        assert_eq!((windowed.commits, windowed.commit_range.clone()), (2, range(feature, second)));
+        let capped = plan(&["--branch", "feature", "--commit-since", "2024-01-02", "--max-commits", "1"]);
+        assert_eq!((capped.commits, capped.commit_range), (1, range(feature, feature)));
+        let history = plan(&["--branch", "feature", "--max-commits", "5"]);
+        assert_eq!((history.commits, history.commit_range), (3, range(feature, first)));
+        let head = plan(&["--commit-since", "2024-01-02", "--max-commits", "1"]);
+        assert_eq!((head.refs.as_str(), head.commits, head.commit_range), ("HEAD since 2024-01-02", 1, range(third, third)));
+
+        // Without a window or cap, only the heads of the branches are scanned
+        let heads = plan(&["--branch", "feature"]);
+        assert_eq!((heads.commits, heads.max_commits), (1, None));
+        assert_eq!(plan(&["--branch", "nothing", "--max-commits", "5"]).commits, 0);
+
+        // Every commit reads its whole tree: the four files of feature, then the two of second
+        assert!(windowed.rules > 0 && windowed.ruleset_hash.len() == 64);
+        assert_eq!((windowed.files, windowed.bytes, windowed.notes.len()), (Some(6), Some(6), 0));
+        assert_eq!(plan(&["--branch", "nothing", "--max-commits", "5"]).notes, ["no local branch name contains nothing"]);
+        assert_eq!(plan(&["--commit-since", "2025-01-01"]).notes, ["no commit was made within the date window"]);
+
+        // Only day4.txt, committed to feature, differs from HEAD in the index
+        let uncommitted = plan(&["--uncommitted"]);
+        assert_eq!((uncommitted.commits, uncommitted.files, uncommitted.refs.as_str()), (0, Some(1), "uncommitted"));
+        assert_eq!(plan(&["--file", "day1.txt", "--file", "day2.txt"]).files, Some(2));
+        assert_eq!(plan(&["--packfiles"]).files, None);
+
+        let printed = windowed.to_string();
+        assert!(printed.contains("  work:        6 files, 0.0 MiB\n"), "{}", printed);
+        assert!(printed.contains("  date window: 2024-01-02 to now\n"), "{}", printed);
+        assert!(printed.contains(&format!("  commits:     2, from {} to {}\n", &feature.to_string()[..7], &second.to_string()[..7])));
+        assert!(Config::try_parse_from(["scan", "--repo", ".", "--commit", "a", "--commit-since", "2024-01-02"]).is_err());
+        assert!(Config::try_parse_from(["scan", "--repo", ".", "--commits", "a", "--max-commits", "2"]).is_err());
+    }
+}
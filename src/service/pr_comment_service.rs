@@ -0,0 +1,611 @@
+use crate::errors::CustomError;
+use crate::models::{Leak, Provider};
+use crate::utils::http_util;
+use serde_json::{json, Value};
+use std::error::Error;
+use std::fs;
+
+/// Marks the comment sensleak posted, so that later scans of the pull request update it. A link
+/// reference definition is hidden by the Markdown of every platform, unlike HTML comments.
+const MARKER: &str = "[//]: # (sensleak-pr-comment)";
+
+/// How many comments are listed per request.
+const PAGE_SIZE: usize = 100;
+
+/// The version of the Azure DevOps REST API the requests are made for.
+const AZURE_API_VERSION: &str = "api-version=7.0";
+
+/// How many leaks the comment lists, the others are only counted.
+const MAX_ROWS: usize = 50;
+
+/// How many characters of a secret are left in the snippets of the comment.
+const SECRET_PREFIX: usize = 4;
+
+/// The pull request or merge request a CI job runs for, read from the variables of the CI.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PullRequest {
+    /// A pull request built by GitHub Actions, with the token in `GITHUB_TOKEN`.
+    GitHub {
+        api: String,
+        repo: String,
+        number: u64,
+        token: String,
+    },
+    /// A merge request pipeline of GitLab CI, with a token allowed to comment in `GITLAB_TOKEN`.
+    GitLab {
+        api: String,
+        project: String,
+        iid: u64,
+        token: String,
+    },
+    /// A pull request built by Azure Pipelines, with the job token in `SYSTEM_ACCESSTOKEN`.
+    Azure {
+        collection: String,
+        project: String,
+        repository: String,
+        id: u64,
+        token: String,
+    },
+    /// A pull request pipeline of Bitbucket Cloud, with an access token in `BITBUCKET_TOKEN`.
+    Bitbucket {
+        api: String,
+        workspace: String,
+        repo: String,
+        id: u64,
+        token: String,
+    },
+    /// A pull request of Bitbucket Server or Data Center, with an HTTP access token in
+    /// `BITBUCKET_TOKEN`.
+    BitbucketServer {
+        url: String,
+        project: String,
+        repo: String,
+        id: u64,
+        token: String,
+    },
+}
+
+/// A comment on a pull request.
+#[derive(Debug, Clone, PartialEq)]
+struct Comment {
+    /// The URL the comment is updated at.
+    url: String,
+    body: String,
+    /// The version Bitbucket Server needs to update the comment.
+    version: Option<u64>,
+}
+
+impl PullRequest {
+    /// Reads the pull request from the variables of the CI.
+    ///
+    /// # Arguments
+    ///
+    /// * `provider` - The platform given to `--provider`. Without one, it is found from the
+    ///   variables of GitHub Actions, GitLab CI, Azure Pipelines or Bitbucket Pipelines.
+    /// * `var` - Looks up an environment variable, `std::env::var` outside of tests.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CustomError::PullRequestCommentError` if the job doesn't run for a pull request,
+    /// or a variable or the token isn't set.
+    pub fn from_env(
+        provider: Option<Provider>,
+        var: impl Fn(&str) -> Option<String>,
+    ) -> Result<Self, CustomError> {
+        let error = |reason: String| CustomError::PullRequestCommentError { reason };
+        let required = |name: &str| var(name).ok_or_else(|| error(format!("{} isn't set", name)));
+        let number = |name: &str| {
+            var(name)
+                .and_then(|value| value.parse().ok())
+                .ok_or_else(|| error("the job doesn't run for a pull request".to_string()))
+        };
+        let provider = match provider {
+            Some(provider) => provider,
+            None if var("GITHUB_ACTIONS").as_deref() == Some("true") => Provider::Github,
+            None if var("GITLAB_CI").as_deref() == Some("true") => Provider::Gitlab,
+            None if var("TF_BUILD").is_some_and(|value| value.eq_ignore_ascii_case("true")) => {
+                Provider::Azure
+            }
+            None if var("BITBUCKET_BUILD_NUMBER").is_some() => Provider::Bitbucket,
+            None => {
+                return Err(error(
+                    "not running in a known CI, pass --provider".to_string(),
+                ))
+            }
+        };
+        Ok(match provider {
+            Provider::Github => PullRequest::GitHub {
+                api: var("GITHUB_API_URL").unwrap_or_else(|| "https://api.github.com".to_string()),
+                repo: required("GITHUB_REPOSITORY")?,
+                number: var("GITHUB_EVENT_PATH")
+                    .and_then(|path| fs::read_to_string(path).ok())
+                    .and_then(|event| serde_json::from_str::<Value>(&event).ok())
+                    .and_then(|event| event["pull_request"]["number"].as_u64())
+                    .or_else(|| {
+                        // refs/pull/<number>/merge
+                        var("GITHUB_REF")?
+                            .strip_prefix("refs/pull/")?
+                            .split('/')
+                            .next()?
+                            .parse()
+                            .ok()
+                    })
+                    .ok_or_else(|| error("the workflow doesn't run for a pull request".to_string()))?,
+                token: required("GITHUB_TOKEN")?,
+            },
+            Provider::Gitlab => PullRequest::GitLab {
+                api: required("CI_API_V4_URL")?,
+                project: required("CI_PROJECT_ID")?,
+                iid: number("CI_MERGE_REQUEST_IID")?,
+                token: required("GITLAB_TOKEN")?,
+            },
+            Provider::Azure => PullRequest::Azure {
+                collection: required("SYSTEM_COLLECTIONURI")?,
+                project: required("SYSTEM_TEAMPROJECT")?,
+                repository: required("BUILD_REPOSITORY_ID")?,
+                id: number("SYSTEM_PULLREQUEST_PULLREQUESTID")?,
+                token: required("SYSTEM_ACCESSTOKEN")?,
+            },
+            Provider::Bitbucket => PullRequest::Bitbucket {
+                api: var("BITBUCKET_API_URL")
+                    .unwrap_or_else(|| "https://api.bitbucket.org/2.0".to_string()),
+                workspace: required("BITBUCKET_WORKSPACE")?,
+                repo: required("BITBUCKET_REPO_SLUG")?,
+                id: number("BITBUCKET_PR_ID")?,
+                token: required("BITBUCKET_TOKEN")?,
+            },
+            Provider::BitbucketServer => PullRequest::BitbucketServer {
+                url: required("BITBUCKET_SERVER_URL")?,
+                project: required("BITBUCKET_PROJECT_KEY")?,
+                repo: required("BITBUCKET_REPO_SLUG")?,
+                id: number("BITBUCKET_PR_ID")?,
+                token: required("BITBUCKET_TOKEN")?,
+            },
+        })
+    }
+
+    /// Returns the URL of the comments of the pull request.
+    fn comments_url(&self) -> String {
+        match self {
+            PullRequest::GitHub { api, repo, number, .. } => {
+                format!("{}/repos/{}/issues/{}/comments", trim(api), repo, number)
+            }
+            PullRequest::GitLab { api, project, iid, .. } => format!(
+                "{}/projects/{}/merge_requests/{}/notes",
+                trim(api),
+                http_util::encode_query(project),
+                iid
+            ),
+            // Comments of Azure DevOps are in threads, a new comment opens one
+            PullRequest::Azure { collection, project, repository, id, .. } => format!(
+                "{}/{}/_apis/git/repositories/{}/pullRequests/{}/threads",
+                trim(collection),
+                http_util::encode_query(project),
+                repository,
+                id
+            ),
+            PullRequest::Bitbucket { api, workspace, repo, id, .. } => format!(
+                "{}/repositories/{}/{}/pullrequests/{}/comments",
+                trim(api),
+                workspace,
+                repo,
+                id
+            ),
+            PullRequest::BitbucketServer { url, project, repo, id, .. } => format!(
+                "{}/rest/api/1.0/projects/{}/repos/{}/pull-requests/{}/comments",
+                trim(url),
+                project,
+                repo,
+                id
+            ),
+        }
+    }
+
+    fn headers(&self) -> Vec<(&'static str, String)> {
+        match self {
+            PullRequest::GitHub { token, .. } => vec![
+                ("Authorization", format!("Bearer {}", token)),
+                ("Accept", "application/vnd.github+json".to_string()),
+            ],
+            PullRequest::GitLab { token, .. } => vec![("PRIVATE-TOKEN", token.clone())],
+            PullRequest::Azure { token, .. }
+            | PullRequest::Bitbucket { token, .. }
+            | PullRequest::BitbucketServer { token, .. } => vec![
+                ("Authorization", format!("Bearer {}", token)),
+                ("Accept", "application/json".to_string()),
+            ],
+        }
+    }
+
+    fn send(&self, method: &str, url: &str, body: Option<&Value>) -> Result<Value, String> {
+        let headers = self.headers();
+        let headers: Vec<(&str, &str)> =
+            headers.iter().map(|(name, value)| (*name, value.as_str())).collect();
+        http_util::send_json(method, url, &headers, body)
+    }
+
+    /// Returns the comments of the pull request, following the pages of the API.
+    fn list_comments(&self) -> Result<Vec<Comment>, String> {
+        let comments_url = self.comments_url();
+        let mut comments = Vec::new();
+        match self {
+            PullRequest::GitHub { api, repo, .. } => {
+                for page in 1.. {
+                    let url = format!("{}?per_page={}&page={}", comments_url, PAGE_SIZE, page);
+                    let listed = array(self.send("GET", &url, None)?);
+                    comments.extend(listed.iter().map(|comment| Comment {
+                        // Comments are updated under the repository, not the pull request
+                        url: format!("{}/repos/{}/issues/comments/{}", trim(api), repo, comment["id"]),
+                        body: text(&comment["body"]),
+                        version: None,
+                    }));
+                    if listed.len() < PAGE_SIZE {
+                        break;
+                    }
+                }
+            }
+            PullRequest::GitLab { .. } => {
+                for page in 1.. {
+                    let url = format!("{}?per_page={}&page={}", comments_url, PAGE_SIZE, page);
+                    let listed = array(self.send("GET", &url, None)?);
+                    comments.extend(listed.iter().map(|note| Comment {
+                        url: format!("{}/{}", comments_url, note["id"]),
+                        body: text(&note["body"]),
+                        version: None,
+                    }));
+                    if listed.len() < PAGE_SIZE {
+                        break;
+                    }
+                }
+            }
+            PullRequest::Azure { .. } => {
+                let url = format!("{}?{}", comments_url, AZURE_API_VERSION);
+                for thread in array(self.send("GET", &url, None)?["value"].take()) {
+                    // The first comment of a thread is the one that opened it
+                    let comment = &thread["comments"][0];
+                    comments.push(Comment {
+                        url: format!(
+                            "{}/{}/comments/{}?{}",
+                            comments_url, thread["id"], comment["id"], AZURE_API_VERSION
+                        ),
+                        body: text(&comment["content"]),
+                        version: None,
+                    });
+                }
+            }
+            PullRequest::Bitbucket { .. } => {
+                let mut url = format!("{}?pagelen={}", comments_url, PAGE_SIZE);
+                loop {
+                    let mut page = self.send("GET", &url, None)?;
+                    comments.extend(array(page["values"].take()).iter().map(|comment| Comment {
+                        url: format!("{}/{}", comments_url, comment["id"]),
+                        body: text(&comment["content"]["raw"]),
+                        version: None,
+                    }));
+                    match page["next"].as_str() {
+                        Some(next) => url = next.to_string(),
+                        None => break,
+                    }
+                }
+            }
+            PullRequest::BitbucketServer { .. } => {
+                // Comments are listed among the other activities of the pull request
+                let activities = comments_url.replace("/comments", "/activities");
+                let mut start = 0;
+                loop {
+                    let url = format!("{}?limit={}&start={}", activities, PAGE_SIZE, start);
+                    let mut page = self.send("GET", &url, None)?;
+                    for activity in array(page["values"].take()) {
+                        let comment = &activity["comment"];
+                        if activity["action"] == "COMMENTED" && comment.is_object() {
+                            comments.push(Comment {
+                                url: format!("{}/{}", comments_url, comment["id"]),
+                                body: text(&comment["text"]),
+                                version: comment["version"].as_u64(),
+                            });
+                        }
+                    }
+                    match page["nextPageStart"].as_u64() {
+                        Some(next) if page["isLastPage"] != true => start = next,
+                        _ => break,
+                    }
+                }
+            }
+        }
+        Ok(comments)
+    }
+
+    fn create_comment(&self, body: &str) -> Result<(), String> {
+        let url = self.comments_url();
+        let (url, request) = match self {
+            PullRequest::GitHub { .. } | PullRequest::GitLab { .. } => (url, json!({ "body": body })),
+            PullRequest::Azure { .. } => (
+                format!("{}?{}", url, AZURE_API_VERSION),
+                // Status 1 is active, comment type 1 is text
+                json!({ "comments": [{ "parentCommentId": 0, "content": body, "commentType": 1 }], "status": 1 }),
+            ),
+            PullRequest::Bitbucket { .. } => (url, json!({ "content": { "raw": body } })),
+            PullRequest::BitbucketServer { .. } => (url, json!({ "text": body })),
+        };
+        self.send("POST", &url, Some(&request))?;
+        Ok(())
+    }
+
+    fn update_comment(&self, comment: &Comment, body: &str) -> Result<(), String> {
+        let (method, request) = match self {
+            PullRequest::GitHub { .. } => ("PATCH", json!({ "body": body })),
+            PullRequest::GitLab { .. } => ("PUT", json!({ "body": body })),
+            PullRequest::Azure { .. } => ("PATCH", json!({ "content": body })),
+            PullRequest::Bitbucket { .. } => ("PUT", json!({ "content": { "raw": body } })),
+            PullRequest::BitbucketServer { .. } => {
+                ("PUT", json!({ "text": body, "version": comment.version.unwrap_or_default() }))
+            }
+        };
+        self.send(method, &comment.url, Some(&request))?;
+        Ok(())
+    }
+}
+
+fn trim(url: &str) -> &str {
+    url.trim_end_matches('/')
+}
+
+fn array(value: Value) -> Vec<Value> {
+    match value {
+        Value::Array(values) => values,
+        _ => Vec::new(),
+    }
+}
+
+fn text(value: &Value) -> String {
+    value.as_str().unwrap_or_default().to_string()
+}
+
+/// What [`post_comment`] did.
+#[derive(Debug, PartialEq, Eq)]
+pub enum CommentAction {
+    Created,
+    Updated,
+    /// No leaks were found and there was no comment to update.
+    Skipped,
+}
+
+/// Posts the summary of the leaks found in a pull request as a comment, or updates the comment
+/// sensleak posted before, so that the pull request has a single one however often it is scanned.
+/// Without leaks, a comment is only posted to update an earlier one.
+///
+/// # Errors
+///
+/// Returns `CustomError::PullRequestCommentError` if the API rejects a request.
+pub fn post_comment(
+    pull_request: &PullRequest,
+    leaks: &[Leak],
+) -> Result<CommentAction, Box<dyn Error>> {
+    let error = |reason: String| CustomError::PullRequestCommentError { reason };
+    let comments = pull_request.list_comments().map_err(error)?;
+    let existing = comments.iter().find(|comment| comment.body.contains(MARKER));
+    let body = comment_body(leaks);
+    match existing {
+        Some(comment) => {
+            pull_request.update_comment(comment, &body).map_err(error)?;
+            Ok(CommentAction::Updated)
+        }
+        None if leaks.is_empty() => Ok(CommentAction::Skipped),
+        None => {
+            pull_request.create_comment(&body).map_err(error)?;
+            Ok(CommentAction::Created)
+        }
+    }
+}
+
+/// Renders the comment, a table of the leaks with their secrets redacted.
+pub fn comment_body(leaks: &[Leak]) -> String {
+    let mut body = format!("{}\n\n### sensleak\n\n", MARKER);
+    if leaks.is_empty() {
+        body.push_str("No leaks found in this pull request.\n");
+        return body;
+    }
+    body.push_str(&format!(
+        "Found {} leaks in this pull request. Rotate the secrets before merging, removing them from the branch doesn't remove them from its history.\n\n",
+        leaks.len()
+    ));
+    body.push_str("| Rule | File | Line | Commit | Snippet |\n|---|---|---|---|---|\n");
+    for leak in leaks.iter().take(MAX_ROWS) {
+        body.push_str(&format!(
+            "| {} | {} | {} | {} | `{}` |\n",
+            escape_cell(&leak.rule_id),
+            escape_cell(leak.file.trim_start_matches('/')),
+            leak.line_number,
+            leak.commit.get(..8).unwrap_or(&leak.commit),
+            escape_cell(&redacted_snippet(leak)).replace('`', "'")
+        ));
+    }
+    if leaks.len() > MAX_ROWS {
+        body.push_str(&format!("\nand {} more.\n", leaks.len() - MAX_ROWS));
+    }
+    body
+}
+
+/// Returns the line of a leak with all but the first few characters of the secret masked.
+pub fn redacted_snippet(leak: &Leak) -> String {
+    let prefix: String = leak.offender.chars().take(SECRET_PREFIX).collect();
+    let masked = format!("{}{}", prefix, "*".repeat(8));
+    let line = if leak.offender.is_empty() {
+        leak.line.clone()
+    } else {
+        leak.line.replace(&leak.offender, &masked)
+    };
+    let line = line.trim();
+    if line.chars().count() > 120 {
+        format!("{}...", line.chars().take(120).collect::<String>())
+    } else {
+        line.to_string()
+    }
+}
+
+fn escape_cell(text: &str) -> String {
+    text.replace('|', "\\|").replace('\n', " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mockito::Matcher;
+    use std::collections::HashMap;
+
+    fn leak() -> Leak {
+        Leak {
+            line: "stripe = sk_test_1234567890abcdef".to_string(),
+            offender: "sk_test_1234567890abcdef".to_string(),
+            commit: "0123456789abcdef".to_string(),
+            file: "/config/a|b.env".to_string(),
+            rule_id: "stripe-access-token".to_string(),
+            line_number: 3,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_pull_request_from_env() {
+        let dir = tempfile::tempdir().unwrap();
+        let event = dir.path().join("event.json");
+        fs::write(&event, r#"{"pull_request": {"number": 42}}"#).unwrap();
+        let vars = HashMap::from([
+            ("GITHUB_ACTIONS", "true".to_string()),
+            ("GITHUB_REPOSITORY", "org/app".to_string()),
+            ("GITHUB_EVENT_PATH", event.to_string_lossy().into_owned()),
+            ("GITHUB_TOKEN", "token".to_string()),
+        ]);
+        assert_eq!(
+            PullRequest::from_env(None, |name| vars.get(name).cloned()).unwrap(),
+            PullRequest::GitHub {
+                api: "https://api.github.com".to_string(),
+                repo: "org/app".to_string(),
+                number: 42,
+                token: "token".to_string(),
+            }
+        );
+
+        let vars = HashMap::from([
+            ("GITLAB_CI", "true".to_string()),
+            ("CI_API_V4_URL", "https://gitlab.example.com/api/v4".to_string()),
+            ("CI_PROJECT_ID", "7".to_string()),
+            ("CI_MERGE_REQUEST_IID", "5".to_string()),
+            ("GITLAB_TOKEN", "token".to_string()),
+        ]);
+        let pull_request = PullRequest::from_env(None, |name| vars.get(name).cloned()).unwrap();
+        assert!(matches!(pull_request, PullRequest::GitLab { iid: 5, .. }));
+
+        let vars = HashMap::from([
+            ("TF_BUILD", "True".to_string()),
+            ("SYSTEM_COLLECTIONURI", "https://dev.azure.com/org/".to_string()),
+            ("SYSTEM_TEAMPROJECT", "Payments API".to_string()),
+            ("BUILD_REPOSITORY_ID", "repo-id".to_string()),
+            ("SYSTEM_PULLREQUEST_PULLREQUESTID", "9".to_string()),
+            ("SYSTEM_ACCESSTOKEN", "token".to_string()),
+        ]);
+        let pull_request = PullRequest::from_env(None, |name| vars.get(name).cloned()).unwrap();
+        assert_eq!(
+            pull_request.comments_url(),
+            "https://dev.azure.com/org/Payments%20API/_apis/git/repositories/repo-id/pullRequests/9/threads"
+        );
+
+        // Bitbucket Server has no CI to find it from
+        let vars = HashMap::from([
+            ("BITBUCKET_SERVER_URL", "https://bitbucket.example.com".to_string()),
+            ("BITBUCKET_PROJECT_KEY", "SEC".to_string()),
+            ("BITBUCKET_REPO_SLUG", "app".to_string()),
+            ("BITBUCKET_PR_ID", "3".to_string()),
+            ("BITBUCKET_TOKEN", "token".to_string()),
+        ]);
+        assert!(PullRequest::from_env(None, |name| vars.get(name).cloned()).is_err());
+        let pull_request =
+            PullRequest::from_env(Some(Provider::BitbucketServer), |name| vars.get(name).cloned())
+                .unwrap();
+        assert!(matches!(pull_request, PullRequest::BitbucketServer { id: 3, .. }));
+
+        assert!(PullRequest::from_env(None, |_| None).is_err());
+        let vars = HashMap::from([("GITLAB_CI", "true".to_string())]);
+        assert!(PullRequest::from_env(None, |name| vars.get(name).cloned()).is_err());
+    }
+
+    #[test]
+    fn test_comment_body() {
+        let body = comment_body(&[leak()]);
+        assert!(body.starts_with(MARKER));
+        assert!(body.contains("| stripe-access-token | config/a\\|b.env | 3 | 01234567 | `stripe = sk_t********` |"));
+        assert!(!body.contains("sk_test_1234567890abcdef"));
+        assert!(comment_body(&[]).contains("No leaks found"));
+    }
+
+    #[test]
+    fn test_post_comment() {
+        let mut server = mockito::Server::new();
+        let pull_request = PullRequest::GitHub {
+            api: server.url(),
+            repo: "org/app".to_string(),
+            number: 42,
+            token: "token".to_string(),
+        };
+        let listed = server
+            .mock("GET", "/repos/org/app/issues/42/comments")
+            .match_query(Matcher::Any)
+            .with_body(r#"[{"id": 1, "body": "LGTM"}]"#)
+            .create();
+        let created = server
+            .mock("POST", "/repos/org/app/issues/42/comments")
+            .match_header("authorization", "Bearer token")
+            .match_body(Matcher::Regex("sensleak-pr-comment".to_string()))
+            .with_status(201)
+            .with_body(r#"{"id": 2}"#)
+            .create();
+        assert_eq!(post_comment(&pull_request, &[leak()]).unwrap(), CommentAction::Created);
+        assert_eq!(post_comment(&pull_request, &[]).unwrap(), CommentAction::Skipped);
+        created.assert();
+        listed.remove();
+
+        server
+            .mock("GET", "/repos/org/app/issues/42/comments")
+            .match_query(Matcher::Any)
+            .with_body(format!(r#"[{{"id": 2, "body": "{}"}}]"#, MARKER))
+            .create();
+        let updated = server
+            .mock("PATCH", "/repos/org/app/issues/comments/2")
+            .match_body(Matcher::Regex("No leaks found".to_string()))
+            .with_body("{}")
+            .create();
+        assert_eq!(post_comment(&pull_request, &[]).unwrap(), CommentAction::Updated);
+        updated.assert();
+    }
+
+    #[test]
+    fn test_post_bitbucket_server_comment() {
+        let mut server = mockito::Server::new();
+        let pull_request = PullRequest::BitbucketServer {
+            url: server.url(),
+            project: "SEC".to_string(),
+            repo: "app".to_string(),
+            id: 3,
+            token: "token".to_string(),
+        };
+        let activities = json!({
+            "values": [
+                { "action": "APPROVED" },
+                { "action": "COMMENTED", "comment": { "id": 11, "version": 2, "text": MARKER } },
+            ],
+            "isLastPage": true,
+        });
+        server
+            .mock("GET", "/rest/api/1.0/projects/SEC/repos/app/pull-requests/3/activities")
+            .match_query(Matcher::Any)
+            .with_body(activities.to_string())
+            .create();
+        let updated = server
+            .mock("PUT", "/rest/api/1.0/projects/SEC/repos/app/pull-requests/3/comments/11")
+            .match_body(Matcher::PartialJson(json!({ "version": 2 })))
+            .with_body("{}")
+            .create();
+        assert_eq!(post_comment(&pull_request, &[leak()]).unwrap(), CommentAction::Updated);
+        updated.assert();
+    }
+}
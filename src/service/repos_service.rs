@@ -0,0 +1,560 @@
+use crate::errors::CustomError;
+use crate::models::{
+    Config, DiskCleanup, FailOn, Leak, ManifestRepo, Policy, ReportMetadata, ReposArgs, Results, ScanManifest,
+    STDOUT_REPORT,
+};
+use crate::service::detect_service::{detect, failing_leaks, write_manifest, write_report};
+use crate::service::gist_service::SnippetApi;
+use crate::utils::detect_utils::sort_leaks;
+use crate::utils::http_util::sha256_hex;
+use crate::utils::log_util::is_quiet;
+use crate::utils::signal_util::is_cancelled;
+use chrono::Local;
+use serde_json::{json, Value};
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Instant;
+
+/// Where the workers of the `repos` subcommand clone the repositories they scan, in `--tmp-dir`
+/// or `workplace`.
+const CLONE_DIR: &str = "repos";
+
+/// A repository the `repos` subcommand scans.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Repo {
+    /// The name policies match, e.g. `acme/payments-api`.
+    pub name: String,
+
+    /// The URL the repository is cloned from, or its path.
+    pub url: String,
+}
+
+/// How a repository is scanned, once the policy is applied to it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RepoSettings {
+    /// The config the repository is scanned with.
+    pub config: String,
+
+    /// The conditions its leaks fail the scan on.
+    pub fail_on: Vec<FailOn>,
+}
+
+/// The scan of a repository.
+#[derive(Debug, Clone)]
+pub struct RepoOutcome {
+    pub repo: Repo,
+
+    /// The leaks found, with the name of the repository as their repo.
+    pub leaks: Vec<Leak>,
+
+    /// How many of the leaks meet the `--fail-on` conditions of the repository.
+    pub failing: usize,
+
+    /// Why the repository couldn't be scanned.
+    pub error: Option<String>,
+
+    /// What was scanned in the repository, see `--manifest`.
+    pub manifest: ManifestRepo,
+}
+
+impl RepoOutcome {
+    /// Returns whether the repository was scanned and none of its leaks fail it.
+    pub fn passed(&self) -> bool {
+        self.error.is_none() && self.failing == 0
+    }
+}
+
+/// Reads a policy file.
+///
+/// # Errors
+///
+/// Returns `CustomError::PolicyError` if the file can't be read or isn't a valid policy.
+pub fn load_policy(path: &str) -> Result<Policy, CustomError> {
+    let error = |reason: String| CustomError::PolicyError { path: path.to_string(), reason };
+    let toml_str = fs::read_to_string(path).map_err(|e| error(e.to_string()))?;
+    toml::from_str(&toml_str).map_err(|e| error(e.message().to_string()))
+}
+
+/// Returns whether a policy scans a repository: it is included, or the policy includes every
+/// repository, and it isn't excluded.
+pub fn is_selected(policy: &Policy, name: &str) -> bool {
+    let included = policy.include.is_empty() || policy.include.iter().any(|glob| glob_match(glob, name));
+    included && !policy.exclude.iter().any(|glob| glob_match(glob, name))
+}
+
+/// Returns how a policy scans a repository.
+///
+/// # Arguments
+///
+/// * `policy` - The policy.
+/// * `name` - The name of the repository.
+/// * `config` - The config of `--config`, for repositories the policy gives none.
+/// * `policy_dir` - The directory of the policy file, which the relative paths of its configs are
+///   relative to.
+pub fn repo_settings(policy: &Policy, name: &str, config: &str, policy_dir: Option<&Path>) -> RepoSettings {
+    let mut settings = RepoSettings {
+        config: config.to_string(),
+        fail_on: policy.fail_on.clone(),
+    };
+    for repo in policy.repos.iter().filter(|repo| glob_match(&repo.pattern, name)) {
+        if let Some(config) = &repo.config {
+            settings.config = match policy_dir {
+                Some(dir) if Path::new(config).is_relative() => dir.join(config).to_string_lossy().into_owned(),
+                _ => config.clone(),
+            };
+        }
+        if let Some(fail_on) = &repo.fail_on {
+            settings.fail_on = fail_on.clone();
+        }
+    }
+    settings
+}
+
+/// Returns whether a repository name matches a glob of a policy, ignoring case: `*` matches
+/// anything but `/`, `**` anything, `**/` any number of parts, and `?` one character but `/`.
+fn glob_match(glob: &str, name: &str) -> bool {
+    fn matches(glob: &[char], name: &[char]) -> bool {
+        match glob {
+            [] => name.is_empty(),
+            ['*', '*', '/', rest @ ..] => {
+                matches(rest, name)
+                    || (1..=name.len()).any(|i| name[i - 1] == '/' && matches(rest, &name[i..]))
+            }
+            ['*', '*', rest @ ..] => (0..=name.len()).any(|i| matches(rest, &name[i..])),
+            ['*', rest @ ..] => (0..=name.len())
+                .take_while(|&i| i == 0 || name[i - 1] != '/')
+                .any(|i| matches(rest, &name[i..])),
+            ['?', rest @ ..] => matches!(name.first(), Some(c) if *c != '/') && matches(rest, &name[1..]),
+            [c, rest @ ..] => name.first() == Some(c) && matches(rest, &name[1..]),
+        }
+    }
+    let glob: Vec<char> = glob.to_lowercase().chars().collect();
+    let name: Vec<char> = name.to_lowercase().chars().collect();
+    matches(&glob, &name)
+}
+
+/// Returns the name of a repository policies match, from its URL or path: the path on its host,
+/// e.g. `acme/payments-api` for `https://github.com/acme/payments-api.git` or
+/// `git@github.com:acme/payments-api.git`, and local paths as they are.
+pub fn repo_name(url: &str) -> String {
+    let url = url.trim_end_matches('/');
+    let url = url.strip_suffix(".git").unwrap_or(url);
+    let path = match url.split_once("://") {
+        Some((_, rest)) => rest.split_once('/').map_or(rest, |(_, path)| path),
+        // git@host:owner/repo, but not C:\repo or ./a:b
+        None => match url.split_once(':') {
+            Some((host, path)) if host.contains('@') && !host.contains('/') => path,
+            _ => url,
+        },
+    };
+    path.to_string()
+}
+
+/// Reads a file listing repositories, one URL or path per line, skipping empty lines and comments
+/// starting with `#`.
+///
+/// # Errors
+///
+/// This function returns an error if the file can't be read.
+pub fn read_repos_file(path: &str) -> Result<Vec<Repo>, Box<dyn Error>> {
+    Ok(fs::read_to_string(path)?
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|url| Repo {
+            name: repo_name(url),
+            url: url.to_string(),
+        })
+        .collect())
+}
+
+/// Where the progress events of `--progress` are written.
+type Progress = Mutex<Box<dyn Write + Send>>;
+
+/// Writes a progress event as a JSON line, with the time it happened. The progress is only
+/// informative, so failing to write it doesn't stop the scan.
+fn emit(progress: Option<&Progress>, mut event: Value) {
+    if let Some(progress) = progress {
+        event["time"] = Local::now().to_rfc3339().into();
+        let mut progress = progress.lock().unwrap();
+        let _ = writeln!(progress, "{}", event).and_then(|_| progress.flush());
+    }
+}
+
+/// Scans a repository on a worker, in a clone of its own that is deleted once it is scanned, or
+/// kept for the next run with `--max-disk-gb`. Errors and panics of the scan are the outcome of
+/// the repository, so that they don't stop the scans of the others.
+fn scan_repo(
+    index: usize,
+    repo: Repo,
+    settings: RepoSettings,
+    clone_dir: &Path,
+    max_disk_gb: Option<f64>,
+) -> RepoOutcome {
+    let slug: String = repo
+        .name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '-' })
+        .collect();
+    // A kept clone is named after its URL, so that the next run finds it
+    let (name, disk_cleanup) = match max_disk_gb {
+        Some(_) => (format!("{}-{}", slug, &sha256_hex(repo.url.as_bytes())[..8]), DiskCleanup::Never),
+        None => (format!("{}-{}", index, slug), DiskCleanup::Always),
+    };
+    let config = Config {
+        repo: repo.url.clone(),
+        config: settings.config,
+        disk: Some(clone_dir.join(name).to_string_lossy().into_owned()),
+        disk_cleanup,
+        max_disk_gb,
+        ..Default::default()
+    };
+    let refs = config.target().describe();
+    let started_at = Local::now().to_rfc3339();
+    let failed = |error: String| ManifestRepo {
+        repo: repo.url.clone(),
+        refs: refs.clone(),
+        started_at: started_at.clone(),
+        error: Some(error),
+        ..Default::default()
+    };
+    let scanned = panic::catch_unwind(AssertUnwindSafe(|| -> Result<Results, String> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|err| err.to_string())?;
+        runtime.block_on(detect(config)).map_err(|err| err.to_string())
+    }));
+    match scanned {
+        Ok(Ok(results)) => {
+            let manifest = ManifestRepo::new(&repo.url, &refs, &started_at, &results);
+            let mut leaks = results.outputs;
+            for leak in &mut leaks {
+                leak.repo = repo.name.clone();
+            }
+            let failing = failing_leaks(&leaks, &settings.fail_on);
+            RepoOutcome { repo, leaks, failing, error: None, manifest }
+        }
+        Ok(Err(err)) => RepoOutcome { manifest: failed(err.clone()), repo, leaks: Vec::new(), failing: 0, error: Some(err) },
+        Err(_) => RepoOutcome {
+            manifest: failed("the scan panicked".to_string()),
+            repo,
+            leaks: Vec::new(),
+            failing: 0,
+            error: Some("the scan panicked".to_string()),
+        },
+    }
+}
+
+/// Runs the `repos` subcommand: scans every repository the policy selects, with its config, on
+/// `--workers` workers, printing how many of its leaks meet its `--fail-on` conditions, and
+/// writes the leaks of all of them to the report if one is given, and what was scanned in each to
+/// the manifest. A repository that can't be scanned is reported and doesn't stop the others.
+///
+/// # Arguments
+///
+/// * `args` - The arguments of the subcommand.
+/// * `global` - The global options, `--tmp-dir` and `--max-disk-gb`.
+///
+/// # Returns
+///
+/// The outcome of the scan of each repository.
+///
+/// # Errors
+///
+/// Returns an error if the policy or the repositories can't be read, or the report can't be
+/// written.
+pub fn scan_repos(args: &ReposArgs, global: &Config) -> Result<Vec<RepoOutcome>, Box<dyn Error>> {
+    let policy = match &args.policy {
+        Some(path) => load_policy(path)?,
+        None => Policy::default(),
+    };
+    let policy_dir = args.policy.as_deref().and_then(|path| Path::new(path).parent());
+    let repos = match (&args.org, &args.repos_file) {
+        (Some(org), _) => SnippetApi::new(args.provider, args.api_url.as_deref())
+            .repositories(org)
+            .map_err(|reason| CustomError::RepoListError { reason })?
+            .into_iter()
+            .map(|(name, url)| Repo { name, url })
+            .collect(),
+        (None, Some(file)) => read_repos_file(file)?,
+        (None, None) => unreachable!("clap requires --org or --repos-file"),
+    };
+    let source = args.org.as_ref().or(args.repos_file.as_ref()).cloned().unwrap_or_default();
+    let mut metadata = ReportMetadata {
+        started_at: Local::now().to_rfc3339(),
+        ..ReportMetadata::new(&args.config, &source)
+    };
+    let start = Instant::now();
+    let progress: Option<Progress> = match args.progress.as_deref() {
+        Some(STDOUT_REPORT) => Some(Mutex::new(Box::new(io::stdout()))),
+        Some(path) => Some(Mutex::new(Box::new(File::create(path)?))),
+        None => None,
+    };
+    let clone_dir = PathBuf::from(global.tmp_dir.as_deref().unwrap_or("workplace")).join(CLONE_DIR);
+    fs::create_dir_all(&clone_dir)?;
+
+    let jobs: Vec<(Repo, RepoSettings)> = repos
+        .into_iter()
+        .filter(|repo| is_selected(&policy, &repo.name))
+        .map(|repo| {
+            let settings = repo_settings(&policy, &repo.name, &args.config, policy_dir);
+            (repo, settings)
+        })
+        .collect();
+    let next = AtomicUsize::new(0);
+    let outcomes: Mutex<Vec<Option<RepoOutcome>>> = Mutex::new(vec![None; jobs.len()]);
+    thread::scope(|scope| {
+        for _ in 0..args.workers.min(jobs.len()) {
+            scope.spawn(|| {
+                let mut index = next.fetch_add(1, Ordering::SeqCst);
+                while let Some((repo, settings)) = jobs.get(index).filter(|_| !is_cancelled()) {
+                    let started = Instant::now();
+                    emit(progress.as_ref(), json!({ "event": "started", "repo": repo.name }));
+                    let outcome = scan_repo(index, repo.clone(), settings.clone(), &clone_dir, global.max_disk_gb);
+                    let duration_ms = started.elapsed().as_millis() as u64;
+                    match &outcome.error {
+                        Some(error) => {
+                            if !is_quiet() {
+                                println!(
+                                    "\x1b[31m[ERROR]\x1b[0m[{}] {}: {}",
+                                    Local::now().format("%Y-%m-%d %H:%M:%S"),
+                                    repo.name,
+                                    error
+                                );
+                            }
+                            emit(
+                                progress.as_ref(),
+                                json!({ "event": "failed", "repo": repo.name, "error": error, "duration_ms": duration_ms }),
+                            );
+                        }
+                        None => {
+                            if !is_quiet() {
+                                println!(
+                                    "\x1b[34m[INFO]\x1b[0m[{}] {}: {} leaks, {} match --fail-on",
+                                    Local::now().format("%Y-%m-%d %H:%M:%S"),
+                                    repo.name,
+                                    outcome.leaks.len(),
+                                    outcome.failing
+                                );
+                            }
+                            emit(
+                                progress.as_ref(),
+                                json!({
+                                    "event": "finished",
+                                    "repo": repo.name,
+                                    "leaks": outcome.leaks.len(),
+                                    "failing": outcome.failing,
+                                    "passed": outcome.passed(),
+                                    "duration_ms": duration_ms,
+                                }),
+                            );
+                        }
+                    }
+                    outcomes.lock().unwrap()[index] = Some(outcome);
+                    index = next.fetch_add(1, Ordering::SeqCst);
+                }
+            });
+        }
+    });
+    let outcomes: Vec<RepoOutcome> = outcomes.into_inner().unwrap().into_iter().flatten().collect();
+    emit(
+        progress.as_ref(),
+        json!({
+            "event": "done",
+            "repos": outcomes.len(),
+            "failed": outcomes.iter().filter(|outcome| !outcome.passed()).count(),
+            "leaks": outcomes.iter().map(|outcome| outcome.leaks.len()).sum::<usize>(),
+            "duration_ms": start.elapsed().as_millis() as u64,
+        }),
+    );
+
+    if let Some(report) = &args.report {
+        let mut leaks: Vec<Leak> = outcomes.iter().flat_map(|outcome| outcome.leaks.clone()).collect();
+        sort_leaks(&mut leaks);
+        metadata.duration_ms = start.elapsed().as_millis() as u64;
+        metadata.partial = is_cancelled();
+        write_report(report, args.report_format, &leaks, &metadata)?;
+    }
+    if let Some(path) = &args.manifest {
+        let repos = outcomes.iter().map(|outcome| outcome.manifest.clone()).collect();
+        write_manifest(path, &ScanManifest::new(repos, &Local::now().to_rfc3339()))?;
+    }
+    if !is_quiet() {
+        println!(
+            "\x1b[38;5;208m[WARN]\x1b[0m[{}]{} leaks detected. {} repositories scanned, {} failed",
+            Local::now().format("%Y-%m-%d %H:%M:%S"),
+            outcomes.iter().map(|outcome| outcome.leaks.len()).sum::<usize>(),
+            outcomes.len(),
+            outcomes.iter().filter(|outcome| !outcome.passed()).count()
+        );
+    }
+    Ok(outcomes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("acme/*", "acme/payments-api"));
+        assert!(glob_match("ACME/payments-*", "acme/Payments-API"));
+        assert!(!glob_match("acme/*", "acme/platform/api"));
+        assert!(glob_match("acme/**", "acme/platform/api"));
+        assert!(glob_match("acme/**/api", "acme/api"));
+        assert!(glob_match("acme/**/api", "acme/platform/team/api"));
+        assert!(glob_match("acme/a?i", "acme/api"));
+        assert!(!glob_match("acme/a?i", "acme/a/i"));
+        assert!(!glob_match("acme/api", "acme/api-docs"));
+    }
+
+    #[test]
+    fn test_repo_name() {
+        assert_eq!(repo_name("https://github.com/acme/payments-api.git"), "acme/payments-api");
+        assert_eq!(repo_name("https://gitlab.example.com/acme/platform/api/"), "acme/platform/api");
+        assert_eq!(repo_name("git@github.com:acme/payments-api.git"), "acme/payments-api");
+        assert_eq!(repo_name("/srv/git/app"), "/srv/git/app");
+    }
+
+    #[test]
+    fn test_policy() {
+        let policy: Policy = toml::from_str(
+            r#"
+include = ["acme/*"]
+exclude = ["acme/archive-*"]
+failOn = ["severity>=high"]
+
+[[repos]]
+match = "acme/payments-*"
+config = "configs/payments.toml"
+failOn = ["severity>=medium"]
+
+[[repos]]
+match = "acme/payments-sandbox"
+failOn = []
+"#,
+        )
+        .unwrap();
+        assert!(is_selected(&policy, "acme/web"));
+        assert!(!is_selected(&policy, "acme/archive-2019"));
+        assert!(!is_selected(&policy, "other/web"));
+
+        let dir = Path::new("policies");
+        assert_eq!(
+            repo_settings(&policy, "acme/web", "gitleaks.toml", Some(dir)),
+            RepoSettings {
+                config: "gitleaks.toml".to_string(),
+                fail_on: vec![FailOn::SeverityAtLeast("high".to_string())],
+            }
+        );
+        assert_eq!(
+            repo_settings(&policy, "acme/payments-api", "gitleaks.toml", Some(dir)),
+            RepoSettings {
+                config: Path::new("policies").join("configs/payments.toml").to_string_lossy().into_owned(),
+                fail_on: vec![FailOn::SeverityAtLeast("medium".to_string())],
+            }
+        );
+        assert!(repo_settings(&policy, "acme/payments-sandbox", "gitleaks.toml", None)
+            .fail_on
+            .is_empty());
+    }
+
+    #[test]
+    fn test_scan_repos() {
+        let dir = tempfile::tempdir().unwrap();
+        let signature = git2::Signature::now("John Doe", "johndoe@example.com").unwrap();
+        let mut lines = Vec::new();
+        for (name, content) in [("leaky", "key = sk_live_1234567890abcdef\n"), ("clean", "hello\n")] {
+            let path = dir.path().join(name);
+            let repo = git2::Repository::init(&path).unwrap();
+            fs::write(path.join("app.env"), content).unwrap();
+            let mut index = repo.index().unwrap();
+            index.add_path(Path::new("app.env")).unwrap();
+            let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+            repo.commit(Some("HEAD"), &signature, &signature, "init", &tree, &[]).unwrap();
+            lines.push(path.to_string_lossy().into_owned());
+        }
+        lines.push(dir.path().join("missing").to_string_lossy().into_owned());
+        let repos_file = dir.path().join("repos.txt");
+        fs::write(&repos_file, lines.join("\n")).unwrap();
+        let config = dir.path().join("gitleaks.toml");
+        fs::write(
+            &config,
+            "[[rules]]\nid = \"stripe\"\ndescription = \"Stripe\"\nregex = '''sk_live_[0-9a-z]{16}'''\nkeywords = [\"sk_live\"]\n",
+        )
+        .unwrap();
+        let progress = dir.path().join("progress.ndjson");
+        let manifest = dir.path().join("manifest.json");
+        let args = ReposArgs {
+            org: None,
+            repos_file: Some(repos_file.to_string_lossy().into_owned()),
+            provider: crate::models::GistHost::Github,
+            api_url: None,
+            policy: None,
+            config: config.to_string_lossy().into_owned(),
+            workers: 2,
+            progress: Some(progress.to_string_lossy().into_owned()),
+            report: None,
+            report_format: crate::models::ReportFormat::Json,
+            manifest: Some(manifest.to_string_lossy().into_owned()),
+        };
+
+        let global = Config {
+            tmp_dir: Some(dir.path().join("tmp").to_string_lossy().into_owned()),
+            ..Default::default()
+        };
+        let outcomes = scan_repos(&args, &global).unwrap();
+        let found: Vec<(usize, bool)> =
+            outcomes.iter().map(|outcome| (outcome.leaks.len(), outcome.error.is_some())).collect();
+        assert_eq!(found, vec![(1, false), (0, false), (0, true)]);
+        assert_eq!(outcomes[0].leaks[0].repo, lines[0]);
+
+        let events: Vec<Value> = fs::read_to_string(&progress)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        let count = |name: &str| events.iter().filter(|event| event["event"] == name).count();
+        assert_eq!((count("started"), count("finished"), count("failed")), (3, 2, 1));
+        let done = events.last().unwrap();
+        assert_eq!((done["event"].as_str(), done["repos"].as_u64(), done["failed"].as_u64()), (Some("done"), Some(3), Some(1)));
+
+        let manifest: ScanManifest = serde_json::from_str(&fs::read_to_string(&manifest).unwrap()).unwrap();
+        let scanned: Vec<(&str, usize, usize, usize, bool)> = manifest
+            .repos
+            .iter()
+            .map(|repo| (repo.refs.as_str(), repo.commits, repo.files, repo.leaks, repo.error.is_some()))
+            .collect();
+        assert_eq!(scanned, vec![("HEAD", 1, 1, 1, false), ("HEAD", 1, 1, 0, false), ("HEAD", 0, 0, 0, true)]);
+        assert_eq!(manifest.repos[0].repo, lines[0]);
+        assert_eq!(manifest.repos[0].head.as_ref().map(String::len), Some(40));
+        assert!(manifest.repos[2].head.is_none());
+    }
+
+    #[test]
+    fn test_read_repos_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("repos.txt");
+        fs::write(&file, "# payments\nhttps://github.com/acme/payments-api.git\n\n  ./local-repo\n").unwrap();
+        assert_eq!(
+            read_repos_file(file.to_str().unwrap()).unwrap(),
+            vec![
+                Repo {
+                    name: "acme/payments-api".to_string(),
+                    url: "https://github.com/acme/payments-api.git".to_string(),
+                },
+                Repo {
+                    name: "./local-repo".to_string(),
+                    url: "./local-repo".to_string(),
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,283 @@
+use crate::errors::CustomError;
+use crate::models::{Config, Leak, Scan};
+use crate::service::detect_service::{detect_bytes, detect_large_file};
+use crate::service::lsp_service::diagnostic;
+use crate::utils::detect_utils::{load_config, write_problem_matcher_report_to, STREAM_THRESHOLD};
+use crate::utils::generated_util::{is_generated, is_generated_path};
+use crate::utils::git_util::{clone_or_load_repository, encode_url_path};
+use git2::{Repository, StatusOptions};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+/// How long `--watch` waits between two looks at the working tree.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// How long a client of `--serve-diagnostics` may take to read a line before it is dropped.
+#[cfg(unix)]
+const CLIENT_WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The clients of `--serve-diagnostics`.
+#[cfg(unix)]
+type Client = std::os::unix::net::UnixStream;
+#[cfg(not(unix))]
+type Client = fs::File;
+
+/// Runs `--watch`: scans the modified and untracked files of the working tree, then scans them
+/// again whenever they change, until it is killed.
+///
+/// After every look at the working tree that found changes, the leaks of all the files are
+/// printed in the `problem-matcher` format, between a `sensleak: scanning` and a
+/// `sensleak: N leaks` line that background problem matchers of VS Code tasks can wait for. With
+/// `--serve-diagnostics`, the diagnostics of the files that changed are also streamed to the
+/// clients of the socket, see [`DiagnosticsStream`].
+///
+/// # Errors
+///
+/// Returns an error if the repository or the config can't be loaded, the socket can't be
+/// listened on, or the status of the working tree can't be read.
+pub fn watch(config: &Config) -> Result<(), Box<dyn Error>> {
+    let repo = clone_or_load_repository(config)?;
+    let mut scan = load_config(&repo, config)?;
+    scan.include_generated = config.include_generated;
+    let workdir = repo.workdir().ok_or(CustomError::FailLoadRepo)?.to_path_buf();
+    let stream = match &config.serve_diagnostics {
+        Some(path) => Some(serve_diagnostics(path)?),
+        None => None,
+    };
+    let mut watcher = Watcher::default();
+    loop {
+        let changed = watcher.poll(&repo, &workdir, &scan)?;
+        if !changed.is_empty() {
+            println!("sensleak: scanning {} changed files", changed.len());
+            let leaks: Vec<Leak> = watcher.leaks.values().flatten().cloned().collect();
+            write_problem_matcher_report_to(io::stdout().lock(), &leaks)?;
+            println!("sensleak: {} leaks", leaks.len());
+        }
+        if let Some(stream) = &stream {
+            let mut stream = stream.lock().unwrap();
+            for path in &changed {
+                let uri = format!("file://{}", encode_url_path(&workdir.join(path).to_string_lossy()));
+                let leaks = watcher.leaks.get(path).map(Vec::as_slice).unwrap_or_default();
+                stream.publish(&uri, leaks.iter().map(diagnostic).collect());
+            }
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// The modified and untracked files of a working tree `--watch` scanned, and their leaks.
+#[derive(Debug, Default)]
+struct Watcher {
+    /// The size and modification time of every file when it was scanned, by path.
+    files: HashMap<String, (u64, Option<SystemTime>)>,
+
+    /// The leaks of the files that have some, by path.
+    leaks: BTreeMap<String, Vec<Leak>>,
+}
+
+impl Watcher {
+    /// Scans the files that are new or changed since the last poll, and forgets those that were
+    /// deleted or committed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the paths of the files whose leaks may have changed, sorted. Files that can't be
+    /// read, e.g. while they are written, are tried again at the next poll.
+    fn poll(&mut self, repo: &Repository, workdir: &Path, scan: &Scan) -> Result<Vec<String>, Box<dyn Error>> {
+        let mut options = StatusOptions::new();
+        options.include_untracked(true);
+        options.recurse_untracked_dirs(true);
+        options.exclude_submodules(true);
+        let mut files = HashMap::new();
+        let mut changed = Vec::new();
+        for entry in repo.statuses(Some(&mut options))?.iter() {
+            let Some(path) = entry.path() else {
+                continue;
+            };
+            // Deleted files, links and directories have nothing to scan
+            let Ok(metadata) = fs::symlink_metadata(workdir.join(path)) else {
+                continue;
+            };
+            if !metadata.is_file() {
+                continue;
+            }
+            let stamp = (metadata.len(), metadata.modified().ok());
+            if self.files.get(path) != Some(&stamp) {
+                let Ok(leaks) = detect_file(workdir, path, metadata.len(), scan) else {
+                    continue;
+                };
+                self.leaks.remove(path);
+                if !leaks.is_empty() {
+                    self.leaks.insert(path.to_string(), leaks);
+                }
+                changed.push(path.to_string());
+            }
+            files.insert(path.to_string(), stamp);
+        }
+        for path in self.files.keys().filter(|path| !files.contains_key(*path)) {
+            self.leaks.remove(path);
+            changed.push(path.clone());
+        }
+        self.files = files;
+        changed.sort();
+        Ok(changed)
+    }
+}
+
+/// Scans a file of the working tree, reading it a chunk at a time if it is large. Generated files
+/// have no leaks unless `--include-generated` is set.
+fn detect_file(workdir: &Path, path: &str, size: u64, scan: &Scan) -> Result<Vec<Leak>, Box<dyn Error>> {
+    let file_path = workdir.join(path);
+    if size > STREAM_THRESHOLD {
+        if !scan.include_generated && is_generated_path(path) {
+            return Ok(Vec::new());
+        }
+        return detect_large_file(&file_path, path, &scan.ruleslist, &scan.allowlist, scan.mmap);
+    }
+    let contents = fs::read(&file_path)?;
+    if !scan.include_generated && is_generated(path, &contents) {
+        return Ok(Vec::new());
+    }
+    Ok(detect_bytes(&contents, path, &scan.ruleslist, &scan.allowlist, None))
+}
+
+/// Streams the diagnostics of `--watch` to its clients, one JSON line per file whose leaks may
+/// have changed, e.g. `{"uri": "file:///work/app/.env", "diagnostics": [...]}`, with the
+/// diagnostics as the `lsp` subcommand publishes them. A file whose leaks are gone is sent with no
+/// diagnostics. Clients that connect get the files that have diagnostics first.
+struct DiagnosticsStream<W> {
+    clients: Vec<W>,
+
+    /// The last line sent for each file that has diagnostics, by URI.
+    lines: BTreeMap<String, String>,
+}
+
+impl<W: Write> DiagnosticsStream<W> {
+    fn new() -> Self {
+        DiagnosticsStream {
+            clients: Vec::new(),
+            lines: BTreeMap::new(),
+        }
+    }
+
+    fn add_client(&mut self, mut client: W) {
+        if self.lines.values().all(|line| client.write_all(line.as_bytes()).is_ok()) {
+            self.clients.push(client);
+        }
+    }
+
+    /// Sends the diagnostics of a file to the clients, dropping those that can't be written to.
+    fn publish(&mut self, uri: &str, diagnostics: Vec<Value>) {
+        let empty = diagnostics.is_empty();
+        let line = format!("{}\n", json!({ "uri": uri, "diagnostics": diagnostics }));
+        if empty {
+            self.lines.remove(uri);
+        } else {
+            self.lines.insert(uri.to_string(), line.clone());
+        }
+        self.clients.retain_mut(|client| client.write_all(line.as_bytes()).is_ok());
+    }
+}
+
+/// Listens on a Unix socket for the clients of `--serve-diagnostics`, replacing the socket a
+/// killed earlier run left behind.
+///
+/// # Errors
+///
+/// Returns `CustomError::DiagnosticsSocketError` if the socket can't be listened on.
+#[cfg(unix)]
+fn serve_diagnostics(path: &str) -> Result<Arc<Mutex<DiagnosticsStream<Client>>>, Box<dyn Error>> {
+    use std::os::unix::fs::FileTypeExt;
+    use std::os::unix::net::UnixListener;
+
+    let socket_error = |err: io::Error| CustomError::DiagnosticsSocketError {
+        path: path.to_string(),
+        reason: err.to_string(),
+    };
+    if fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_type().is_socket()) {
+        fs::remove_file(path).map_err(socket_error)?;
+    }
+    let listener = UnixListener::bind(path).map_err(socket_error)?;
+    let stream = Arc::new(Mutex::new(DiagnosticsStream::new()));
+    let accepted = Arc::clone(&stream);
+    thread::spawn(move || {
+        for client in listener.incoming().flatten() {
+            if client.set_write_timeout(Some(CLIENT_WRITE_TIMEOUT)).is_ok() {
+                accepted.lock().unwrap().add_client(client);
+            }
+        }
+    });
+    Ok(stream)
+}
+
+#[cfg(not(unix))]
+fn serve_diagnostics(path: &str) -> Result<Arc<Mutex<DiagnosticsStream<Client>>>, Box<dyn Error>> {
+    Err(Box::new(CustomError::DiagnosticsSocketError {
+        path: path.to_string(),
+        reason: "Unix domain sockets aren't supported on this platform".to_string(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::detect_utils::load_config_from_str;
+
+    const CONFIG: &str = r#"
+[[rules]]
+id = "stripe-access-token"
+description = "Stripe"
+regex = '''sk_(test|live)_[0-9a-z]{10,32}'''
+keywords = ["sk_test", "sk_live"]
+"#;
+
+    #[test]
+    fn test_watcher_poll() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let scan = load_config_from_str(CONFIG).unwrap();
+        let mut watcher = Watcher::default();
+        fs::create_dir(dir.path().join("src")).unwrap();
+        fs::write(dir.path().join("src/app.py"), "KEY = 'sk_live_1234567890abcdef'\n").unwrap();
+        fs::write(dir.path().join("README.md"), "# App\n").unwrap();
+
+        assert_eq!(watcher.poll(&repo, dir.path(), &scan).unwrap(), ["README.md", "src/app.py"]);
+        assert_eq!(watcher.leaks["src/app.py"][0].line_number, 1);
+        assert!(watcher.poll(&repo, dir.path(), &scan).unwrap().is_empty());
+
+        fs::write(dir.path().join("src/app.py"), "KEY = os.environ['KEY']\n").unwrap();
+        assert_eq!(watcher.poll(&repo, dir.path(), &scan).unwrap(), ["src/app.py"]);
+        assert!(watcher.leaks.is_empty());
+        fs::remove_file(dir.path().join("README.md")).unwrap();
+        assert_eq!(watcher.poll(&repo, dir.path(), &scan).unwrap(), ["README.md"]);
+    }
+
+    #[test]
+    fn test_diagnostics_stream() {
+        let mut stream = DiagnosticsStream::new();
+        stream.add_client(Vec::new());
+        stream.publish("file:///app/.env", vec![json!({ "code": "stripe-access-token" })]);
+        stream.publish("file:///app/main.py", Vec::new());
+        // Clients connecting later only get the files that have diagnostics
+        stream.add_client(Vec::new());
+
+        let lines = |client: &Vec<u8>| -> Vec<Value> {
+            String::from_utf8_lossy(client).lines().map(|line| serde_json::from_str(line).unwrap()).collect()
+        };
+        let first = lines(&stream.clients[0]);
+        assert_eq!(first.len(), 2);
+        assert_eq!(first[0]["diagnostics"][0]["code"], "stripe-access-token");
+        assert_eq!(first[1], json!({ "uri": "file:///app/main.py", "diagnostics": [] }));
+        assert_eq!(lines(&stream.clients[1]), first[..1]);
+
+        stream.publish("file:///app/.env", Vec::new());
+        assert!(stream.lines.is_empty());
+    }
+}
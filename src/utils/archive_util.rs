@@ -0,0 +1,168 @@
+use crate::utils::compress_util::decompress;
+use std::error::Error;
+use std::io::{Cursor, Read};
+
+/// The largest file read from an archive, larger ones are skipped like large files of commits.
+pub const MAX_ENTRY_SIZE: u64 = 64 * (1 << 20);
+
+/// The paths and contents of the files in an archive.
+pub type ArchiveFiles = Vec<(String, Vec<u8>)>;
+
+/// The first bytes of zip archives, e.g. wheels.
+const ZIP_MAGIC: &[u8] = b"PK\x03\x04";
+
+/// Returns the files in a tar archive, compressed or not, or a zip archive, with their paths
+/// without a leading `./`. Directories, links and files larger than [`MAX_ENTRY_SIZE`] are left
+/// out.
+///
+/// # Errors
+///
+/// Returns an error if the archive is truncated or corrupt, or isn't a tar or zip archive.
+pub fn read_archive(bytes: Vec<u8>) -> Result<ArchiveFiles, Box<dyn Error>> {
+    if bytes.starts_with(ZIP_MAGIC) {
+        return read_zip(&bytes);
+    }
+    let bytes = decompress(bytes)?;
+    if bytes.len() < 512 || &bytes[257..262] != b"ustar" {
+        return Err("not a tar or zip archive".into());
+    }
+    Ok(read_tar(&bytes)?)
+}
+
+fn read_zip(bytes: &[u8]) -> Result<ArchiveFiles, Box<dyn Error>> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(bytes))?;
+    let mut files = Vec::new();
+    for i in 0..archive.len() {
+        let file = archive.by_index(i)?;
+        if !file.is_file() || file.size() > MAX_ENTRY_SIZE {
+            continue;
+        }
+        let name = clean_path(file.name());
+        let mut contents = Vec::new();
+        file.take(MAX_ENTRY_SIZE).read_to_end(&mut contents)?;
+        files.push((name, contents));
+    }
+    Ok(files)
+}
+
+/// Reads a ustar archive, with the long paths of GNU and pax headers.
+fn read_tar(bytes: &[u8]) -> Result<ArchiveFiles, String> {
+    let mut files = Vec::new();
+    let mut offset = 0;
+    let mut long_path = None;
+    while offset + 512 <= bytes.len() {
+        let header = &bytes[offset..offset + 512];
+        // The archive ends with zeroed blocks
+        if header.iter().all(|&byte| byte == 0) {
+            break;
+        }
+        let size = parse_octal(&header[124..136])?;
+        let start = offset + 512;
+        let data = start
+            .checked_add(size)
+            .and_then(|end| bytes.get(start..end))
+            .ok_or("truncated tar archive")?;
+        let path = long_path.take().unwrap_or_else(|| {
+            let name = field(&header[..100]);
+            let prefix = field(&header[345..500]);
+            if prefix.is_empty() {
+                name
+            } else {
+                format!("{}/{}", prefix, name)
+            }
+        });
+        match header[156] {
+            b'0' | 0 | b'7' if size as u64 <= MAX_ENTRY_SIZE => {
+                files.push((clean_path(&path), data.to_vec()))
+            }
+            b'L' => long_path = Some(field(data)),
+            b'x' => long_path = pax_path(data),
+            // Directories, links, global pax headers and large files
+            _ => {}
+        }
+        offset = start + size.div_ceil(512) * 512;
+    }
+    Ok(files)
+}
+
+/// Reads a NUL terminated field of a tar header.
+fn field(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|&byte| byte == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).into_owned()
+}
+
+fn parse_octal(bytes: &[u8]) -> Result<usize, String> {
+    let digits = field(bytes);
+    let digits = digits.trim();
+    if digits.is_empty() {
+        return Ok(0);
+    }
+    usize::from_str_radix(digits, 8).map_err(|_| "invalid size in tar header".to_string())
+}
+
+/// Returns the `path` record of a pax extended header, records being `<length> <key>=<value>\n`.
+fn pax_path(data: &[u8]) -> Option<String> {
+    String::from_utf8_lossy(data).lines().find_map(|record| {
+        let (_, record) = record.split_once(' ')?;
+        record.strip_prefix("path=").map(str::to_string)
+    })
+}
+
+fn clean_path(path: &str) -> String {
+    path.trim_start_matches("./").to_string()
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::io::Write;
+
+    /// Builds a ustar archive of regular files.
+    pub(crate) fn tar(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut archive = Vec::new();
+        for (path, contents) in files {
+            let mut header = [0u8; 512];
+            header[..path.len()].copy_from_slice(path.as_bytes());
+            header[124..135].copy_from_slice(format!("{:011o}", contents.len()).as_bytes());
+            header[156] = b'0';
+            header[257..262].copy_from_slice(b"ustar");
+            archive.extend_from_slice(&header);
+            archive.extend_from_slice(contents);
+            archive.resize(archive.len().div_ceil(512) * 512, 0);
+        }
+        archive.resize(archive.len() + 1024, 0);
+        archive
+    }
+
+    #[test]
+    fn test_read_tar() {
+        let archive = tar(&[("./package/index.js", b"module.exports = 1\n"), ("package/.npmrc", b"x")]);
+        let mut compressed = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        compressed.write_all(&archive).unwrap();
+        let files = read_archive(compressed.finish().unwrap()).unwrap();
+        assert_eq!(
+            files,
+            [
+                ("package/index.js".to_string(), b"module.exports = 1\n".to_vec()),
+                ("package/.npmrc".to_string(), b"x".to_vec()),
+            ]
+        );
+        assert_eq!(pax_path(b"30 path=a/very/long/name.txt\n").as_deref(), Some("a/very/long/name.txt"));
+        assert!(read_archive(b"not an archive".to_vec()).is_err());
+        assert!(read_tar(&archive[..520]).is_err());
+    }
+
+    #[test]
+    fn test_read_zip() {
+        let options = zip::write::SimpleFileOptions::default();
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        writer.add_directory("pkg/", options).unwrap();
+        writer.start_file("pkg/settings.py", options).unwrap();
+        writer.write_all(b"SECRET = 1\n").unwrap();
+        let archive = writer.finish().unwrap().into_inner();
+        assert_eq!(
+            read_archive(archive).unwrap(),
+            [("pkg/settings.py".to_string(), b"SECRET = 1\n".to_vec())]
+        );
+    }
+}
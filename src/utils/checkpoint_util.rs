@@ -0,0 +1,90 @@
+use crate::errors::CustomError;
+use crate::models::Checkpoint;
+use std::error::Error;
+use std::fs;
+use std::io::{ErrorKind, Write};
+use std::path::Path;
+
+/// Reads the checkpoint a scan of `repo` saved with `--checkpoint`.
+///
+/// # Returns
+///
+/// Returns `None` if there is no checkpoint at `path`, so that a scan can always be started with
+/// `--resume`.
+///
+/// # Errors
+///
+/// Returns `CustomError::CheckpointError` if the checkpoint can't be read or parsed, or was saved
+/// by a scan of another repository.
+pub fn load_checkpoint(path: &str, repo: &str) -> Result<Option<Checkpoint>, Box<dyn Error>> {
+    let error = |reason: String| CustomError::CheckpointError {
+        path: path.to_string(),
+        reason,
+    };
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(error(err.to_string()).into()),
+    };
+    let checkpoint: Checkpoint =
+        serde_json::from_str(&contents).map_err(|err| error(err.to_string()))?;
+    if checkpoint.repo != repo {
+        return Err(error(format!("it was saved by a scan of {}", checkpoint.repo)).into());
+    }
+    Ok(Some(checkpoint))
+}
+
+/// Saves the progress of a scan to `path`.
+///
+/// The checkpoint holds the secrets of the leaks found so far, so it is only readable by its
+/// owner (mode 0600 on Unix). It is written to a temporary file next to `path` and then renamed
+/// over it, so that a scan killed while saving leaves the previous checkpoint whole.
+///
+/// # Errors
+///
+/// This function returns an error if the checkpoint can't be written.
+pub fn save_checkpoint(path: &str, checkpoint: &Checkpoint) -> Result<(), Box<dyn Error>> {
+    let dir = Path::new(path).parent().filter(|dir| !dir.as_os_str().is_empty());
+    // Temporary files are created with mode 0600
+    let mut temp = tempfile::Builder::new()
+        .prefix(".sensleak-checkpoint")
+        .tempfile_in(dir.unwrap_or(Path::new(".")))?;
+    temp.write_all(&serde_json::to_vec(checkpoint)?)?;
+    temp.as_file().sync_all()?;
+    temp.persist(path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checkpoint_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("scan.checkpoint").to_str().unwrap().to_string();
+        assert!(load_checkpoint(&path, "repo").unwrap().is_none());
+
+        let checkpoint = Checkpoint {
+            repo: "repo".to_string(),
+            last_commit: Some("abc".to_string()),
+            commits: vec!["abc".to_string()],
+            ..Default::default()
+        };
+        save_checkpoint(&path, &checkpoint).unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        }
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+        let loaded = load_checkpoint(&path, "repo").unwrap().unwrap();
+        assert_eq!(loaded.commits, ["abc"]);
+        assert_eq!(loaded.last_commit.as_deref(), Some("abc"));
+
+        let err = load_checkpoint(&path, "other").unwrap_err();
+        assert!(err.to_string().contains("saved by a scan of repo"));
+        fs::write(&path, "{").unwrap();
+        assert!(load_checkpoint(&path, "repo").is_err());
+    }
+}
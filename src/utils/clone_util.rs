@@ -0,0 +1,138 @@
+use chrono::Utc;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use walkdir::WalkDir;
+
+/// The file in the `.git` directory of a clone kept for later scans, holding when it was last
+/// scanned, in seconds since the epoch. Only the clones that have it are ever evicted.
+pub const LAST_USED_FILE: &str = "sensleak-last-used";
+
+/// The clones being written or scanned, which eviction leaves alone and which are deleted if the
+/// process is interrupted.
+static ACTIVE_CLONES: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+/// Records that a clone is being written or scanned, until [`release_clone`].
+pub fn track_clone(path: &Path) {
+    ACTIVE_CLONES.lock().unwrap().push(path.to_path_buf());
+}
+
+/// Records that a clone is no longer scanned.
+pub fn release_clone(path: &Path) {
+    let mut active = ACTIVE_CLONES.lock().unwrap();
+    if let Some(i) = active.iter().position(|active| active == path) {
+        active.remove(i);
+    }
+}
+
+fn is_active(path: &Path) -> bool {
+    ACTIVE_CLONES.lock().unwrap().iter().any(|active| active == path)
+}
+
+/// Deletes the clones being written or scanned, e.g. when the process is interrupted, as they may
+/// be incomplete and nothing would delete them later.
+pub fn remove_active_clones() {
+    let active = std::mem::take(&mut *ACTIVE_CLONES.lock().unwrap());
+    for path in active {
+        if let Err(e) = fs::remove_dir_all(&path) {
+            if e.kind() != io::ErrorKind::NotFound {
+                eprintln!("Delete dir fail: {}", e);
+            }
+        }
+    }
+}
+
+/// Marks a clone as kept for later scans and just used, so that it is evicted after the clones
+/// used before it.
+///
+/// # Arguments
+///
+/// * `git_dir` - The `.git` directory of the clone.
+pub fn touch_clone(git_dir: &Path) -> io::Result<()> {
+    fs::write(git_dir.join(LAST_USED_FILE), Utc::now().timestamp().to_string())
+}
+
+/// Returns when a clone kept for later scans was last used, or `None` if it isn't one.
+fn last_used(clone: &Path) -> Option<i64> {
+    fs::read_to_string(clone.join(".git").join(LAST_USED_FILE)).ok()?.trim().parse().ok()
+}
+
+/// Returns the size of the files in a directory and its subdirectories, in bytes.
+pub fn dir_size(path: &Path) -> u64 {
+    WalkDir::new(path)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter_map(|entry| entry.metadata().ok())
+        .filter(|metadata| metadata.is_file())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+/// Deletes the clones kept in a directory, least recently used first, until they take no more
+/// than `max_bytes`. Clones being written or scanned are left alone, and other directories
+/// aren't touched.
+///
+/// # Returns
+///
+/// The clones deleted.
+///
+/// # Errors
+///
+/// This function returns an error if the directory can't be listed or a clone can't be deleted.
+pub fn evict_clones(dir: &Path, max_bytes: u64) -> io::Result<Vec<PathBuf>> {
+    let mut clones: Vec<(i64, u64, PathBuf)> = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if let Some(used) = last_used(&path) {
+            clones.push((used, dir_size(&path), path));
+        }
+    }
+    let mut total: u64 = clones.iter().map(|(_, size, _)| size).sum();
+    clones.sort();
+    let mut evicted = Vec::new();
+    for (_, size, path) in clones {
+        if total <= max_bytes {
+            break;
+        }
+        if is_active(&path) {
+            continue;
+        }
+        fs::remove_dir_all(&path)?;
+        total -= size;
+        evicted.push(path);
+    }
+    Ok(evicted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evict_clones() {
+        let dir = tempfile::tempdir().unwrap();
+        let clone = |name: &str, used: i64, size: usize| {
+            let git_dir = dir.path().join(name).join(".git");
+            fs::create_dir_all(&git_dir).unwrap();
+            fs::write(git_dir.join("pack"), vec![0; size]).unwrap();
+            fs::write(git_dir.join(LAST_USED_FILE), used.to_string()).unwrap();
+            dir.path().join(name)
+        };
+        let oldest = clone("oldest", 100, 1000);
+        let older = clone("older", 200, 1000);
+        let active = clone("active", 50, 1000);
+        let newest = clone("newest", 300, 1000);
+        // Not a clone kept by sensleak
+        fs::create_dir_all(dir.path().join("other")).unwrap();
+        fs::write(dir.path().join("other").join("data"), vec![0; 5000]).unwrap();
+
+        track_clone(&active);
+        let evicted = evict_clones(dir.path(), 2500).unwrap();
+        release_clone(&active);
+        assert_eq!(evicted, vec![oldest.clone(), older.clone()]);
+        assert!(!oldest.exists() && !older.exists());
+        assert!(active.exists() && newest.exists() && dir.path().join("other").exists());
+        assert!(evict_clones(dir.path(), 2500).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,149 @@
+use crate::utils::crypt_util;
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use std::error::Error;
+use std::io::{Read, Write};
+use std::process::Command;
+
+/// The first bytes of gzip and zstd files.
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+/// How a report file is compressed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    /// Picks the compression of a report from its extension, `.gz` or `.zst`. An encryption
+    /// extension after it is skipped, so `leaks.json.gz.age` is compressed before it is encrypted.
+    pub fn from_path(path: &str) -> Self {
+        let path = [".age", ".gpg", ".asc"]
+            .iter()
+            .find_map(|ext| path.strip_suffix(ext))
+            .unwrap_or(path);
+        if path.ends_with(".gz") {
+            Compression::Gzip
+        } else if path.ends_with(".zst") {
+            Compression::Zstd
+        } else {
+            Compression::None
+        }
+    }
+
+    /// Recognizes compressed contents by their first bytes.
+    pub fn detect(contents: &[u8]) -> Self {
+        if contents.starts_with(GZIP_MAGIC) {
+            Compression::Gzip
+        } else if contents.starts_with(ZSTD_MAGIC) {
+            Compression::Zstd
+        } else {
+            Compression::None
+        }
+    }
+}
+
+/// Compresses the contents of a report. Gzip is built in, zstd runs the `zstd` command.
+///
+/// # Errors
+///
+/// Returns `CustomError::ReportToolError` if the `zstd` command can't be run or fails.
+pub fn compress(compression: Compression, contents: Vec<u8>) -> Result<Vec<u8>, Box<dyn Error>> {
+    match compression {
+        Compression::None => Ok(contents),
+        Compression::Gzip => {
+            let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(&contents)?;
+            Ok(encoder.finish()?)
+        }
+        Compression::Zstd => {
+            crypt_util::run(Command::new("zstd").args(["--quiet", "--stdout"]), Some(&contents))
+        }
+    }
+}
+
+/// Decompresses contents read from a report, given as they are if they aren't compressed.
+///
+/// # Errors
+///
+/// Returns an error if the gzip data is corrupt, or the `zstd` command can't be run or fails.
+pub fn decompress(contents: Vec<u8>) -> Result<Vec<u8>, Box<dyn Error>> {
+    match Compression::detect(&contents) {
+        Compression::None => Ok(contents),
+        Compression::Gzip => {
+            let mut decompressed = Vec::new();
+            MultiGzDecoder::new(contents.as_slice()).read_to_end(&mut decompressed)?;
+            Ok(decompressed)
+        }
+        Compression::Zstd => crypt_util::run(
+            Command::new("zstd").args(["--quiet", "--decompress", "--stdout"]),
+            Some(&contents),
+        ),
+    }
+}
+
+/// Reads a report or a list of leaks, decompressing it if it is compressed.
+///
+/// # Errors
+///
+/// Returns an error if the file can't be read or decompressed.
+pub fn read_file(path: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+    decompress(std::fs::read(path)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compression_from_path() {
+        assert_eq!(Compression::from_path("leaks.json.gz"), Compression::Gzip);
+        assert_eq!(Compression::from_path("leaks.json.zst"), Compression::Zstd);
+        assert_eq!(Compression::from_path("leaks.json.gz.age"), Compression::Gzip);
+        assert_eq!(Compression::from_path("leaks.json"), Compression::None);
+        assert_eq!(Compression::from_path("leaks.gzip"), Compression::None);
+    }
+
+    #[test]
+    fn test_gzip_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("leaks.json.gz");
+        let path = path.to_str().unwrap();
+        let compressed = compress(Compression::from_path(path), b"[]".to_vec()).unwrap();
+        assert_eq!(Compression::detect(&compressed), Compression::Gzip);
+        std::fs::write(path, compressed).unwrap();
+        assert_eq!(read_file(path).unwrap(), b"[]");
+
+        let plain = dir.path().join("leaks.json");
+        let plain = plain.to_str().unwrap();
+        std::fs::write(plain, b"[]").unwrap();
+        assert_eq!(read_file(plain).unwrap(), b"[]");
+    }
+
+    #[test]
+    fn test_zstd_round_trip() {
+        // Only runs where zstd is installed
+        if Command::new("zstd").arg("--version").output().is_err() {
+            return;
+        }
+        let compressed = compress(Compression::Zstd, b"[]".to_vec()).unwrap();
+        assert_eq!(Compression::detect(&compressed), Compression::Zstd);
+        assert_eq!(decompress(compressed).unwrap(), b"[]");
+
+        // Larger than a pipe buffer both ways, so zstd blocks on its output while still reading
+        let mut state = 0x2545_f491_u32;
+        let contents: Vec<u8> = (0..8 * 1024 * 1024)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state as u8
+            })
+            .collect();
+        let compressed = compress(Compression::Zstd, contents.clone()).unwrap();
+        assert_eq!(decompress(compressed).unwrap(), contents);
+    }
+}
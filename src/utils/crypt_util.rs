@@ -0,0 +1,167 @@
+use crate::errors::CustomError;
+use std::error::Error;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// The first bytes of binary and armored age files.
+const AGE_HEADERS: [&[u8]; 2] = [b"age-encryption.org/v1", b"-----BEGIN AGE ENCRYPTED FILE-----"];
+
+/// Returns whether a recipient is an age public key, rather than a GPG key id, fingerprint or
+/// email.
+pub fn is_age_recipient(recipient: &str) -> bool {
+    recipient.starts_with("age1") || recipient.starts_with("ssh-")
+}
+
+/// Returns whether a file was encrypted with age, judging from its first bytes.
+pub fn is_age_file(contents: &[u8]) -> bool {
+    AGE_HEADERS.iter().any(|header| contents.starts_with(header))
+}
+
+/// Encrypts `contents` to a recipient and writes them to `path`, with the `age` command for age
+/// recipients and with `gpg` otherwise. The contents are piped to the command, so they are never
+/// written to disk unencrypted. GPG keys must be in the local keyring, they aren't looked up
+/// online.
+///
+/// # Errors
+///
+/// Returns `CustomError::ReportToolError` if the command can't be run or fails, e.g. because
+/// GPG doesn't know the key.
+pub fn encrypt_to(path: &str, recipient: &str, contents: &[u8]) -> Result<(), Box<dyn Error>> {
+    encrypt_with(Command::new("gpg"), path, recipient, contents)
+}
+
+/// Encrypts as [`encrypt_to`] does, running `gpg` for GPG recipients.
+fn encrypt_with(mut gpg: Command, path: &str, recipient: &str, contents: &[u8]) -> Result<(), Box<dyn Error>> {
+    let mut command = if is_age_recipient(recipient) {
+        let mut command = Command::new("age");
+        command.args(["--encrypt", "--recipient", recipient, "--output", path]);
+        command
+    } else {
+        gpg.args(["--batch", "--yes", "--trust-model", "always", "--auto-key-locate", "local", "--encrypt"]);
+        gpg.args(["--recipient", recipient, "--output", path]);
+        gpg
+    };
+    run(&mut command, Some(contents)).map(|_| ())
+}
+
+/// Decrypts a file written by [`encrypt_to`], with `age` or `gpg` depending on its format.
+///
+/// # Arguments
+///
+/// * `path` - The encrypted file.
+/// * `identity` - The age identity file to decrypt with. GPG finds its keys itself.
+///
+/// # Errors
+///
+/// Returns `CustomError::ReportToolError` if the command can't be run or fails, e.g. because
+/// no key can decrypt the file.
+pub fn decrypt(path: &str, identity: Option<&str>) -> Result<Vec<u8>, Box<dyn Error>> {
+    decrypt_with(Command::new("gpg"), path, identity)
+}
+
+/// Decrypts as [`decrypt`] does, running `gpg` for files that aren't age files.
+fn decrypt_with(mut gpg: Command, path: &str, identity: Option<&str>) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut header = [0u8; 34];
+    let read = {
+        use std::io::Read;
+        std::fs::File::open(path)?.read(&mut header)?
+    };
+    let mut command = if is_age_file(&header[..read]) {
+        let mut command = Command::new("age");
+        command.arg("--decrypt");
+        if let Some(identity) = identity {
+            command.args(["--identity", identity]);
+        }
+        command
+    } else {
+        gpg.args(["--quiet", "--decrypt"]);
+        gpg
+    };
+    command.arg(path);
+    run(&mut command, None)
+}
+
+/// Runs a command reports are piped through, feeding it `input` if any, and returns what it printed.
+pub(crate) fn run(command: &mut Command, input: Option<&[u8]>) -> Result<Vec<u8>, Box<dyn Error>> {
+    let tool = command.get_program().to_string_lossy().into_owned();
+    let error = |reason: String| CustomError::ReportToolError {
+        tool: tool.clone(),
+        reason,
+    };
+    // Passphrase prompts go to the terminal, only the decrypted contents are captured
+    let mut child = command
+        .stdin(if input.is_some() { Stdio::piped() } else { Stdio::inherit() })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn()
+        .map_err(|err| error(err.to_string()))?;
+    // Input is fed from its own thread while stdout is drained, as tools like zstd stop reading
+    // once the pipe they write to is full
+    let stdin = child.stdin.take();
+    let (output, written) = std::thread::scope(|scope| {
+        let writer = input
+            .zip(stdin)
+            .map(|(input, mut stdin)| scope.spawn(move || stdin.write_all(input)));
+        let output = child.wait_with_output();
+        let written = writer.map_or(Ok(()), |writer| writer.join().unwrap_or(Ok(())));
+        (output, written)
+    });
+    let output = output.map_err(|err| error(err.to_string()))?;
+    if !output.status.success() {
+        return Err(Box::new(error(format!("exited with {}", output.status))));
+    }
+    written.map_err(|err| error(err.to_string()))?;
+    Ok(output.stdout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_age() {
+        assert!(is_age_recipient(
+            "age1ql3z7hjy54pw3hyww5ayyfg7zqgvc7w3j2elw8zmrj2kg5sfn9aqmcac8p"
+        ));
+        assert!(!is_age_recipient("security@example.com"));
+        assert!(is_age_file(b"age-encryption.org/v1\n-> X25519 abc\n"));
+        assert!(is_age_file(b"-----BEGIN AGE ENCRYPTED FILE-----\nYWdl"));
+        assert!(!is_age_file(b"-----BEGIN PGP MESSAGE-----\n"));
+    }
+
+    #[test]
+    fn test_encrypt_to_gpg() {
+        // Only runs where GPG is installed
+        if Command::new("gpg").arg("--version").output().is_err() {
+            return;
+        }
+        // A keyring of its own, leaving the user's and the environment of other tests alone
+        let dir = tempfile::tempdir().unwrap();
+        let home = dir.path().join("gnupg");
+        std::fs::create_dir(&home).unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&home, std::fs::Permissions::from_mode(0o700)).unwrap();
+        }
+        let gpg = || {
+            let mut gpg = Command::new("gpg");
+            gpg.arg("--homedir").arg(&home);
+            gpg
+        };
+        let generated = gpg()
+            .args(["--batch", "--passphrase", "", "--quick-generate-key"])
+            .args(["report@example.com", "default", "default", "never"])
+            .output()
+            .unwrap();
+        assert!(generated.status.success());
+
+        let path = dir.path().join("leaks.json.gpg");
+        let path = path.to_str().unwrap();
+        encrypt_with(gpg(), path, "report@example.com", b"[]").unwrap();
+        assert_ne!(std::fs::read(path).unwrap(), b"[]");
+        assert_eq!(decrypt_with(gpg(), path, None).unwrap(), b"[]");
+        assert!(encrypt_with(gpg(), path, "unknown@example.com", b"[]").is_err());
+        let _ = Command::new("gpgconf").arg("--homedir").arg(&home).args(["--kill", "gpg-agent"]).output();
+    }
+}
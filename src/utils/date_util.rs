@@ -0,0 +1,133 @@
+use crate::errors::CustomError;
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveTime, Utc};
+use std::error::Error;
+
+/// Date and time formats accepted besides RFC 3339, e.g. `2023-01-02T15:04:05-0700`.
+const DATETIME_FORMATS: [&str; 4] = [
+    "%Y-%m-%dT%H:%M:%S%.f%z",
+    "%Y-%m-%dT%H:%M:%S%.f%:z",
+    "%Y-%m-%d %H:%M:%S%.f%z",
+    "%Y-%m-%d %H:%M:%S%.f%:z",
+];
+
+/// Parses one bound of a commit time range.
+///
+/// # Arguments
+///
+/// * `input` - A date (`2006-01-02`, taken in UTC) or a date and time with an offset, such as
+///   `2006-01-02T15:04:05Z`, `2006-01-02T15:04:05+08:00` or `2006-01-02T15:04:05-0700`.
+/// * `end_of_day` - Whether a date stands for the end of that day instead of its start, so that a
+///   range of dates includes both days entirely.
+///
+/// # Returns
+///
+/// Returns the point in time, keeping the offset it was given in.
+///
+/// # Errors
+///
+/// Returns `CustomError::InvalidDateFormat` if the input matches none of the formats, including
+/// dates that don't exist such as `2023-02-30`.
+pub fn parse_commit_time(
+    input: &str,
+    end_of_day: bool,
+) -> Result<DateTime<FixedOffset>, Box<dyn Error>> {
+    let input = input.trim();
+
+    if let Ok(date) = NaiveDate::parse_from_str(input, "%Y-%m-%d") {
+        let time = if end_of_day {
+            NaiveTime::from_hms_opt(23, 59, 59)
+        } else {
+            NaiveTime::from_hms_opt(0, 0, 0)
+        }
+        .ok_or(CustomError::InvalidTimeFormat)?;
+        return Ok(date.and_time(time).and_utc().fixed_offset());
+    }
+
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(input) {
+        return Ok(datetime);
+    }
+    DATETIME_FORMATS
+        .iter()
+        .find_map(|format| DateTime::parse_from_str(input, format).ok())
+        .ok_or_else(|| CustomError::InvalidDateFormat.into())
+}
+
+/// Parses the bounds of a commit time range, both included.
+///
+/// # Errors
+///
+/// Returns `CustomError::InvalidDateFormat` if a bound can't be parsed, or
+/// `CustomError::InvalidDateRange` if `since` is later than `until`.
+pub fn parse_time_range(
+    since: &str,
+    until: &str,
+) -> Result<(DateTime<FixedOffset>, DateTime<FixedOffset>), Box<dyn Error>> {
+    let start_time = parse_commit_time(since, false)?;
+    let end_time = parse_commit_time(until, true)?;
+    if start_time > end_time {
+        return Err(Box::new(CustomError::InvalidDateRange));
+    }
+    Ok((start_time, end_time))
+}
+
+/// Converts a commit timestamp to a `DateTime` in the offset recorded with it.
+pub fn to_datetime(seconds: i64, offset_minutes: i32) -> DateTime<FixedOffset> {
+    let offset = FixedOffset::east_opt(offset_minutes * 60)
+        .unwrap_or_else(|| FixedOffset::east_opt(0).unwrap());
+    DateTime::<Utc>::from_timestamp(seconds, 0)
+        .unwrap_or_default()
+        .with_timezone(&offset)
+}
+
+/// Validates a `--commit-since`/`--commit-until` value while the arguments are parsed.
+pub(crate) fn parse_date_arg(input: &str) -> Result<String, String> {
+    parse_commit_time(input, false)
+        .map(|_| input.to_string())
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_commit_time_date() {
+        let start = parse_commit_time("2023-05-25", false).unwrap();
+        let end = parse_commit_time("2023-05-25", true).unwrap();
+        assert_eq!(start.to_rfc3339(), "2023-05-25T00:00:00+00:00");
+        assert_eq!(end.to_rfc3339(), "2023-05-25T23:59:59+00:00");
+    }
+
+    #[test]
+    fn test_parse_commit_time_with_offset() {
+        for input in [
+            "2023-01-02T15:04:05-07:00",
+            "2023-01-02T15:04:05-0700",
+            "2023-01-02 15:04:05-0700",
+            "2023-01-02T22:04:05Z",
+        ] {
+            let time = parse_commit_time(input, false).unwrap();
+            assert_eq!(time.timestamp(), 1672697045, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_parse_commit_time_invalid() {
+        for input in ["2023-02-30", "2023-01-02T15:04:05", "yesterday", ""] {
+            assert!(parse_commit_time(input, false).is_err(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_parse_time_range() {
+        let (since, until) = parse_time_range("2023-05-20", "2023-05-20T12:00:00+08:00").unwrap();
+        assert!(since < until);
+        assert!(parse_time_range("2023-05-21", "2023-05-20").is_err());
+    }
+
+    #[test]
+    fn test_to_datetime() {
+        let time = to_datetime(1672697045, -420);
+        assert_eq!(time.to_rfc3339(), "2023-01-02T15:04:05-07:00");
+    }
+}
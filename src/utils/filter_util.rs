@@ -0,0 +1,107 @@
+use crate::models::BuiltinFilter;
+
+/// Every built-in filter, in the order they are checked.
+pub const BUILTIN_FILTERS: [BuiltinFilter; 4] = [
+    BuiltinFilter::Placeholder,
+    BuiltinFilter::Uuid,
+    BuiltinFilter::GitSha,
+    BuiltinFilter::Hash,
+];
+
+/// Returns the value a finding assigns, e.g. `abc` for `api_key = "abc"`, or the whole finding
+/// if it isn't an assignment, without the quotes and spaces around it.
+fn assigned_value(offender: &str) -> &str {
+    let value = offender.rsplit(['=', ':']).next().unwrap_or(offender);
+    let value = if value.trim().is_empty() { offender } else { value };
+    value.trim().trim_matches(['"', '\'', '`', ';', ','])
+}
+
+/// Returns the built-in filter that drops a finding, unless it is disabled.
+///
+/// The filters look at the value the finding assigns, if it is an assignment, then at the whole
+/// finding, so that `token: ${TOKEN}` is a placeholder like `${TOKEN}`.
+///
+/// # Arguments
+///
+/// * `offender` - The secret the rule found.
+/// * `disabled` - The filters `--disable-filter` turned off.
+///
+/// # Returns
+///
+/// Returns the first filter the finding is plainly an instance of, or `None` if it may be a secret.
+pub fn builtin_filter(offender: &str, disabled: &[BuiltinFilter]) -> Option<BuiltinFilter> {
+    let value = assigned_value(offender);
+    BUILTIN_FILTERS
+        .into_iter()
+        .filter(|filter| !disabled.contains(filter))
+        .find(|filter| [value, offender.trim()].iter().any(|text| matches(*filter, text)))
+}
+
+fn matches(filter: BuiltinFilter, text: &str) -> bool {
+    let hex = |text: &str| !text.is_empty() && text.chars().all(|c| c.is_ascii_hexdigit());
+    match filter {
+        BuiltinFilter::Uuid => {
+            let groups: Vec<&str> = text.split('-').collect();
+            groups.len() == 5
+                && groups.iter().map(|group| group.len()).eq([8, 4, 4, 4, 12])
+                && groups.iter().all(|group| hex(group))
+        }
+        BuiltinFilter::GitSha => text.len() == 40 && hex(text),
+        BuiltinFilter::Hash => {
+            let digest = ["sha256:", "sha512:", "sha256-", "sha384-", "sha512-"]
+                .iter()
+                .any(|prefix| text.len() > prefix.len() + 40 && text.starts_with(prefix));
+            digest || (matches!(text.len(), 32 | 64 | 128) && hex(text))
+        }
+        BuiltinFilter::Placeholder => is_template(text),
+    }
+}
+
+/// Returns whether a text is a placeholder of a template rather than a value: `${VAR}`, `$VAR`,
+/// `{{ var }}`, `%(var)s` or `<VAR>`.
+fn is_template(text: &str) -> bool {
+    let enclosed = |open: &str, close: &str| {
+        text.len() > open.len() + close.len() && text.starts_with(open) && text.ends_with(close)
+    };
+    let name = |name: &str| {
+        !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+    };
+    if enclosed("${", "}") || enclosed("{{", "}}") || enclosed("%(", ")s") {
+        return true;
+    }
+    if let Some(variable) = text.strip_prefix('$') {
+        return name(variable) && variable.chars().all(|c| !c.is_ascii_lowercase());
+    }
+    // Upper case names only, so that e.g. `<br>` in HTML isn't one
+    enclosed("<", ">")
+        && name(&text[1..text.len() - 1])
+        && text[1..text.len() - 1].chars().any(|c| c.is_ascii_uppercase())
+        && text.chars().all(|c| !c.is_ascii_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_filter() {
+        let filter = |offender| builtin_filter(offender, &[]);
+        assert_eq!(filter("123e4567-e89b-12d3-a456-426614174000"), Some(BuiltinFilter::Uuid));
+        assert_eq!(filter("commit = 9fceb02d0ae598e95dc970b74767f19372d61af8"), Some(BuiltinFilter::GitSha));
+        assert_eq!(filter("d41d8cd98f00b204e9800998ecf8427e"), Some(BuiltinFilter::Hash));
+        assert_eq!(
+            filter("sha512-9BhmYzMzGd+3zS0aQ8xCyRll5qvPr7ZwT0ZJzFcR7Ia6S0w3RY96uDZIFJYdgKusVPUhGsxG2yINA1KfhT0Rcg=="),
+            Some(BuiltinFilter::Hash)
+        );
+        assert_eq!(filter("password = \"${DB_PASSWORD}\""), Some(BuiltinFilter::Placeholder));
+        assert_eq!(filter("{{ secret }}"), Some(BuiltinFilter::Placeholder));
+        assert_eq!(filter("token: <PASSWORD>"), Some(BuiltinFilter::Placeholder));
+        assert_eq!(filter("$API_TOKEN"), Some(BuiltinFilter::Placeholder));
+
+        assert_eq!(filter("sk_live_1234567890abcdef"), None);
+        assert_eq!(filter("wJalrXUtnFEMI/K7MDENG/bPxRfiCYEXAMPLEKEY"), None);
+        assert_eq!(filter("<br>"), None);
+        assert_eq!(filter("$ecretPa55"), None);
+        assert_eq!(builtin_filter("123e4567-e89b-12d3-a456-426614174000", &[BuiltinFilter::Uuid]), None);
+    }
+}
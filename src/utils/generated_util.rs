@@ -0,0 +1,111 @@
+/// The names of lockfiles and other files package managers and tools write, matched whatever the
+/// directory they are in.
+const GENERATED_FILE_NAMES: &[&str] = &[
+    "package-lock.json",
+    "npm-shrinkwrap.json",
+    "yarn.lock",
+    "pnpm-lock.yaml",
+    "bun.lockb",
+    "cargo.lock",
+    "composer.lock",
+    "gemfile.lock",
+    "poetry.lock",
+    "pipfile.lock",
+    "uv.lock",
+    "go.sum",
+    "flake.lock",
+    "packages.lock.json",
+    "podfile.lock",
+    "pubspec.lock",
+    "mix.lock",
+];
+
+/// The endings of the names of minified, compiled and generated sources, e.g. `app.min.js` or
+/// `user.pb.go`.
+const GENERATED_FILE_SUFFIXES: &[&str] = &[
+    ".min.js",
+    ".min.mjs",
+    ".min.css",
+    ".js.map",
+    ".css.map",
+    ".bundle.js",
+    ".pb.go",
+    ".pb.cc",
+    ".pb.h",
+    "_pb2.py",
+    "_pb2_grpc.py",
+    ".g.dart",
+    ".designer.cs",
+    ".generated.cs",
+];
+
+/// The markers code generators write at the top of what they generate, e.g. `// @generated` or
+/// `// Code generated by protoc-gen-go. DO NOT EDIT.`
+const GENERATED_MARKERS: &[&str] = &["@generated", "DO NOT EDIT", "auto-generated", "autogenerated"];
+
+/// How much of the start of a file is searched for [`GENERATED_MARKERS`].
+const MARKER_SEARCH_LEN: usize = 1024;
+
+/// How much of the start of a file is measured to tell whether it is minified.
+const MINIFIED_SAMPLE_LEN: usize = 64 * 1024;
+
+/// The average length of the lines of a minified file, above which few people write code.
+const MINIFIED_LINE_LEN: usize = 500;
+
+/// Returns whether a file was written by a tool rather than by hand: a lockfile, a minified
+/// bundle or a generated source. Their contents are mostly hashes, integrity checksums and long
+/// runs of random-looking identifiers, which rules find far more false positives in than secrets,
+/// so they are skipped unless `--include-generated` is set.
+///
+/// # Arguments
+///
+/// * `path` - The path of the file, whose name is matched against known generated files.
+/// * `contents` - The start of the contents of the file, searched for generator markers and
+///   measured for minified lines.
+pub fn is_generated(path: &str, contents: &[u8]) -> bool {
+    is_generated_path(path) || has_generated_marker(contents) || is_minified(contents)
+}
+
+/// Returns whether the name of a file is that of a lockfile or a minified or generated source,
+/// e.g. `web/package-lock.json` or `static/app.min.js`.
+pub fn is_generated_path(path: &str) -> bool {
+    let name = path.rsplit(['/', '\\']).next().unwrap_or(path).to_ascii_lowercase();
+    GENERATED_FILE_NAMES.contains(&name.as_str())
+        || GENERATED_FILE_SUFFIXES.iter().any(|suffix| name.ends_with(suffix))
+}
+
+/// Returns whether the first lines of a file have the marker of a code generator.
+fn has_generated_marker(contents: &[u8]) -> bool {
+    let head = String::from_utf8_lossy(&contents[..contents.len().min(MARKER_SEARCH_LEN)]);
+    GENERATED_MARKERS.iter().any(|marker| head.contains(marker))
+}
+
+/// Returns whether a file looks minified: the lines of its start are longer than
+/// [`MINIFIED_LINE_LEN`] on average.
+fn is_minified(contents: &[u8]) -> bool {
+    let sample = &contents[..contents.len().min(MINIFIED_SAMPLE_LEN)];
+    let lines = sample.split(|&byte| byte == b'\n').filter(|line| !line.is_empty()).count();
+    lines > 0 && sample.len() / lines > MINIFIED_LINE_LEN
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_generated() {
+        assert!(is_generated("/web/package-lock.json", b"{}"));
+        assert!(is_generated("/Cargo.lock", b""));
+        assert!(is_generated("/static/app.min.js", b""));
+        assert!(is_generated("/api/user.pb.go", b""));
+        assert!(is_generated(
+            "/src/schema.rs",
+            b"// @generated automatically by Diesel CLI.\n\ndiesel::table! {}\n"
+        ));
+        assert!(is_generated("/dist/app.js", format!("var a={};\n", "b".repeat(2000)).as_bytes()));
+
+        assert!(!is_generated("/src/config.py", b"API_KEY = 'sk_live_1234567890'\n"));
+        assert!(!is_generated("/src/lock.rs", b"fn main() {}\n"));
+        assert!(!is_generated("/empty.txt", b""));
+    }
+}
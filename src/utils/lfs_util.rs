@@ -0,0 +1,231 @@
+use crate::utils::git_util::common_dir;
+use crate::utils::http_util::{send, sha256_hex, MAX_RESPONSE_SIZE};
+use git2::Repository;
+use serde_json::{json, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The largest Git LFS object `--fetch-lfs` downloads and scans, as large as a response can be.
+pub const LFS_MAX_SIZE: u64 = MAX_RESPONSE_SIZE as u64;
+
+/// Pointer files are a few lines, well under this size.
+const MAX_POINTER_SIZE: usize = 1024;
+
+/// The first lines of pointer files, of the current and of the pre-release spec.
+const POINTER_VERSIONS: &[&str] = &[
+    "version https://git-lfs.github.com/spec/v1",
+    "version https://hawser.github.com/spec/v1",
+];
+
+/// The media type of requests and responses of the batch API.
+const LFS_MEDIA_TYPE: &str = "application/vnd.git-lfs+json";
+
+/// A Git LFS pointer file, committed in place of the file it points to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LfsPointer {
+    /// The SHA-256 of the object, in lowercase hex.
+    pub oid: String,
+
+    /// The size of the object, in bytes.
+    pub size: u64,
+}
+
+/// Reads a Git LFS pointer file, e.g.
+///
+/// ```text
+/// version https://git-lfs.github.com/spec/v1
+/// oid sha256:4d7a214614ab2935c943f9e0ff69d22eadbb8f32b1258daaa5e2ca24d17e2393
+/// size 12345
+/// ```
+///
+/// # Returns
+///
+/// Returns `None` if the contents aren't a pointer file.
+pub fn parse_pointer(contents: &[u8]) -> Option<LfsPointer> {
+    if contents.len() > MAX_POINTER_SIZE {
+        return None;
+    }
+    let text = std::str::from_utf8(contents).ok()?;
+    let mut lines = text.lines();
+    if !POINTER_VERSIONS.contains(&lines.next()?) {
+        return None;
+    }
+    let (mut oid, mut size) = (None, None);
+    for line in lines {
+        if let Some(hash) = line.strip_prefix("oid sha256:") {
+            let valid = hash.len() == 64 && hash.bytes().all(|byte| byte.is_ascii_hexdigit());
+            oid = valid.then(|| hash.to_ascii_lowercase());
+        } else if let Some(value) = line.strip_prefix("size ") {
+            size = value.parse().ok();
+        }
+    }
+    Some(LfsPointer {
+        oid: oid?,
+        size: size?,
+    })
+}
+
+/// Returns where `git lfs fetch` stores an object in a repository, e.g.
+/// `.git/lfs/objects/4d/7a/4d7a2146...`, shared by its linked worktrees.
+fn object_path(repo: &Repository, oid: &str) -> PathBuf {
+    common_dir(repo)
+        .join("lfs/objects")
+        .join(&oid[..2])
+        .join(&oid[2..4])
+        .join(oid)
+}
+
+/// Returns the URL of the LFS server of a repository: `lfs.url` if it is set, or the one Git LFS
+/// derives from the URL of the `origin` remote, e.g. `https://github.com/owner/repo.git/info/lfs`
+/// for `git@github.com:owner/repo`.
+///
+/// # Returns
+///
+/// Returns `None` if the repository has neither, or its remote isn't over HTTP(S) or SSH.
+pub fn lfs_endpoint(repo: &Repository) -> Option<String> {
+    let config = repo.config().ok()?;
+    if let Ok(url) = config.get_string("lfs.url") {
+        return Some(url.trim_end_matches('/').to_string());
+    }
+    let remote = repo.find_remote("origin").ok()?;
+    let url = remote.url()?.trim_end_matches('/');
+    let url = if url.starts_with("https://") || url.starts_with("http://") {
+        url.to_string()
+    } else {
+        // ssh://git@host/owner/repo or git@host:owner/repo
+        let rest = url.strip_prefix("ssh://").unwrap_or(url);
+        let rest = rest.split_once('@').map_or(rest, |(_, host)| host);
+        let (host, path) = rest.split_once([':', '/'])?;
+        format!("https://{}/{}", host, path.trim_start_matches('/'))
+    };
+    let url = if url.ends_with(".git") { url } else { format!("{}.git", url) };
+    Some(format!("{}/info/lfs", url))
+}
+
+/// Returns the object a pointer points to, for `--fetch-lfs`.
+///
+/// The object is read from the LFS storage of the repository if `git lfs fetch` got it there.
+/// Otherwise it is downloaded from the LFS server of the repository, see `lfs_endpoint`, with the
+/// batch API, and stored there so that the other commits holding the pointer don't download it
+/// again.
+///
+/// # Returns
+///
+/// Returns `None` if the object is larger than [`LFS_MAX_SIZE`] or isn't text, which is skipped.
+///
+/// # Errors
+///
+/// Returns the reason the object couldn't be downloaded, or didn't match its pointer.
+pub fn load_lfs_object(repo: &Repository, pointer: &LfsPointer) -> Result<Option<Vec<u8>>, String> {
+    if pointer.size > LFS_MAX_SIZE {
+        return Ok(None);
+    }
+    let path = object_path(repo, &pointer.oid);
+    let object = match fs::read(&path) {
+        Ok(object) => object,
+        Err(_) => {
+            let object = download(repo, pointer)?;
+            if sha256_hex(&object) != pointer.oid {
+                return Err("the downloaded object doesn't match the pointer".to_string());
+            }
+            // The storage is only a cache, the scan goes on if it can't be written
+            let _ = store_object(&path, &object);
+            object
+        }
+    };
+    // Binary files, as Git tells them apart
+    let text = !object.iter().take(8000).any(|&byte| byte == 0);
+    Ok(text.then_some(object))
+}
+
+/// Writes an object to the LFS storage of a repository, next to its path and then renamed there,
+/// as Git LFS does.
+fn store_object(path: &Path, object: &[u8]) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let temporary = path.with_extension("tmp");
+    fs::write(&temporary, object)?;
+    fs::rename(&temporary, path)
+}
+
+/// Downloads an object with the basic transfer of the batch API of the LFS server.
+fn download(repo: &Repository, pointer: &LfsPointer) -> Result<Vec<u8>, String> {
+    let endpoint = lfs_endpoint(repo).ok_or("the repository has no LFS server")?;
+    let url = format!("{}/objects/batch", endpoint);
+    let request = json!({
+        "operation": "download",
+        "transfers": ["basic"],
+        "objects": [{ "oid": pointer.oid, "size": pointer.size }],
+    });
+    let headers = [("Accept", LFS_MEDIA_TYPE), ("Content-Type", LFS_MEDIA_TYPE)];
+    let response = send("POST", &url, &headers, Some(request.to_string().as_bytes()))?;
+    if response.status != 200 {
+        return Err(format!("POST {} returned status {}", url, response.status));
+    }
+    let batch: Value = serde_json::from_slice(&response.body).map_err(|err| err.to_string())?;
+    let object = &batch["objects"][0];
+    if let Some(message) = object["error"]["message"].as_str() {
+        return Err(message.to_string());
+    }
+    let action = &object["actions"]["download"];
+    let href = action["href"].as_str().ok_or("the LFS server returned no download link")?;
+    let headers: Vec<(&str, &str)> = action["header"]
+        .as_object()
+        .into_iter()
+        .flatten()
+        .filter_map(|(name, value)| Some((name.as_str(), value.as_str()?)))
+        .collect();
+    let response = send("GET", href, &headers, None)?;
+    if response.status != 200 {
+        return Err(format!("GET {} returned status {}", href, response.status));
+    }
+    Ok(response.body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OID: &str = "4d7a214614ab2935c943f9e0ff69d22eadbb8f32b1258daaa5e2ca24d17e2393";
+
+    #[test]
+    fn test_parse_pointer() {
+        let pointer = format!("version https://git-lfs.github.com/spec/v1\noid sha256:{}\nsize 12345\n", OID);
+        assert_eq!(
+            parse_pointer(pointer.as_bytes()),
+            Some(LfsPointer {
+                oid: OID.to_string(),
+                size: 12345
+            })
+        );
+        assert_eq!(parse_pointer(b"version https://git-lfs.github.com/spec/v1\nsize 1\n"), None);
+        assert_eq!(parse_pointer(b"token = sk_live_1234"), None);
+    }
+
+    #[test]
+    fn test_load_lfs_object() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        assert_eq!(lfs_endpoint(&repo), None);
+        repo.remote("origin", "git@github.com:owner/repo").unwrap();
+        assert_eq!(
+            lfs_endpoint(&repo).as_deref(),
+            Some("https://github.com/owner/repo.git/info/lfs")
+        );
+
+        let object = b"token = sk_live_1234567890abcdef\n";
+        let pointer = LfsPointer {
+            oid: sha256_hex(object),
+            size: object.len() as u64,
+        };
+        let path = object_path(&repo, &pointer.oid);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, object).unwrap();
+        assert_eq!(load_lfs_object(&repo, &pointer).unwrap().as_deref(), Some(&object[..]));
+        fs::write(&path, b"\x89PNG\r\n\x1a\n\0\0").unwrap();
+        assert_eq!(load_lfs_object(&repo, &pointer).unwrap(), None);
+        let large = LfsPointer { size: LFS_MAX_SIZE + 1, ..pointer };
+        assert_eq!(load_lfs_object(&repo, &large).unwrap(), None);
+    }
+}
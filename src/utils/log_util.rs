@@ -0,0 +1,15 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Whether progress, information and warnings are left out, see `--quiet`.
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// Leaves the progress, information and warnings of scans out of the output, or prints them again.
+/// Errors are printed either way.
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+/// Returns whether progress, information and warnings are left out of the output.
+pub fn is_quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
@@ -0,0 +1,209 @@
+use crate::utils::detect_utils::shannon_entropy;
+use crate::utils::dotenv_util::{is_placeholder, is_sensitive_name, DOTENV_MIN_ENTROPY};
+use crate::utils::key_path_util::split_yaml_key;
+use base64::Engine;
+
+/// The rule reported for the values of Kubernetes Secret manifests no rule matches once decoded.
+pub const KUBERNETES_SECRET_RULE: &str = "Kubernetes Secret data";
+
+/// The rule id of [`KUBERNETES_SECRET_RULE`].
+pub const KUBERNETES_SECRET_RULE_ID: &str = "kubernetes-secret-data";
+
+/// The rule reported for credentials in Helm values files.
+pub const HELM_VALUES_RULE: &str = "Credential in Helm values";
+
+/// The rule id of [`HELM_VALUES_RULE`].
+pub const HELM_VALUES_RULE_ID: &str = "helm-values-credential";
+
+/// A scalar value of a YAML file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ManifestValue<'a> {
+    /// The line of the value, from 1.
+    pub line_number: usize,
+
+    /// The column the value starts at, in characters from 1.
+    pub column: usize,
+
+    /// The line of the value.
+    pub line: &'a str,
+
+    /// The key of the value, without the keys of the mappings it is in.
+    pub key: String,
+
+    /// The value, without its quotes.
+    pub value: &'a str,
+}
+
+/// Returns whether a file is a YAML file, which manifests and values files are.
+pub fn is_yaml(path: &str) -> bool {
+    let path = path.to_ascii_lowercase();
+    path.ends_with(".yaml") || path.ends_with(".yml")
+}
+
+/// Returns whether a file is a Helm values file, e.g. `values.yaml` or `values-prod.yaml`.
+pub fn is_helm_values(path: &str) -> bool {
+    let name = path.rsplit(['/', '\\']).next().unwrap_or(path).to_ascii_lowercase();
+    is_yaml(&name) && (name.starts_with("values.") || name.starts_with("values-"))
+}
+
+/// Reads a `key: value` line of YAML, with the value unquoted, if the value is a scalar on the
+/// line.
+fn scalar<'a>(line_number: usize, line: &'a str) -> Option<ManifestValue<'a>> {
+    let content = line.trim_start().trim_start_matches("- ");
+    let (key, value) = split_yaml_key(content)?;
+    if value.is_empty() || value.starts_with(['|', '>', '{', '[', '&', '*', '#']) {
+        return None;
+    }
+    let value = match value.chars().next() {
+        Some(quote @ ('"' | '\'')) => value[1..].split(quote).next().unwrap_or_default(),
+        _ => value.split(" #").next().unwrap_or(value).trim_end(),
+    };
+    // The value is a slice of the line
+    let start = value.as_ptr() as usize - line.as_ptr() as usize;
+    Some(ManifestValue {
+        line_number,
+        column: line[..start].chars().count() + 1,
+        line,
+        key,
+        value,
+    })
+}
+
+/// Returns the values of the `data` of the Kubernetes Secrets in a YAML file, which may hold
+/// several manifests separated by `---`.
+pub fn secret_data(contents: &str) -> Vec<ManifestValue<'_>> {
+    let mut values = Vec::new();
+    for document in documents(contents) {
+        let is_secret = document
+            .iter()
+            .any(|(_, line)| line.trim_end() == "kind: Secret");
+        if !is_secret {
+            continue;
+        }
+        let mut in_data = false;
+        for &(line_number, line) in &document {
+            if !line.starts_with([' ', '\t']) {
+                in_data = line.trim_end() == "data:";
+            } else if in_data {
+                values.extend(scalar(line_number, line));
+            }
+        }
+    }
+    values
+}
+
+/// Splits a YAML file into its documents, with the number of each line.
+fn documents(contents: &str) -> Vec<Vec<(usize, &str)>> {
+    let mut documents = vec![Vec::new()];
+    for (i, line) in contents.lines().enumerate() {
+        if line.trim_end() == "---" {
+            documents.push(Vec::new());
+        } else if let Some(document) = documents.last_mut() {
+            document.push((i + 1, line));
+        }
+    }
+    documents
+}
+
+/// Returns the scalar values of a Helm values file.
+pub fn helm_values(contents: &str) -> Vec<ManifestValue<'_>> {
+    contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim_start().starts_with('#'))
+        .filter_map(|(i, line)| scalar(i + 1, line))
+        .collect()
+}
+
+/// Returns whether a value of a Helm values file looks like a hard-coded credential: its key
+/// looks sensitive, e.g. `adminPassword`, and it isn't a placeholder nor has less entropy than
+/// [`DOTENV_MIN_ENTROPY`], like the values of dotenv files.
+///
+/// Keys naming where a credential is rather than holding it, such as `existingSecret` or
+/// `passwordSecretName`, are left out.
+pub fn is_helm_credential(value: &ManifestValue) -> bool {
+    let key = value.key.to_ascii_uppercase();
+    let reference = key.starts_with("EXISTING")
+        || ["NAME", "REF", "FILE", "PATH", "ENABLED"]
+            .iter()
+            .any(|suffix| key.ends_with(suffix));
+    is_sensitive_name(&key)
+        && !reference
+        && !is_placeholder(value.value)
+        && shannon_entropy(value.value) >= DOTENV_MIN_ENTROPY
+}
+
+/// Decodes a base64 value into text, so that rules can be run on what it encodes.
+///
+/// # Returns
+///
+/// Returns `None` if the value isn't base64, or doesn't encode UTF-8 text.
+pub fn decode_base64(value: &str) -> Option<String> {
+    let bytes = base64::engine::general_purpose::STANDARD.decode(value).ok()?;
+    let text = String::from_utf8(bytes).ok()?;
+    let printable = text
+        .chars()
+        .all(|c| !c.is_control() || c.is_ascii_whitespace());
+    printable.then_some(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secret_data() {
+        let manifests = "\
+apiVersion: v1
+kind: ConfigMap
+data:
+  mode: cHJvZA==
+---
+apiVersion: v1
+kind: Secret
+metadata:
+  name: db
+data:
+  password: \"aHVudGVyMg==\"
+  username: YWRtaW4= # admin
+type: Opaque
+";
+        let values = secret_data(manifests);
+        assert_eq!(
+            values
+                .iter()
+                .map(|value| (value.line_number, value.column, value.key.as_str(), value.value))
+                .collect::<Vec<_>>(),
+            [(11, 14, "password", "aHVudGVyMg=="), (12, 13, "username", "YWRtaW4=")]
+        );
+        assert_eq!(decode_base64(values[0].value).as_deref(), Some("hunter2"));
+        assert_eq!(decode_base64("not base64!"), None);
+        assert_eq!(decode_base64("AAEC"), None);
+    }
+
+    #[test]
+    fn test_helm_values() {
+        assert!(is_helm_values("charts/app/values.yaml"));
+        assert!(is_helm_values("values-prod.yml"));
+        assert!(!is_helm_values("charts/app/Chart.yaml"));
+
+        let values = helm_values(
+            "\
+postgresql:
+  auth:
+    postgresPassword: Xk9mQ2vL7pR4
+    existingSecret: db-credentials
+    password: changeme
+  # password: commented
+image:
+  tag: 1.2.3
+",
+        );
+        let credentials: Vec<&str> = values
+            .iter()
+            .filter(|value| is_helm_credential(value))
+            .map(|value| value.value)
+            .collect();
+        assert_eq!(credentials, ["Xk9mQ2vL7pR4"]);
+    }
+}
@@ -0,0 +1,79 @@
+use std::sync::{Condvar, Mutex};
+
+/// A number of bytes shared between threads, that threads wait for when it is used up.
+///
+/// It bounds the memory held by work that has been produced but not consumed yet: producers
+/// `acquire` the size of what they hand over, and consumers `release` it once they are done.
+pub struct MemoryBudget {
+    limit: usize,
+    used: Mutex<usize>,
+    released: Condvar,
+}
+
+impl MemoryBudget {
+    /// Creates a budget of `limit` bytes.
+    pub fn new(limit: usize) -> Self {
+        MemoryBudget {
+            limit,
+            used: Mutex::new(0),
+            released: Condvar::new(),
+        }
+    }
+
+    /// Waits until `bytes` fit in the budget, then takes them.
+    ///
+    /// A request larger than the whole budget waits until nothing else is held, so that it can't
+    /// wait forever.
+    pub fn acquire(&self, bytes: usize) {
+        let mut used = self.used.lock().unwrap();
+        while *used > 0 && *used + bytes > self.limit {
+            used = self.released.wait(used).unwrap();
+        }
+        *used += bytes;
+    }
+
+    /// Gives back bytes taken with `acquire`.
+    pub fn release(&self, bytes: usize) {
+        let mut used = self.used.lock().unwrap();
+        *used = used.saturating_sub(bytes);
+        self.released.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_memory_budget_blocks_until_released() {
+        let budget = MemoryBudget::new(100);
+        let peak = AtomicUsize::new(0);
+        thread::scope(|s| {
+            for _ in 0..4 {
+                s.spawn(|| {
+                    for _ in 0..10 {
+                        budget.acquire(40);
+                        peak.fetch_max(*budget.used.lock().unwrap(), Ordering::SeqCst);
+                        thread::sleep(Duration::from_millis(1));
+                        budget.release(40);
+                    }
+                });
+            }
+        });
+        assert_eq!(*budget.used.lock().unwrap(), 0);
+        assert!(peak.load(Ordering::SeqCst) <= 80);
+    }
+
+    #[test]
+    fn test_memory_budget_oversized_request() {
+        let budget = MemoryBudget::new(10);
+        budget.acquire(50);
+        assert_eq!(*budget.used.lock().unwrap(), 50);
+        budget.release(50);
+        budget.acquire(5);
+        assert_eq!(*budget.used.lock().unwrap(), 5);
+    }
+}
@@ -0,0 +1,118 @@
+use std::fs::File;
+use std::io;
+use std::ops::Deref;
+
+/// A read-only memory map of a whole file.
+///
+/// The pages of the file are shared with the page cache instead of being copied into buffers.
+/// The file must not be truncated while it is mapped: reading the missing pages would crash the
+/// process.
+///
+/// On platforms other than Unix, the file is read into memory instead.
+pub struct Mmap {
+    #[cfg(unix)]
+    ptr: *mut libc::c_void,
+    #[cfg(unix)]
+    len: usize,
+    #[cfg(not(unix))]
+    data: Vec<u8>,
+}
+
+// The mapping is read-only and owned by the `Mmap`.
+unsafe impl Send for Mmap {}
+unsafe impl Sync for Mmap {}
+
+impl Mmap {
+    /// Maps `file` into memory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the size of the file can't be read or the file can't be mapped.
+    #[cfg(unix)]
+    pub fn map(file: &File) -> io::Result<Mmap> {
+        use std::os::unix::io::AsRawFd;
+
+        let len = usize::try_from(file.metadata()?.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "file too large to map"))?;
+        if len == 0 {
+            // mmap refuses empty mappings
+            return Ok(Mmap {
+                ptr: std::ptr::null_mut(),
+                len,
+            });
+        }
+        let ptr = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                len,
+                libc::PROT_READ,
+                libc::MAP_PRIVATE,
+                file.as_raw_fd(),
+                0,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Mmap { ptr, len })
+    }
+
+    /// Reads `file` into memory, as no memory map is available.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file can't be read.
+    #[cfg(not(unix))]
+    pub fn map(file: &File) -> io::Result<Mmap> {
+        use std::io::Read;
+
+        let mut data = Vec::new();
+        (&*file).read_to_end(&mut data)?;
+        Ok(Mmap { data })
+    }
+}
+
+impl Deref for Mmap {
+    type Target = [u8];
+
+    #[cfg(unix)]
+    fn deref(&self) -> &[u8] {
+        if self.len == 0 {
+            return &[];
+        }
+        unsafe { std::slice::from_raw_parts(self.ptr as *const u8, self.len) }
+    }
+
+    #[cfg(not(unix))]
+    fn deref(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+#[cfg(unix)]
+impl Drop for Mmap {
+    fn drop(&mut self) {
+        if self.len > 0 {
+            unsafe {
+                libc::munmap(self.ptr, self.len);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_mmap() {
+        let mut file = tempfile::tempfile().unwrap();
+        file.write_all(b"token = sk_test_1234567890abcdef\n").unwrap();
+        let map = Mmap::map(&file).unwrap();
+        assert_eq!(&map[..], b"token = sk_test_1234567890abcdef\n");
+
+        let empty = tempfile::tempfile().unwrap();
+        assert!(Mmap::map(&empty).unwrap().is_empty());
+    }
+}
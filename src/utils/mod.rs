@@ -1,2 +1,28 @@
+pub mod archive_util;
+pub mod checkpoint_util;
+pub mod clone_util;
+pub mod compress_util;
+pub mod connection_string_util;
+pub mod crypt_util;
+pub mod date_util;
 pub mod detect_utils;
-pub mod git_util;
\ No newline at end of file
+pub mod dotenv_util;
+pub mod filter_util;
+pub mod generated_util;
+pub mod git_util;
+pub mod http_util;
+pub mod key_path_util;
+pub mod lfs_util;
+pub mod log_util;
+pub mod manifest_util;
+pub mod memory_util;
+pub mod mmap_util;
+pub mod notebook_util;
+pub mod private_key_util;
+pub mod rule_packs;
+pub mod rule_regex;
+pub mod score_util;
+pub mod signal_util;
+pub mod terraform_util;
+#[cfg(any(feature = "upload-s3", feature = "upload-gcs", feature = "upload-azure"))]
+pub mod upload_util;
@@ -0,0 +1,119 @@
+use serde_json::Value;
+
+/// Returns whether a file is a Jupyter notebook, by its extension.
+pub fn is_notebook(path: &str) -> bool {
+    path.to_ascii_lowercase().ends_with(".ipynb")
+}
+
+/// Returns the text of the sources and outputs of the cells of a Jupyter notebook, each with
+/// where it is in the notebook, e.g. `cells/2/source` or `cells/2/outputs/0/data/text/plain`,
+/// cells and outputs being counted from 0 as in the JSON of the notebook.
+///
+/// The text is unescaped from the JSON, so that secrets match as they were typed and line numbers
+/// count from the start of the source or output. Images and other binary outputs are left out.
+///
+/// # Returns
+///
+/// Returns `None` if the contents aren't a notebook in the nbformat 4 format.
+pub fn notebook_cells(contents: &str) -> Option<Vec<(String, String)>> {
+    let notebook: Value = serde_json::from_str(contents).ok()?;
+    let cells = notebook.get("cells")?.as_array()?;
+    let mut texts = Vec::new();
+    for (i, cell) in cells.iter().enumerate() {
+        if let Some(source) = join_text(&cell["source"]) {
+            texts.push((format!("cells/{}/source", i), source));
+        }
+        let outputs = cell["outputs"].as_array().map(Vec::as_slice).unwrap_or_default();
+        for (j, output) in outputs.iter().enumerate() {
+            let location = format!("cells/{}/outputs/{}", i, j);
+            // Stream outputs, e.g. what was printed
+            if let Some(text) = join_text(&output["text"]) {
+                texts.push((format!("{}/text", location), text));
+            }
+            // Results and displays, in every format they were rendered to
+            if let Some(data) = output["data"].as_object() {
+                for (mime, value) in data.iter().filter(|(mime, _)| is_text_mime(mime)) {
+                    let text = match value {
+                        Value::Object(_) => Some(value.to_string()),
+                        _ => join_text(value),
+                    };
+                    if let Some(text) = text {
+                        texts.push((format!("{}/data/{}", location, mime), text));
+                    }
+                }
+            }
+            // The lines of tracebacks don't end with line breaks
+            if let Some(traceback) = output["traceback"].as_array() {
+                let lines: Vec<&str> = traceback.iter().filter_map(Value::as_str).collect();
+                texts.push((format!("{}/traceback", location), lines.join("\n")));
+            }
+        }
+    }
+    Some(texts)
+}
+
+/// Reads multiline text, stored as a string or as a list of lines ending with their line breaks.
+fn join_text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(text) => Some(text.clone()),
+        Value::Array(lines) => Some(lines.iter().filter_map(Value::as_str).collect()),
+        _ => None,
+    }
+}
+
+fn is_text_mime(mime: &str) -> bool {
+    mime.starts_with("text/")
+        || mime.ends_with("json")
+        || mime.ends_with("javascript")
+        || mime.ends_with("xml")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_notebook_cells() {
+        let notebook = r##"{
+            "nbformat": 4,
+            "cells": [
+                { "cell_type": "markdown", "source": "# Setup" },
+                {
+                    "cell_type": "code",
+                    "source": ["import os\n", "KEY = \"sk_live_\u0031234\"\n"],
+                    "outputs": [
+                        { "output_type": "stream", "name": "stdout", "text": ["connected\n"] },
+                        { "output_type": "execute_result", "data": {
+                            "text/plain": ["{'token': 'abc'}"],
+                            "image/png": "iVBORw0KGgo="
+                        }},
+                        { "output_type": "error", "traceback": ["Traceback", "KeyError: 'x'"] }
+                    ]
+                }
+            ]
+        }"##;
+        assert_eq!(
+            notebook_cells(notebook).unwrap(),
+            [
+                ("cells/0/source".to_string(), "# Setup".to_string()),
+                (
+                    "cells/1/source".to_string(),
+                    "import os\nKEY = \"sk_live_1234\"\n".to_string()
+                ),
+                ("cells/1/outputs/0/text".to_string(), "connected\n".to_string()),
+                (
+                    "cells/1/outputs/1/data/text/plain".to_string(),
+                    "{'token': 'abc'}".to_string()
+                ),
+                (
+                    "cells/1/outputs/2/traceback".to_string(),
+                    "Traceback\nKeyError: 'x'".to_string()
+                ),
+            ]
+        );
+        assert!(notebook_cells("print('not a notebook')").is_none());
+        assert!(notebook_cells(r#"{"worksheets": []}"#).is_none());
+        assert!(is_notebook("analysis/Model.IPYNB"));
+        assert!(!is_notebook("analysis/model.py"));
+    }
+}
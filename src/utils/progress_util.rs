@@ -0,0 +1,152 @@
+use chrono::Local;
+use serde_json::{json, Value};
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How often, at most, the progress of a phase is written. A new phase is written at once.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Whether progress events are written to stderr, see `--progress-format json`.
+static JSON: AtomicBool = AtomicBool::new(false);
+
+/// The progress of the scan that is running.
+static PROGRESS: Mutex<Progress> = Mutex::new(Progress::new());
+
+/// Where a scan is at, as written in its progress events.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Progress {
+    /// `clone`, `load`, `scan`, `report` or `done`.
+    pub phase: &'static str,
+
+    pub commits_done: usize,
+
+    /// The number of commits to scan, unknown for the working tree and the packfiles.
+    pub commits_total: Option<usize>,
+
+    /// The file scanned last.
+    pub current_file: Option<String>,
+
+    /// The number of leaks found so far, before the allowlists of nested configs,
+    /// `.gitleaksignore` and the filters drop some. The `done` event has those reported.
+    pub findings: usize,
+
+    /// When the last event was written.
+    written_at: Option<Instant>,
+}
+
+impl Progress {
+    const fn new() -> Self {
+        Progress {
+            phase: "",
+            commits_done: 0,
+            commits_total: None,
+            current_file: None,
+            findings: 0,
+            written_at: None,
+        }
+    }
+
+    /// Returns the event of the progress, a JSON object written on a line of its own.
+    pub fn event(&self, time: &str) -> Value {
+        json!({
+            "time": time,
+            "phase": self.phase,
+            "commits_done": self.commits_done,
+            "commits_total": self.commits_total,
+            "current_file": self.current_file,
+            "findings": self.findings,
+        })
+    }
+}
+
+/// Writes the progress of scans to stderr as JSON lines, or stops writing it, starting over from
+/// no progress.
+pub fn set_json_progress(json: bool) {
+    JSON.store(json, Ordering::Relaxed);
+    *PROGRESS.lock().unwrap() = Progress::new();
+}
+
+/// Returns whether the progress of scans is written as JSON lines.
+pub fn is_json_progress() -> bool {
+    JSON.load(Ordering::Relaxed)
+}
+
+/// Updates the progress, writing it if the scan entered a new phase or the last event is older
+/// than `PROGRESS_INTERVAL`. Does nothing unless the progress is written.
+fn update(change: impl FnOnce(&mut Progress)) {
+    if !is_json_progress() {
+        return;
+    }
+    let mut progress = PROGRESS.lock().unwrap();
+    let phase = progress.phase;
+    change(&mut progress);
+    let due = progress.written_at.is_none_or(|written_at| written_at.elapsed() >= PROGRESS_INTERVAL);
+    if progress.phase != phase || due {
+        progress.written_at = Some(Instant::now());
+        // The progress is only informative, so failing to write it doesn't stop the scan
+        let _ = writeln!(io::stderr().lock(), "{}", progress.event(&Local::now().to_rfc3339()));
+    }
+}
+
+/// Enters a phase of the scan, e.g. `scan`.
+pub fn set_phase(phase: &'static str) {
+    update(|progress| progress.phase = phase);
+}
+
+/// Sets the number of commits the scan covers.
+pub fn set_commits_total(total: usize) {
+    update(|progress| progress.commits_total = Some(total));
+}
+
+/// Records that a file is being scanned.
+pub fn scanning_file(path: &str) {
+    update(|progress| progress.current_file = Some(path.to_string()));
+}
+
+/// Records that commits were scanned, with the leaks found in them.
+pub fn commits_scanned(commits: usize, findings: usize) {
+    update(|progress| {
+        progress.commits_done += commits;
+        progress.findings += findings;
+    });
+}
+
+/// Records leaks found outside of commits, e.g. in the working tree.
+pub fn leaks_found(findings: usize) {
+    update(|progress| progress.findings += findings);
+}
+
+/// Ends the scan with the number of leaks reported.
+pub fn finish(findings: usize) {
+    update(|progress| {
+        progress.phase = "done";
+        progress.current_file = None;
+        progress.findings = findings;
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_progress_event() {
+        let progress = Progress {
+            phase: "scan",
+            commits_done: 3,
+            commits_total: Some(10),
+            current_file: Some("src/config.rs".to_string()),
+            findings: 2,
+            ..Progress::new()
+        };
+        assert_eq!(
+            progress.event("2024-06-01T12:00:00+00:00").to_string(),
+            r#"{"commits_done":3,"commits_total":10,"current_file":"src/config.rs","findings":2,"phase":"scan","time":"2024-06-01T12:00:00+00:00"}"#
+        );
+        let event = Progress::new().event("2024-06-01T12:00:00+00:00");
+        assert_eq!(event["commits_total"], Value::Null);
+        assert_eq!(event["current_file"], Value::Null);
+    }
+}
@@ -0,0 +1,362 @@
+use regex::{Match, Regex, RegexBuilder};
+use regex_syntax::hir::literal::{ExtractKind, Extractor};
+
+/// The compiled regex of a rule.
+///
+/// Patterns the `regex` crate accepts run on it as they are. The crate rejects look-around, which
+/// many published gitleaks rules use to check what comes right before or after a secret, so a
+/// look-behind at the start of a pattern and a look-ahead at its end are split off and checked
+/// separately around every match of the rest of the pattern:
+///
+/// * `(?<=a)b` and `(?<!a)b` match `b` if the text before it does (not) end with a match of `a`.
+/// * `a(?=b)` and `a(?!b)` match `a` if the text after it does (not) start with a match of `b`.
+///
+/// Unlike a backtracking engine, a match that fails the check isn't retried with a shorter or
+/// longer match at the same position. Look-around anywhere else in the pattern is still rejected.
+#[derive(Debug, Clone)]
+pub struct RuleRegex {
+    regex: Regex,
+    behind: Option<Assertion>,
+    ahead: Option<Assertion>,
+}
+
+/// A look-around that was split off a pattern.
+#[derive(Debug, Clone)]
+struct Assertion {
+    regex: Regex,
+    negated: bool,
+}
+
+impl Assertion {
+    fn holds(&self, text: &str) -> bool {
+        self.regex.is_match(text) != self.negated
+    }
+}
+
+impl RuleRegex {
+    /// Compiles a pattern, using no more than `size_limit` bytes for each regex it is made of.
+    ///
+    /// # Errors
+    ///
+    /// Returns the reason the pattern can't be compiled.
+    pub fn new(pattern: &str, size_limit: usize) -> Result<Self, String> {
+        let err = match build(pattern, size_limit) {
+            Ok(regex) => return Ok(RuleRegex { regex, behind: None, ahead: None }),
+            Err(err) => err,
+        };
+        if !matches!(err, regex::Error::Syntax(_)) {
+            return Err(describe(err));
+        }
+        split_lookaround(pattern, size_limit)
+            .unwrap_or_else(|| Err(describe(err)))
+    }
+
+    /// Returns whether look-around is checked outside of the `regex` crate.
+    pub fn uses_lookaround(&self) -> bool {
+        self.behind.is_some() || self.ahead.is_some()
+    }
+
+    /// Returns the successive non-overlapping matches in `text`.
+    pub fn find_iter<'r, 't>(&'r self, text: &'t str) -> impl Iterator<Item = Match<'t>> + 'r
+    where
+        't: 'r,
+    {
+        self.regex.find_iter(text).filter(move |matched| {
+            self.behind
+                .as_ref()
+                .is_none_or(|behind| behind.holds(&text[..matched.start()]))
+                && self
+                    .ahead
+                    .as_ref()
+                    .is_none_or(|ahead| ahead.holds(&text[matched.end()..]))
+        })
+    }
+}
+
+fn build(pattern: &str, size_limit: usize) -> Result<Regex, regex::Error> {
+    RegexBuilder::new(pattern).size_limit(size_limit).build()
+}
+
+fn describe(err: regex::Error) -> String {
+    match err {
+        regex::Error::Syntax(message) => message,
+        regex::Error::CompiledTooBig(limit) => format!(
+            "the compiled regex is larger than {} bytes, try smaller counted repetitions",
+            limit
+        ),
+        _ => err.to_string(),
+    }
+}
+
+/// Splits a leading look-behind and a trailing look-ahead off a pattern.
+///
+/// Returns `None` if the pattern has neither, or look-around is left in what remains.
+fn split_lookaround(pattern: &str, size_limit: usize) -> Option<Result<RuleRegex, String>> {
+    // Inline flags at the very start apply to the whole pattern, and so to every part of it.
+    let (flags, mut body) = match leading_flags(pattern) {
+        Some(end) => pattern.split_at(end),
+        None => ("", pattern),
+    };
+
+    let mut behind = None;
+    if let Some((prefix, negated)) = [("(?<=", false), ("(?<!", true)]
+        .into_iter()
+        .find(|(prefix, _)| body.starts_with(prefix))
+    {
+        let end = group_end(body)?;
+        behind = Some((&body[prefix.len()..end], negated));
+        body = &body[end + 1..];
+    }
+
+    let mut ahead = None;
+    if let Some(start) = last_group_start(body) {
+        if let Some((prefix, negated)) = [("(?=", false), ("(?!", true)]
+            .into_iter()
+            .find(|(prefix, _)| body[start..].starts_with(prefix))
+        {
+            ahead = Some((&body[start + prefix.len()..body.len() - 1], negated));
+            body = &body[..start];
+        }
+    }
+
+    // An alternation would leave the assertion applying to one branch only.
+    if (behind.is_none() && ahead.is_none()) || has_top_level_alternation(body) {
+        return None;
+    }
+
+    let assertion = |pattern: String, negated: bool| {
+        build(&pattern, size_limit)
+            .map(|regex| Assertion { regex, negated })
+            .map_err(describe)
+    };
+    let compile = || -> Result<RuleRegex, String> {
+        Ok(RuleRegex {
+            regex: build(&format!("{}{}", flags, body), size_limit).map_err(describe)?,
+            behind: behind
+                .map(|(inner, negated)| assertion(format!(r"{}(?:{})\z", flags, inner), negated))
+                .transpose()?,
+            ahead: ahead
+                .map(|(inner, negated)| assertion(format!(r"{}\A(?:{})", flags, inner), negated))
+                .transpose()?,
+        })
+    };
+    Some(compile())
+}
+
+/// Returns the end of a `(?flags)` group at the start of the pattern.
+fn leading_flags(pattern: &str) -> Option<usize> {
+    let rest = pattern.strip_prefix("(?")?;
+    let len = rest.find(')')?;
+    if len > 0 && rest[..len].chars().all(|c| c.is_ascii_alphabetic() || c == '-') {
+        Some(len + 3)
+    } else {
+        None
+    }
+}
+
+/// Walks a pattern, calling `on_char` with the byte offset, the character and the depth of
+/// parentheses for everything outside of escapes and character classes.
+fn walk(pattern: &str, mut on_char: impl FnMut(usize, char, usize) -> bool) {
+    let mut chars = pattern.char_indices().peekable();
+    let mut depth = 0;
+    let mut class_depth = 0;
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '[' => {
+                class_depth += 1;
+                // A `]` right after the opening bracket is a literal.
+                if let Some(&(_, '^')) = chars.peek() {
+                    chars.next();
+                }
+                if let Some(&(_, ']')) = chars.peek() {
+                    chars.next();
+                }
+            }
+            ']' if class_depth > 0 => class_depth -= 1,
+            _ if class_depth > 0 => {}
+            '(' => {
+                if !on_char(i, c, depth) {
+                    return;
+                }
+                depth += 1;
+            }
+            ')' => {
+                depth = depth.saturating_sub(1);
+                if !on_char(i, c, depth) {
+                    return;
+                }
+            }
+            _ => {
+                if !on_char(i, c, depth) {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// Returns the offset of the `)` closing the group the pattern starts with.
+fn group_end(pattern: &str) -> Option<usize> {
+    let mut end = None;
+    walk(pattern, |i, c, depth| {
+        if c == ')' && depth == 0 {
+            end = Some(i);
+            return false;
+        }
+        true
+    });
+    end
+}
+
+/// Returns the offset of the `(` opening the group the pattern ends with, if it ends with one.
+fn last_group_start(pattern: &str) -> Option<usize> {
+    if !pattern.ends_with(')') {
+        return None;
+    }
+    let mut start = None;
+    let mut last = None;
+    walk(pattern, |i, c, depth| {
+        if depth == 0 {
+            if c == '(' {
+                start = Some(i);
+            }
+            last = Some((i, c));
+        }
+        true
+    });
+    // The closing parenthesis must not be escaped, i.e. seen by the walk.
+    match last {
+        Some((i, ')')) if i == pattern.len() - 1 => start,
+        _ => None,
+    }
+}
+
+fn has_top_level_alternation(pattern: &str) -> bool {
+    let mut found = false;
+    walk(pattern, |_, c, depth| {
+        found = c == '|' && depth == 0;
+        !found
+    });
+    found
+}
+
+/// Returns at most `max` literals one of which every match of a pattern contains, e.g.
+/// `["gho_", "ghp_"]` for `gh[po]_[0-9a-zA-Z]{36}`, to look for before running the pattern.
+///
+/// The literals are passed through `normalize`, e.g. to case fold them, then shortened until no
+/// more than `max` of them are left, e.g. to `ACME_` for `ACME_[0-9]{6}`. The literals the matches
+/// start with are taken, or those they end with when the shortest of them is longer.
+///
+/// # Returns
+///
+/// Returns an empty list if the pattern can't be parsed, e.g. because it uses look-around, or if
+/// a match can start and end without any literal, e.g. for `[a-z]+=\w+`.
+pub fn required_literals(pattern: &str, max: usize, normalize: impl Fn(&str) -> String) -> Vec<String> {
+    // Enough for every casing of the words of case-insensitive patterns, e.g. 256 for `password`
+    const MAX_EXTRACTED: usize = 4096;
+
+    let Ok(hir) = regex_syntax::parse(pattern) else {
+        return Vec::new();
+    };
+    // On a tie, the prefixes are kept, as they come last
+    [ExtractKind::Suffix, ExtractKind::Prefix]
+        .into_iter()
+        .filter_map(|kind| {
+            let seq = Extractor::new()
+                .kind(kind.clone())
+                .limit_total(MAX_EXTRACTED)
+                .extract(&hir);
+            let mut literals = seq
+                .literals()?
+                .iter()
+                .map(|literal| Some(normalize(std::str::from_utf8(literal.as_bytes()).ok()?)))
+                .collect::<Option<Vec<String>>>()?;
+            literals.sort();
+            literals.dedup();
+            let mut length = literals.iter().map(|literal| literal.chars().count()).max()?;
+            while literals.len() > max && length > 1 {
+                length -= 1;
+                for literal in &mut literals {
+                    let count = literal.chars().count();
+                    if count > length {
+                        *literal = match kind {
+                            ExtractKind::Suffix => literal.chars().skip(count - length).collect(),
+                            _ => literal.chars().take(length).collect(),
+                        };
+                    }
+                }
+                literals.sort();
+                literals.dedup();
+            }
+            let shortest = literals.iter().map(|literal| literal.chars().count()).min()?;
+            (literals.len() <= max && shortest > 0).then_some((shortest, literals))
+        })
+        .max_by_key(|(shortest, _)| *shortest)
+        .map(|(_, literals)| literals)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIMIT: usize = 1 << 20;
+
+    #[test]
+    fn test_required_literals() {
+        let literals = |pattern| required_literals(pattern, 8, str::to_string);
+        assert_eq!(literals(r"gh[po]_[0-9a-zA-Z]{36}"), ["gho_", "ghp_"]);
+        assert_eq!(literals(r"[0-9a-f]{32}\.apps\.googleusercontent\.com"), [".apps.googleusercontent.com"]);
+        assert_eq!(literals(r"ACME_[0-9]{6}"), ["ACME_"]);
+        assert!(literals(r"[a-z]+=\w+").is_empty());
+        assert!(literals(r"(?<=key=)[a-z]+").is_empty());
+        // Every casing of the words of case-insensitive patterns, e.g. `PassWord`, before normalizing
+        let folded = required_literals(r"(?i)(?:token|password)[a-z]*=", 8, |literal| literal.to_ascii_lowercase());
+        assert!(folded.contains(&"password".to_string()) && folded.contains(&"token".to_string()));
+    }
+
+    fn matches<'t>(regex: &RuleRegex, text: &'t str) -> Vec<&'t str> {
+        regex.find_iter(text).map(|m| m.as_str()).collect()
+    }
+
+    #[test]
+    fn test_rule_regex_without_lookaround() {
+        let regex = RuleRegex::new(r"key_[a-z]+", LIMIT).unwrap();
+        assert!(!regex.uses_lookaround());
+        assert_eq!(matches(&regex, "key_ab key_cd"), vec!["key_ab", "key_cd"]);
+    }
+
+    #[test]
+    fn test_rule_regex_lookahead() {
+        let regex = RuleRegex::new(r"(?i)token_[a-z0-9]+(?=\s|$)", LIMIT).unwrap();
+        assert!(regex.uses_lookaround());
+        assert_eq!(matches(&regex, "TOKEN_ab1 token_x;"), vec!["TOKEN_ab1"]);
+
+        let regex = RuleRegex::new(r"[0-9]{4}(?![0-9a-f])", LIMIT).unwrap();
+        assert_eq!(matches(&regex, "1234 5678abcd 9012"), vec!["1234", "9012"]);
+    }
+
+    #[test]
+    fn test_rule_regex_lookbehind() {
+        let regex = RuleRegex::new(r"(?<=secret=)[a-z]+", LIMIT).unwrap();
+        assert_eq!(matches(&regex, "secret=abc public=def"), vec!["abc"]);
+
+        let regex = RuleRegex::new(r"(?<![a-z(])sk_[a-z]+(?=\))", LIMIT).unwrap();
+        assert_eq!(matches(&regex, "x(sk_a) (sk_b) sk_c)"), vec!["sk_c"]);
+
+        let regex = RuleRegex::new(r"(?<=^|\s)[0-9]+", LIMIT).unwrap();
+        assert_eq!(matches(&regex, "12 a34 56"), vec!["12", "56"]);
+    }
+
+    #[test]
+    fn test_rule_regex_rejects_other_lookaround() {
+        let err = RuleRegex::new(r"a(?=b)c", LIMIT).unwrap_err();
+        assert!(err.contains("look-around"));
+        assert!(RuleRegex::new(r"(?<=a)b|c", LIMIT).is_err());
+        assert!(RuleRegex::new(r"a\(?=b\)", LIMIT).is_ok());
+        assert!(RuleRegex::new(r"a[(?=b)]", LIMIT).is_ok());
+    }
+}
@@ -0,0 +1,109 @@
+use crate::models::Leak;
+use crate::utils::detect_utils::shannon_entropy;
+use crate::utils::dotenv_util::{is_dotenv_file, is_dotenv_template};
+use chrono::{DateTime, Duration, FixedOffset};
+
+/// The entropy, in bits per character, at which a secret gets the whole entropy part of its score.
+const FULL_SCORE_ENTROPY: f64 = 5.0;
+
+/// The directories and names of files that only tests, examples and documentation read.
+const TEST_PATH_PARTS: &[&str] = &[
+    "test", "tests", "spec", "specs", "__tests__", "fixtures", "fixture", "testdata", "mocks", "mock", "examples",
+    "example", "samples", "sample", "docs",
+];
+
+/// The directories and extensions of the files a deployed application reads its configuration from.
+const CONFIG_PATH_PARTS: &[&str] = &["config", "configs", "conf", "deploy", "deployment", "k8s", "helm", "prod", "production"];
+const CONFIG_EXTENSIONS: &[&str] = &[
+    ".yaml", ".yml", ".json", ".toml", ".ini", ".properties", ".conf", ".cfg", ".tf", ".tfvars", ".xml",
+];
+
+/// Returns how urgent a leak is, from 0 to 100, by combining what is known about it:
+///
+/// - the severity of its rule, up to 40: 40 for `critical`, 32 for `high`, 24 for `medium`, 16 for
+///   `low`, 8 for `info` and 20 for rules without a severity;
+/// - the entropy of the secret, up to 20 at 5 bits per character, as random secrets are more
+///   likely to be real than words;
+/// - the file it is in, 20 for configuration files and dotenv files, 0 for tests, fixtures,
+///   examples and documentation, and 10 for other files;
+/// - whether it is still there, 10 if it is in the scanned working tree or at the tip of the
+///   scanned branch;
+/// - how recent its commit is, 10 within 30 days of `now` or outside commits, 5 within a year.
+pub fn score_leak(leak: &Leak, now: DateTime<FixedOffset>) -> u32 {
+    let severity = match leak.severity.as_deref().map(str::to_ascii_lowercase).as_deref() {
+        Some("critical") => 40,
+        Some("high") => 32,
+        Some("medium") => 24,
+        Some("low") => 16,
+        Some("info") => 8,
+        _ => 20,
+    };
+    let entropy = (shannon_entropy(&leak.offender) / FULL_SCORE_ENTROPY).min(1.0) * 20.0;
+    let present = if leak.commit.is_empty() || leak.present_in_head { 10 } else { 0 };
+    let date = DateTime::parse_from_str(&leak.date, "%Y-%m-%d %H:%M:%S%.f %:z").ok();
+    let recency = match date.map(|date| now - date) {
+        None => 10,
+        Some(age) if age <= Duration::days(30) => 10,
+        Some(age) if age <= Duration::days(365) => 5,
+        Some(_) => 0,
+    };
+    severity + entropy.round() as u32 + file_score(&leak.file) + present + recency
+}
+
+/// Returns the part of the score of a leak that comes from the file it is in, see [`score_leak`].
+fn file_score(path: &str) -> u32 {
+    let path = path.to_ascii_lowercase();
+    let mut parts = path.split(['/', '\\']).filter(|part| !part.is_empty());
+    let name = path.rsplit(['/', '\\']).next().unwrap_or_default();
+    let stem = name.split('.').next().unwrap_or_default();
+    let test = parts.clone().any(|part| TEST_PATH_PARTS.contains(&part))
+        || ["_test.", ".test.", ".spec.", "_spec.", "test_"].iter().any(|marker| name.contains(marker))
+        || is_dotenv_template(&path);
+    if test {
+        return 0;
+    }
+    let config = is_dotenv_file(&path)
+        || parts.any(|part| CONFIG_PATH_PARTS.contains(&part))
+        || CONFIG_EXTENSIONS.iter().any(|extension| name.ends_with(extension))
+        || ["settings", "secrets", "credentials"].contains(&stem);
+    if config {
+        20
+    } else {
+        10
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_score_leak() {
+        let now = DateTime::parse_from_rfc3339("2024-06-01T12:00:00+00:00").unwrap();
+        let leak = Leak {
+            offender: "wJalrXUtnFEMI/K7MDENG/bPxRfiCYEXAMPLEKEY".to_string(),
+            file: "/deploy/prod.yaml".to_string(),
+            severity: Some("critical".to_string()),
+            commit: "9fceb02".to_string(),
+            date: "2024-05-20 09:30:00 +00:00".to_string(),
+            present_in_head: true,
+            ..Default::default()
+        };
+        // 40 + 19 for 4.66 bits per character + 20 + 10 + 10
+        assert_eq!(score_leak(&leak, now), 99);
+
+        let fixture = Leak {
+            offender: "aaaa".to_string(),
+            file: "/tests/fixtures/keys.py".to_string(),
+            severity: Some("low".to_string()),
+            date: "2020-01-01 00:00:00 +00:00".to_string(),
+            present_in_head: false,
+            ..leak.clone()
+        };
+        assert_eq!(score_leak(&fixture, now), 16);
+        assert!(score_leak(&Leak { file: "/src/app.py".to_string(), ..fixture.clone() }, now) > 16);
+        assert_eq!(file_score("/.env.example"), 0);
+        assert_eq!(file_score("/.env"), 20);
+        assert_eq!(file_score("/src/main.rs"), 10);
+    }
+}
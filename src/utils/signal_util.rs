@@ -0,0 +1,74 @@
+use crate::utils::clone_util::remove_active_clones;
+use crate::utils::log_util::is_quiet;
+use chrono::Local;
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+
+/// Whether a signal stops the scan that is running instead of exiting, see [`stop_gracefully`].
+static GRACEFUL: AtomicBool = AtomicBool::new(false);
+
+/// The exit status of the signal that cancelled the scan, or 0 if none did.
+static CANCELLED: AtomicI32 = AtomicI32::new(0);
+
+/// Makes the next SIGINT or SIGTERM stop the scan that is running, which then reports what it
+/// found so far, instead of exiting at once.
+pub fn stop_gracefully() {
+    GRACEFUL.store(true, Ordering::Relaxed);
+}
+
+/// Cancels the scan that is running, as a signal with the given exit status does.
+pub fn cancel(status: i32) {
+    CANCELLED.store(status, Ordering::Relaxed);
+}
+
+/// Returns whether the scan that is running was cancelled. Scans check it between commits and
+/// repositories, and stop with the results they have.
+pub fn is_cancelled() -> bool {
+    CANCELLED.load(Ordering::Relaxed) != 0
+}
+
+/// Returns the exit status of the signal that cancelled the scan, if one did.
+pub fn cancel_status() -> Option<i32> {
+    Some(CANCELLED.load(Ordering::Relaxed)).filter(|status| *status != 0)
+}
+
+/// Waits for a SIGINT or SIGTERM, Ctrl-C outside Unix, with the exit status of the signal.
+async fn next_signal() -> Option<i32> {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        let (Ok(mut interrupt), Ok(mut terminate)) =
+            (signal(SignalKind::interrupt()), signal(SignalKind::terminate()))
+        else {
+            return None;
+        };
+        tokio::select! {
+            _ = interrupt.recv() => Some(130),
+            _ = terminate.recv() => Some(143),
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        tokio::signal::ctrl_c().await.ok().map(|_| 130)
+    }
+}
+
+/// Handles SIGINT and SIGTERM, Ctrl-C outside Unix. After [`stop_gracefully`], the first signal
+/// cancels the scan, which stops and writes its partial results; otherwise, or on a second
+/// signal, the clones being written or scanned are deleted and the process exits with the status
+/// of the signal.
+pub async fn handle_signals() {
+    while let Some(status) = next_signal().await {
+        if GRACEFUL.load(Ordering::Relaxed) && !is_cancelled() {
+            cancel(status);
+            if !is_quiet() {
+                eprintln!(
+                    "\x1b[38;5;208m[WARN]\x1b[0m[{}] Stopping the scan and writing the partial results, interrupt again to exit now",
+                    Local::now().format("%Y-%m-%d %H:%M:%S"),
+                );
+            }
+            continue;
+        }
+        remove_active_clones();
+        std::process::exit(status);
+    }
+}
@@ -0,0 +1,117 @@
+use crate::errors::CustomError;
+use crate::utils::crypt_util;
+use std::error::Error;
+use std::path::Path;
+use std::process::Command;
+
+/// Returns the command that copies a file to object storage, with the CLI of the provider:
+///
+/// * `s3://bucket/key` runs `aws s3 cp`, with the `upload-s3` feature.
+/// * `gs://bucket/object` runs `gcloud storage cp`, with the `upload-gcs` feature.
+/// * `az://account/container/blob` runs `az storage blob upload`, signed in with `--auth-mode login`,
+///   with the `upload-azure` feature.
+///
+/// A URL ending in `/` is a prefix the file name is appended to. The CLIs find their credentials
+/// as usual, e.g. in the environment of the CI runner.
+///
+/// # Errors
+///
+/// Returns `CustomError::UnsupportedUploadUrl` if the URL has another scheme, one whose feature
+/// isn't built, or no object name.
+pub fn upload_command(file_path: &str, url: &str) -> Result<Command, CustomError> {
+    let unsupported = || CustomError::UnsupportedUploadUrl { url: url.to_string() };
+    let (scheme, rest) = url.split_once("://").ok_or_else(unsupported)?;
+    let name = Path::new(file_path)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .ok_or_else(unsupported)?;
+    let rest = match rest.strip_suffix('/') {
+        Some(prefix) => format!("{}/{}", prefix, name),
+        None => rest.to_string(),
+    };
+    let (bucket, object) = rest.split_once('/').ok_or_else(unsupported)?;
+    if bucket.is_empty() || object.is_empty() {
+        return Err(unsupported());
+    }
+
+    let destination = format!("{}://{}", scheme, rest);
+    let mut command;
+    match scheme {
+        #[cfg(feature = "upload-s3")]
+        "s3" => {
+            command = Command::new("aws");
+            command.args(["s3", "cp", "--only-show-errors", file_path, &destination]);
+        }
+        #[cfg(feature = "upload-gcs")]
+        "gs" => {
+            command = Command::new("gcloud");
+            command.args(["storage", "cp", "--quiet", file_path, &destination]);
+        }
+        #[cfg(feature = "upload-azure")]
+        "az" => {
+            let (container, blob) = object.split_once('/').ok_or_else(unsupported)?;
+            if container.is_empty() || blob.is_empty() {
+                return Err(unsupported());
+            }
+            command = Command::new("az");
+            command.args(["storage", "blob", "upload", "--only-show-errors", "--overwrite"]);
+            command.args(["--auth-mode", "login", "--account-name", bucket]);
+            command.args(["--container-name", container, "--name", blob, "--file", file_path]);
+        }
+        _ => return Err(unsupported()),
+    }
+    Ok(command)
+}
+
+/// Uploads a report to object storage, see [`upload_command`].
+///
+/// # Errors
+///
+/// Returns `CustomError::UnsupportedUploadUrl` for URLs that can't be uploaded to, and
+/// `CustomError::ReportToolError` if the CLI can't be run or fails.
+pub fn upload(file_path: &str, url: &str) -> Result<(), Box<dyn Error>> {
+    let mut command = upload_command(file_path, url)?;
+    crypt_util::run(&mut command, None)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(file_path: &str, url: &str) -> Vec<String> {
+        let command = upload_command(file_path, url).unwrap();
+        std::iter::once(command.get_program())
+            .chain(command.get_args())
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    #[test]
+    fn test_upload_command() {
+        #[cfg(feature = "upload-s3")]
+        assert_eq!(
+            args("out/leaks.json", "s3://reports/nightly/leaks.json"),
+            ["aws", "s3", "cp", "--only-show-errors", "out/leaks.json", "s3://reports/nightly/leaks.json"]
+        );
+        #[cfg(feature = "upload-gcs")]
+        assert_eq!(
+            args("out/leaks.json", "gs://reports/nightly/").last().unwrap(),
+            "gs://reports/nightly/leaks.json"
+        );
+        #[cfg(feature = "upload-azure")]
+        {
+            let az = args("leaks.json", "az://acct/reports/nightly/leaks.json");
+            assert_eq!(az[0], "az");
+            assert!(az.windows(2).any(|pair| pair == ["--container-name", "reports"]));
+            assert!(az.windows(2).any(|pair| pair == ["--name", "nightly/leaks.json"]));
+        }
+
+        assert!(upload_command("leaks.json", "https://example.com/leaks.json").is_err());
+        assert!(upload_command("leaks.json", "s3://reports").is_err());
+        assert!(upload_command("leaks.json", "az://acct/reports").is_err());
+        // Providers whose feature isn't built are refused like unknown schemes
+        #[cfg(not(feature = "upload-s3"))]
+        assert!(upload_command("leaks.json", "s3://reports/leaks.json").is_err());
+    }
+}
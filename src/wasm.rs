@@ -0,0 +1,65 @@
+//! WebAssembly exports for scanning text in the browser.
+//!
+//! The module only depends on the content scanner, so the same ruleset used by the CLI can be
+//! applied client-side, e.g. by a web IDE or an upload form before a file leaves the machine.
+//!
+//! Strings cross the boundary as UTF-8 bytes in linear memory: the host allocates input buffers
+//! with `sensleak_alloc`, calls `sensleak_scan_text`, reads the returned buffer and releases every
+//! buffer with `sensleak_dealloc`. The returned buffer starts with its payload length as a
+//! little-endian `u32`, followed by a JSON object, either `{"leaks": [...]}` or `{"error": "..."}`.
+
+use crate::utils::detect_utils::{decode_content, scan_text};
+use serde_json::json;
+use std::slice;
+
+/// Allocates `len` bytes of linear memory for the host to write an input string into.
+#[no_mangle]
+pub extern "C" fn sensleak_alloc(len: usize) -> *mut u8 {
+    let mut buf = Vec::<u8>::with_capacity(len);
+    let ptr = buf.as_mut_ptr();
+    std::mem::forget(buf);
+    ptr
+}
+
+/// Releases a buffer obtained from `sensleak_alloc` or returned by `sensleak_scan_text`.
+///
+/// # Safety
+///
+/// `ptr` must have been returned by this module for a buffer of exactly `len` bytes, and must not
+/// be used after this call.
+#[no_mangle]
+pub unsafe extern "C" fn sensleak_dealloc(ptr: *mut u8, len: usize) {
+    drop(Vec::from_raw_parts(ptr, 0, len));
+}
+
+/// Scans `content` with the TOML ruleset in `config` and returns the findings as JSON.
+///
+/// The total size of the returned buffer is its `u32` length prefix plus 4.
+///
+/// # Safety
+///
+/// Both `(ptr, len)` pairs must describe initialized memory inside the module's linear memory.
+#[no_mangle]
+pub unsafe extern "C" fn sensleak_scan_text(
+    content_ptr: *const u8,
+    content_len: usize,
+    config_ptr: *const u8,
+    config_len: usize,
+) -> *mut u8 {
+    let content = decode_content(slice::from_raw_parts(content_ptr, content_len));
+    let config = String::from_utf8_lossy(slice::from_raw_parts(config_ptr, config_len));
+
+    let output = match scan_text(&content, &config) {
+        Ok(leaks) => json!({ "leaks": leaks }),
+        Err(err) => json!({ "error": err.to_string() }),
+    }
+    .to_string();
+
+    let mut buf = Vec::with_capacity(output.len() + 4);
+    buf.extend_from_slice(&(output.len() as u32).to_le_bytes());
+    buf.extend_from_slice(output.as_bytes());
+    let mut buf = buf.into_boxed_slice();
+    let ptr = buf.as_mut_ptr();
+    std::mem::forget(buf);
+    ptr
+}